@@ -25,7 +25,7 @@ pub const fn test_data() -> TestData {
         test_enum: TestEnum::UnitVariant,
         seq: &[9, 8, 7, 6, 5, 4, 3, 2, 1],
         option: Some(12),
-        map: Generated1 {
+        map: TestDataMap {
             alpha: TestStruct2 {
                 u16: Some(11),
                 test_struct_3: TestStruct3(999),
@@ -11,7 +11,7 @@ pub struct TestStruct {
     pub test_struct_2: TestStruct2,
 }
 #[derive(Clone, Debug, PartialEq)]
-pub struct Generated1 {
+pub struct TestDataMap {
     pub alpha: TestStruct2,
     pub beta: TestStruct2,
     pub gamma: TestStruct2,
@@ -37,7 +37,7 @@ pub struct TestData {
     pub test_enum: TestEnum,
     pub seq: &'static [u8],
     pub option: Option<u8>,
-    pub map: Generated1,
+    pub map: TestDataMap,
     pub enum_variants: &'static [TestEnum2],
     pub undefined_vec: &'static [()],
     pub undefined_option: Option<()>,
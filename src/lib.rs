@@ -5,6 +5,21 @@
 mod model;
 mod ser;
 
+pub use model::EnumArg;
+pub use model::FieldType;
+pub use model::NumericWidening;
+pub use model::OptionalTypeArg;
+pub use model::PathArg;
+pub use model::StructArg;
+pub use model::Structured;
+pub use model::StructuredStruct;
+pub use model::StructuredTuple;
+pub use model::TupleArg;
 pub use ser::Enums;
+pub use ser::IntegerType;
+pub use ser::MapStrategy;
 pub use ser::Serializer;
 pub use ser::Structs;
+pub use ser::TypeHint;
+pub use ser::TypeOverride;
+pub use ser::TypePass;
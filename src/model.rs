@@ -4,23 +4,33 @@ use crate::ser::Error;
 use linked_hash_map::LinkedHashMap;
 use std::borrow::{Borrow, Cow};
 use std::cell::BorrowMutError;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructuredStructBuilder {
     fields: LinkedHashMap<Cow<'static, str>, FieldType>,
-    target_len: usize,
+    keys: HashMap<Cow<'static, str>, String>,
+    skipped: LinkedHashMap<Cow<'static, str>, Option<String>>,
+    target_len: Option<usize>,
 }
 
 impl StructuredStructBuilder {
-    fn new(len: usize) -> Self {
+    fn new(len: Option<usize>) -> Self {
         Self {
-            fields: LinkedHashMap::with_capacity(len),
+            fields: LinkedHashMap::with_capacity(len.unwrap_or(0)),
+            keys: HashMap::new(),
+            skipped: LinkedHashMap::new(),
             target_len: len,
         }
     }
 
     pub fn field(&mut self, name: Cow<'static, str>, field_type: FieldType) -> Result<(), Error> {
-        if self.fields.contains_key(&name) {
+        if let Some(existing_key) = self.keys.get(&name) {
+            Err(Error::message(format!(
+                "Keys \"{}\" and \"{}\" both result in field '{}'.",
+                existing_key, &name, &name
+            )))
+        } else if self.fields.contains_key(&name) {
             Err(Error::Bug(format!(
                 "Attempt at adding the same field twice: '{}'.",
                 &name
@@ -31,34 +41,120 @@ impl StructuredStructBuilder {
         }
     }
 
-    pub fn build(self) -> Result<StructuredStruct, Error> {
-        let actual_len = self.fields.len();
-        let expected_len = self.target_len;
-        if actual_len != expected_len {
+    /// Adds a field whose name differs from the key that it was created from.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the field.
+    /// * `key`: The original key.
+    /// * `field_type`: The type of the field.
+    pub fn renamed_field(
+        &mut self,
+        name: Cow<'static, str>,
+        key: String,
+        field_type: FieldType,
+    ) -> Result<(), Error> {
+        if self.fields.contains_key(&name) {
+            let existing_key = self.keys.get(&name).map_or(name.as_ref(), String::as_str);
             Err(Error::message(format!(
-                "Expected {} fields, but found {}.",
-                expected_len, actual_len
+                "Keys \"{}\" and \"{}\" both result in field '{}'.",
+                existing_key, key, &name
             )))
         } else {
-            Ok(StructuredStruct::new(self.fields))
+            self.keys.insert(name.clone(), key);
+            self.fields.insert(name, field_type);
+            Ok(())
+        }
+    }
+
+    /// Registers a field that was skipped. Skipped fields are not counted towards the length and
+    /// end up as inferred optional fields without a value.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the field.
+    /// * `key`: The original key, if it differs from the name.
+    pub fn skipped_field(&mut self, name: Cow<'static, str>, key: Option<String>) {
+        self.skipped.insert(name, key);
+    }
+
+    /// Builds the structure. If a length was provided when creating the builder, the number of
+    /// fields must match it.
+    pub fn build(self) -> Result<StructuredStruct, Error> {
+        let actual_len = self.fields.len();
+        if let Some(expected_len) = self.target_len {
+            if actual_len != expected_len {
+                return Err(Error::message(format!(
+                    "Expected {} fields, but found {}.",
+                    expected_len, actual_len
+                )));
+            }
         }
+
+        let mut structure = StructuredStruct {
+            fields: self.fields,
+            keys: self.keys,
+            inferred: HashSet::new(),
+        };
+        for (name, key) in self.skipped {
+            if structure.fields.contains_key(&name) {
+                return Err(Error::Bug(format!(
+                    "Field '{}' was both serialized and skipped.",
+                    &name
+                )));
+            }
+            if let Some(key) = key {
+                structure.keys.insert(name.clone(), key);
+            }
+            structure.inferred.insert(name.clone());
+            structure
+                .fields
+                .insert(name, FieldType::Option(None.into()));
+        }
+        Ok(structure)
     }
 }
 
+/// The fields of a generated struct or struct variant.
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructuredStruct {
     fields: LinkedHashMap<Cow<'static, str>, FieldType>,
+    keys: HashMap<Cow<'static, str>, String>,
+    /// The fields that were inferred to be optional, because they were missing in some instances.
+    /// The values of these fields need to be wrapped in `Some(..)`.
+    inferred: HashSet<Cow<'static, str>>,
 }
 
 impl StructuredStruct {
-    pub fn new(fields: LinkedHashMap<Cow<'static, str>, FieldType>) -> Self {
-        Self { fields }
+    pub(crate) fn new(fields: LinkedHashMap<Cow<'static, str>, FieldType>) -> Self {
+        Self {
+            fields,
+            keys: HashMap::new(),
+            inferred: HashSet::new(),
+        }
+    }
+
+    /// Determines whether the provided field was inferred to be optional.
+    pub fn is_inferred(&self, field: &str) -> bool {
+        self.inferred.contains(field)
     }
 
+    /// The fields, by name, in the order in which they are declared.
     pub fn fields(&self) -> &LinkedHashMap<Cow<'static, str>, FieldType> {
         &self.fields
     }
 
+    pub(crate) fn fields_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (&Cow<'static, str>, &mut FieldType)> {
+        self.fields.iter_mut()
+    }
+
+    /// Retrieves the original key of a field, if it differs from the name of the field.
+    pub fn key(&self, field: &str) -> Option<&str> {
+        self.keys.get(field).map(String::as_str)
+    }
+
     fn keys_to_string<'a>(keys: impl Iterator<Item = &'a Cow<'static, str>>) -> String {
         let mut out = String::from("[");
         let mut keys: Vec<&Cow<'static, str>> = keys.collect();
@@ -76,7 +172,12 @@ impl StructuredStruct {
         out
     }
 
-    pub fn merge(&mut self, other: &StructuredStruct) -> Result<(), Error> {
+    /// Merges the other structure into this one. Both structures must have the same fields.
+    pub(crate) fn merge(
+        &mut self,
+        other: &StructuredStruct,
+        widening: NumericWidening,
+    ) -> Result<(), Error> {
         if self.fields.keys().count() != other.fields.keys().count() {
             return Err(Error::message(format!(
                 "Conflicting fields: {} vs {}.",
@@ -86,10 +187,21 @@ impl StructuredStruct {
         }
 
         for (field, field_type) in &mut self.fields {
+            if self.keys.get(field) != other.keys.get(field) {
+                return Err(Error::message(format!(
+                    "Conflicting keys for field '{}': \"{}\" vs \"{}\".",
+                    field,
+                    self.keys.get(field).map_or(field.as_ref(), String::as_str),
+                    other.keys.get(field).map_or(field.as_ref(), String::as_str),
+                )));
+            }
+
             if let Some(other_field_type) = other.fields.get(field) {
-                field_type.merge(other_field_type).map_err(|msg| {
-                    msg.message_prepend(format!("Could not merge field \"{}\".", field))
-                })?;
+                field_type
+                    .merge_with(other_field_type, widening)
+                    .map_err(|msg| {
+                        msg.message_prepend(format!("Could not merge field \"{}\".", field))
+                    })?;
             } else {
                 return Err(Error::message(format!(
                     "Conflicting fields: {} vs {}.",
@@ -102,7 +214,85 @@ impl StructuredStruct {
         Ok(())
     }
 
-    pub fn builder(len: usize) -> StructuredStructBuilder {
+    /// Merges the other structure into this one, inferring optional fields for the fields that
+    /// are missing in either one.
+    pub(crate) fn merge_inferring(
+        &mut self,
+        other: &StructuredStruct,
+        widening: NumericWidening,
+    ) -> Result<(), Error> {
+        for field in other.fields.keys() {
+            let key = self.keys.get(field).map_or(field.as_ref(), String::as_str);
+            let other_key = other.keys.get(field).map_or(field.as_ref(), String::as_str);
+            if self.fields.contains_key(field) && key != other_key {
+                return Err(Error::message(format!(
+                    "Conflicting keys for field '{}': \"{}\" vs \"{}\".",
+                    field, key, other_key,
+                )));
+            }
+        }
+
+        // Fields that are missing in the other structure.
+        for (field, field_type) in &mut self.fields {
+            if !other.fields.contains_key(field)
+                && !self.inferred.contains(field)
+                && !matches!(field_type, FieldType::Option(_))
+            {
+                *field_type = FieldType::Option(Some(Box::new(field_type.clone())).into());
+                self.inferred.insert(field.clone());
+            }
+        }
+
+        for (field, other_field_type) in &other.fields {
+            let other_inferred = other.inferred.contains(field);
+            let Some(field_type) = self.fields.get_mut(field) else {
+                // A field that is missing in this structure.
+                let field_type = match other_field_type {
+                    FieldType::Option(_) => other_field_type.clone(),
+                    _ => FieldType::Option(Some(Box::new(other_field_type.clone())).into()),
+                };
+                if other_inferred || !matches!(other_field_type, FieldType::Option(_)) {
+                    self.inferred.insert(field.clone());
+                }
+                if let Some(key) = other.keys.get(field) {
+                    self.keys.insert(field.clone(), key.clone());
+                }
+                self.fields.insert(field.clone(), field_type);
+                continue;
+            };
+
+            let inferred = self.inferred.contains(field);
+            let result = match (inferred, other_inferred) {
+                (true, false) if !matches!(other_field_type, FieldType::Option(_)) => field_type
+                    .merge_with(
+                        &FieldType::Option(Some(Box::new(other_field_type.clone())).into()),
+                        widening,
+                    ),
+                (false, true) if !matches!(field_type, FieldType::Option(_)) => {
+                    let mut wrapped = FieldType::Option(Some(Box::new(field_type.clone())).into());
+                    let result = wrapped.merge_with(other_field_type, widening);
+                    *field_type = wrapped;
+                    self.inferred.insert(field.clone());
+                    result
+                }
+                (true, false) => {
+                    // The other structure has an actual `Option`, so there is no need for
+                    // wrapping the values in `Some(..)`.
+                    self.inferred.remove(field);
+                    field_type.merge_with(other_field_type, widening)
+                }
+                _ => field_type.merge_with(other_field_type, widening),
+            };
+            result.map_err(|msg| {
+                msg.message_prepend(format!("Could not merge field \"{}\".", field))
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Creates a builder for a structure with the provided number of fields, if known.
+    pub(crate) fn builder(len: Option<usize>) -> StructuredStructBuilder {
         StructuredStructBuilder::new(len)
     }
 }
@@ -110,51 +300,65 @@ impl StructuredStruct {
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructuredTupleBuilder {
     elements: Vec<FieldType>,
-    target_len: usize,
+    target_len: Option<usize>,
 }
 
 impl StructuredTupleBuilder {
-    fn new(len: usize) -> Self {
+    fn new(len: Option<usize>) -> Self {
         Self {
-            elements: Vec::with_capacity(len),
+            elements: Vec::with_capacity(len.unwrap_or(0)),
             target_len: len,
         }
     }
 
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
     pub fn element(&mut self, field_type: FieldType) -> Result<(), Error> {
         self.elements.push(field_type);
         Ok(())
     }
 
+    /// Builds the structure. If a length was provided when creating the builder, the number of
+    /// elements must match it.
     pub fn build(self) -> Result<StructuredTuple, Error> {
         let actual_len = self.elements.len();
-        let expected_len = self.target_len;
-        if actual_len != expected_len {
-            Err(Error::message(format!(
+        match self.target_len {
+            Some(expected_len) if actual_len != expected_len => Err(Error::message(format!(
                 "Expected {} elements, but found {}.",
                 expected_len, actual_len
-            )))
-        } else {
-            Ok(StructuredTuple::new(self.elements))
+            ))),
+            _ => Ok(StructuredTuple::new(self.elements)),
         }
     }
 }
 
+/// The elements of a generated tuple struct or tuple variant.
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructuredTuple {
     elements: Vec<FieldType>,
 }
 
 impl StructuredTuple {
-    pub fn new(elements: Vec<FieldType>) -> Self {
+    pub(crate) fn new(elements: Vec<FieldType>) -> Self {
         Self { elements }
     }
 
+    /// The types of the elements.
     pub fn elements(&self) -> &[FieldType] {
         &self.elements
     }
 
-    pub fn merge(&mut self, other: &StructuredTuple) -> Result<(), Error> {
+    pub(crate) fn elements_mut(&mut self) -> &mut [FieldType] {
+        &mut self.elements
+    }
+
+    pub(crate) fn merge(
+        &mut self,
+        other: &StructuredTuple,
+        widening: NumericWidening,
+    ) -> Result<(), Error> {
         if self.elements.len() != other.elements.len() {
             return Err(Error::message(format!(
                 "Conflicting tuple lengths: {} vs {}.",
@@ -166,31 +370,85 @@ impl StructuredTuple {
         for (index, (field_type, other_field_type)) in
             &mut self.elements.iter_mut().zip(&other.elements).enumerate()
         {
-            field_type.merge(other_field_type).map_err(|msg| {
-                msg.message_prepend(format!("Could not merge element with index {index}."))
-            })?;
+            field_type
+                .merge_with(other_field_type, widening)
+                .map_err(|msg| {
+                    msg.message_prepend(format!("Could not merge element with index {index}."))
+                })?;
         }
 
         Ok(())
     }
 
-    pub fn builder(len: usize) -> StructuredTupleBuilder {
+    /// Creates a builder for a structure with the provided number of elements, if known.
+    pub(crate) fn builder(len: Option<usize>) -> StructuredTupleBuilder {
         StructuredTupleBuilder::new(len)
     }
 
-    pub fn take(self) -> Vec<FieldType> {
+    pub(crate) fn take(self) -> Vec<FieldType> {
         self.elements
     }
 }
 
+/// The structure of a generated struct or enum variant.
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Structured {
+    /// A struct with named fields, e.g. `struct Sample { first: u8 }`.
     Struct(StructuredStruct),
+    /// A tuple struct, e.g. `struct Sample(u8)`.
     Tuple(StructuredTuple),
+    /// A unit struct, e.g. `struct Sample`.
     Unit,
 }
 
 impl Structured {
+    /// The types of the fields or elements, in the order in which they are declared.
+    pub fn field_types(&self) -> impl Iterator<Item = &FieldType> {
+        let (fields, elements) = match self {
+            Structured::Struct(inner) => (Some(inner.fields.values()), None),
+            Structured::Tuple(inner) => (None, Some(inner.elements.iter())),
+            Structured::Unit => (None, None),
+        };
+        fields
+            .into_iter()
+            .flatten()
+            .chain(elements.into_iter().flatten())
+    }
+
+    /// A copy of this structure in which the integers that are narrowed have the type that they
+    /// are written as (see [`FieldType::resolve_int()`]).
+    pub(crate) fn resolved(&self) -> Structured {
+        let mut structure = self.clone();
+        for field_type in structure.field_types_mut() {
+            field_type.resolve_int();
+        }
+        structure
+    }
+
+    /// The mutable types of the fields or elements, in the order in which they are declared.
+    pub(crate) fn field_types_mut(&mut self) -> impl Iterator<Item = &mut FieldType> {
+        let (fields, elements) = match self {
+            Structured::Struct(inner) => (
+                Some(inner.fields.iter_mut().map(|(_, field_type)| field_type)),
+                None,
+            ),
+            Structured::Tuple(inner) => (None, Some(inner.elements.iter_mut())),
+            Structured::Unit => (None, None),
+        };
+        fields
+            .into_iter()
+            .flatten()
+            .chain(elements.into_iter().flatten())
+    }
+
+    /// Determines whether any of the fields or elements refers to the struct or enum with the
+    /// provided name.
+    pub fn references(&self, name: &str) -> bool {
+        self.field_types()
+            .any(|field_type| field_type.references(name))
+    }
+
     fn conflicting_types(a: &str, b: &str) -> Result<(), Error> {
         Err(Error::message(format!(
             "Conflicting types: {} vs {}.",
@@ -198,10 +456,28 @@ impl Structured {
         )))
     }
 
-    pub fn merge(&mut self, other: &Structured) -> Result<(), Error> {
+    pub(crate) fn merge(&mut self, other: &Structured) -> Result<(), Error> {
+        self.merge_with(other, MergeOptions::default())
+    }
+
+    /// Merges the other structure into this one.
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: The other structure.
+    /// * `options`: The options for merging.
+    pub(crate) fn merge_with(
+        &mut self,
+        other: &Structured,
+        options: MergeOptions,
+    ) -> Result<(), Error> {
+        let widening = options.widening;
         match self {
             Structured::Struct(inner) => match other {
-                Structured::Struct(other_inner) => inner.merge(other_inner),
+                Structured::Struct(other_inner) if options.infer_optional => {
+                    inner.merge_inferring(other_inner, widening)
+                }
+                Structured::Struct(other_inner) => inner.merge(other_inner, widening),
                 Structured::Tuple(_) => {
                     Self::conflicting_types("Structured::Struct", "Structured::Tuple")
                 }
@@ -213,7 +489,7 @@ impl Structured {
                 Structured::Struct(_) => {
                     Self::conflicting_types("Structured::Tuple", "Structured::Struct")
                 }
-                Structured::Tuple(other_inner) => inner.merge(other_inner),
+                Structured::Tuple(other_inner) => inner.merge(other_inner, widening),
                 Structured::Unit => {
                     Self::conflicting_types("Structured::Tuple", "Structured::Unit")
                 }
@@ -231,6 +507,231 @@ impl Structured {
     }
 }
 
+/// A lookup function that is generated for a struct that wraps a table of string keys and values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lookup {
+    /// A binary search over the entries, which are sorted by key.
+    Sorted,
+    /// A perfect hash function, using the displacements that were computed during serialization.
+    PerfectHash,
+}
+
+/// The ways in which differing numeric types may be widened into a single type when merging the
+/// types of multiple instances. Widening never loses precision: types that can not be widened
+/// into a type that holds all values of both result in an error.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NumericWidening {
+    /// Whether numbers may be widened into a larger type of the same kind, e.g. `u8` into `u16` or
+    /// `f32` into `f64`.
+    pub larger: bool,
+    /// Whether unsigned and signed integers may be widened into a signed integer that holds both,
+    /// e.g. `u8` and `i8` into `i16`.
+    pub signedness: bool,
+    /// Whether integers may be widened into a float that represents all their values exactly,
+    /// e.g. `u16` into `f32` and `i32` into `f64`.
+    pub floats: bool,
+}
+
+impl NumericWidening {
+    /// No widening: numeric types must match exactly.
+    pub const NONE: Self = Self {
+        larger: false,
+        signedness: false,
+        floats: false,
+    };
+
+    /// All kinds of widening.
+    pub const ALL: Self = Self {
+        larger: true,
+        signedness: true,
+        floats: true,
+    };
+
+    /// Determines the type into which both provided types can be widened.
+    fn widen(self, a: Numeric, b: Numeric) -> Result<Numeric, MergeError> {
+        use Numeric::*;
+        if a == b {
+            return Ok(a);
+        }
+
+        let (allowed, widened) = match (a, b) {
+            (Unsigned(a), Unsigned(b)) => (self.larger, Some(Unsigned(a.max(b)))),
+            (Signed(a), Signed(b)) => (self.larger, Some(Signed(a.max(b)))),
+            (Float(a), Float(b)) => (self.larger, Some(Float(a.max(b)))),
+            (Unsigned(unsigned), Signed(signed)) | (Signed(signed), Unsigned(unsigned)) => {
+                // The signed type needs an additional bit to hold all unsigned values.
+                let bits = Numeric::BITS
+                    .into_iter()
+                    .find(|bits| *bits > unsigned && *bits >= signed);
+                (self.signedness, bits.map(Signed))
+            }
+            (Float(float), int) | (int, Float(float)) => {
+                let bits = Numeric::FLOAT_BITS.into_iter().find(|bits| {
+                    *bits >= float && Numeric::mantissa_bits(*bits) >= int.magnitude_bits()
+                });
+                (self.floats, bits.map(Float))
+            }
+        };
+
+        match widened {
+            _ if !allowed => Err(MergeError::ConflictingFieldTypes),
+            Some(widened) => Ok(widened),
+            None => Err(MergeError::PrecisionLoss),
+        }
+    }
+}
+
+/// A numeric type, with its size in bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Numeric {
+    Unsigned(u32),
+    Signed(u32),
+    Float(u32),
+}
+
+impl Numeric {
+    const BITS: [u32; 5] = [8, 16, 32, 64, 128];
+    const FLOAT_BITS: [u32; 2] = [32, 64];
+
+    fn of(field_type: &FieldType) -> Option<Self> {
+        use Numeric::*;
+        Some(match field_type {
+            FieldType::U8 => Unsigned(8),
+            FieldType::U16 => Unsigned(16),
+            FieldType::U32 => Unsigned(32),
+            FieldType::U64 => Unsigned(64),
+            FieldType::U128 => Unsigned(128),
+            FieldType::I8 => Signed(8),
+            FieldType::I16 => Signed(16),
+            FieldType::I32 => Signed(32),
+            FieldType::I64 => Signed(64),
+            FieldType::I128 => Signed(128),
+            FieldType::F32 => Float(32),
+            FieldType::F64 => Float(64),
+            FieldType::Int(range) => return Self::of(&range.field_type()),
+            _ => return None,
+        })
+    }
+
+    /// The number of bits that are needed for the magnitude of the values of an integer type.
+    fn magnitude_bits(self) -> u32 {
+        match self {
+            Numeric::Unsigned(bits) => bits,
+            Numeric::Signed(bits) => bits - 1,
+            Numeric::Float(bits) => bits,
+        }
+    }
+
+    /// The number of bits of precision of a float type, including the implicit leading bit.
+    fn mantissa_bits(bits: u32) -> u32 {
+        if bits == 32 {
+            f32::MANTISSA_DIGITS
+        } else {
+            f64::MANTISSA_DIGITS
+        }
+    }
+
+    fn field_type(self) -> FieldType {
+        match self {
+            Numeric::Unsigned(8) => FieldType::U8,
+            Numeric::Unsigned(16) => FieldType::U16,
+            Numeric::Unsigned(32) => FieldType::U32,
+            Numeric::Unsigned(64) => FieldType::U64,
+            Numeric::Unsigned(_) => FieldType::U128,
+            Numeric::Signed(8) => FieldType::I8,
+            Numeric::Signed(16) => FieldType::I16,
+            Numeric::Signed(32) => FieldType::I32,
+            Numeric::Signed(64) => FieldType::I64,
+            Numeric::Signed(_) => FieldType::I128,
+            Numeric::Float(32) => FieldType::F32,
+            Numeric::Float(_) => FieldType::F64,
+        }
+    }
+}
+
+/// The range of the values of an integer, from which the smallest type that holds all values is
+/// determined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IntRange {
+    /// The smallest value, or zero if there are no negative values.
+    min: i128,
+    /// The largest value, or zero if there are no positive values.
+    max: u128,
+}
+
+impl IntRange {
+    /// Creates a range of a single signed value.
+    pub(crate) fn signed(value: i128) -> Self {
+        Self {
+            min: value.min(0),
+            max: value.max(0).unsigned_abs(),
+        }
+    }
+
+    /// Creates a range of a single unsigned value.
+    pub(crate) fn unsigned(value: u128) -> Self {
+        Self { min: 0, max: value }
+    }
+
+    /// Determines whether all values in the range fit into the provided integer type.
+    pub(crate) fn fits(&self, field_type: &FieldType) -> bool {
+        match Numeric::of(field_type) {
+            Some(Numeric::Unsigned(bits)) => {
+                self.min == 0 && (bits == 128 || self.max < 1u128 << bits)
+            }
+            Some(Numeric::Signed(bits)) => {
+                let limit = 1u128 << (bits - 1);
+                self.min.unsigned_abs() <= limit && self.max < limit
+            }
+            Some(Numeric::Float(_)) | None => false,
+        }
+    }
+
+    fn merge(&mut self, other: &IntRange) -> Result<(), MergeError> {
+        let merged = Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        };
+        if merged.min < 0 && merged.max > i128::MAX.unsigned_abs() {
+            return Err(MergeError::Unrepresentable);
+        }
+        *self = merged;
+        Ok(())
+    }
+
+    /// The smallest integer type that holds all values in the range. The type is only signed if
+    /// the range contains negative values.
+    pub fn field_type(&self) -> FieldType {
+        let bits = if self.min < 0 {
+            Numeric::BITS.into_iter().find(|bits| {
+                let limit = 1u128 << (bits - 1);
+                self.min.unsigned_abs() <= limit && self.max < limit
+            })
+        } else {
+            Numeric::BITS
+                .into_iter()
+                .find(|bits| *bits == 128 || self.max < 1u128 << bits)
+        };
+
+        let bits = bits.unwrap_or(128);
+        if self.min < 0 {
+            Numeric::Signed(bits).field_type()
+        } else {
+            Numeric::Unsigned(bits).field_type()
+        }
+    }
+}
+
+/// Options for merging the types of multiple instances.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MergeOptions {
+    /// Whether fields that are missing in either struct should be made optional (see
+    /// [`StructuredStruct::merge_inferring()`]), instead of resulting in an error.
+    pub infer_optional: bool,
+    /// The ways in which differing numeric types may be widened.
+    pub widening: NumericWidening,
+}
+
 macro_rules! field_type_arg {
     ($name:ident ( $type:ty ), $ref_type:ty) => {
         #[derive(Clone, Debug, PartialEq)]
@@ -257,6 +758,7 @@ macro_rules! field_type_arg {
 field_type_arg!(StructArg(Cow<'static, str>), str);
 field_type_arg!(EnumArg(Cow<'static, str>), str);
 field_type_arg!(TupleArg(Vec<FieldType>), [FieldType]);
+field_type_arg!(PathArg(Cow<'static, str>), str);
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct OptionalTypeArg(Option<Box<FieldType>>);
@@ -269,10 +771,14 @@ impl OptionalTypeArg {
         self.0.as_ref().map(Borrow::borrow)
     }
 
-    fn merge(&mut self, other: &OptionalTypeArg) -> Result<(), MergeError> {
+    fn merge(
+        &mut self,
+        other: &OptionalTypeArg,
+        widening: NumericWidening,
+    ) -> Result<(), MergeError> {
         if let Some(field_type) = self.0.as_mut().map(|v| v.as_mut()) {
             if let Some(other_field_type) = other.value() {
-                return field_type.merge_internal(other_field_type);
+                return field_type.merge_internal(other_field_type, widening);
             }
         } else {
             if let Some(other_field_type) = other.value() {
@@ -289,32 +795,51 @@ impl From<Option<Box<FieldType>>> for OptionalTypeArg {
     }
 }
 
+/// The type of a field, a tuple element, a sequence element, etc. in the generated code.
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum FieldType {
     Bool,
     U8,
     U16,
     U32,
     U64,
+    U128,
     I8,
     I16,
     I32,
     I64,
+    I128,
     F32,
     F64,
     Char,
     Str,
     Unit,
+    /// A generated struct, by name.
     Struct(StructArg),
+    /// A generated enum, by name.
     Enum(EnumArg),
     Tuple(TupleArg),
+    /// A slice of elements of the provided type, if it is known.
     Sequence(OptionalTypeArg),
+    /// An option of the provided type, if it is known.
     Option(OptionalTypeArg),
+    /// An array of the provided length, with elements of the provided type, if it is known.
+    Array(OptionalTypeArg, usize),
+    /// An existing type, by path.
+    Path(PathArg),
+    /// An integer that is narrowed to the smallest type that holds all of its values. This is only
+    /// used while the types are inferred; the types that are inspected have the integer type that
+    /// holds the values instead.
+    #[doc(hidden)]
+    Int(IntRange),
 }
 
 enum MergeError {
     ConflictingFieldTypes,
     ConflictingArguments,
+    PrecisionLoss,
+    Unrepresentable,
 }
 
 impl From<MergeError> for Result<(), MergeError> {
@@ -324,7 +849,11 @@ impl From<MergeError> for Result<(), MergeError> {
 }
 
 impl FieldType {
-    fn merge_internal(&mut self, other: &FieldType) -> Result<(), MergeError> {
+    fn merge_internal(
+        &mut self,
+        other: &FieldType,
+        widening: NumericWidening,
+    ) -> Result<(), MergeError> {
         macro_rules! handle_primitive {
             ($variant:expr) => {{
                 if other != &$variant {
@@ -333,21 +862,34 @@ impl FieldType {
             }};
         };
 
+        if let (FieldType::Int(range), FieldType::Int(other_range)) = (&mut *self, other) {
+            return range.merge(other_range);
+        }
+
+        if let (Some(numeric), Some(other_numeric)) = (Numeric::of(self), Numeric::of(other)) {
+            *self = widening.widen(numeric, other_numeric)?.field_type();
+            return Ok(());
+        }
+
         match self {
             FieldType::Bool => handle_primitive!(FieldType::Bool),
             FieldType::U8 => handle_primitive!(FieldType::U8),
             FieldType::U16 => handle_primitive!(FieldType::U16),
             FieldType::U32 => handle_primitive!(FieldType::U32),
             FieldType::U64 => handle_primitive!(FieldType::U64),
+            FieldType::U128 => handle_primitive!(FieldType::U128),
             FieldType::I8 => handle_primitive!(FieldType::I8),
             FieldType::I16 => handle_primitive!(FieldType::I16),
             FieldType::I32 => handle_primitive!(FieldType::I32),
             FieldType::I64 => handle_primitive!(FieldType::I64),
+            FieldType::I128 => handle_primitive!(FieldType::I128),
             FieldType::F32 => handle_primitive!(FieldType::F32),
             FieldType::F64 => handle_primitive!(FieldType::F64),
             FieldType::Char => handle_primitive!(FieldType::Char),
             FieldType::Str => handle_primitive!(FieldType::Str),
             FieldType::Unit => handle_primitive!(FieldType::Unit),
+            // Integers with a range are merged above, with other numbers or with each other.
+            FieldType::Int(_) => return MergeError::ConflictingFieldTypes.into(),
             FieldType::Struct(arg) => {
                 if let FieldType::Struct(other_arg) = other {
                     if other_arg != arg {
@@ -374,7 +916,7 @@ impl FieldType {
                         return MergeError::ConflictingArguments.into();
                     }
                     for (entry, other_entry) in entries.iter_mut().zip(other_entries.iter()) {
-                        entry.merge_internal(other_entry)?;
+                        entry.merge_internal(other_entry, widening)?;
                     }
                 } else {
                     return MergeError::ConflictingFieldTypes.into();
@@ -382,26 +924,162 @@ impl FieldType {
             }
             FieldType::Sequence(arg) => {
                 return if let FieldType::Sequence(other_arg) = other {
-                    arg.merge(other_arg)
+                    arg.merge(other_arg, widening)
                 } else {
                     MergeError::ConflictingFieldTypes.into()
                 }
             }
             FieldType::Option(arg) => {
                 return if let FieldType::Option(other_arg) = other {
-                    arg.merge(other_arg)
+                    arg.merge(other_arg, widening)
                 } else {
                     MergeError::ConflictingFieldTypes.into()
                 }
             }
+            FieldType::Array(arg, len) => {
+                return match other {
+                    FieldType::Array(other_arg, other_len) if other_len == len => {
+                        arg.merge(other_arg, widening)
+                    }
+                    FieldType::Array(..) => MergeError::ConflictingArguments.into(),
+                    _ => MergeError::ConflictingFieldTypes.into(),
+                }
+            }
+            FieldType::Path(arg) => {
+                if let FieldType::Path(other_arg) = other {
+                    if other_arg != arg {
+                        return MergeError::ConflictingArguments.into();
+                    }
+                } else {
+                    return MergeError::ConflictingFieldTypes.into();
+                }
+            }
         }
 
         Ok(())
     }
 
-    pub fn merge(&mut self, other: &FieldType) -> Result<(), Error> {
+    pub(crate) fn merge(&mut self, other: &FieldType) -> Result<(), Error> {
+        self.merge_with(other, NumericWidening::NONE)
+    }
+
+    /// Calls the visitor for the type arguments of this type (recursively) and then for this type.
+    pub fn visit(&self, visitor: &mut impl FnMut(&FieldType)) {
+        match self {
+            FieldType::Tuple(arg) => {
+                for inner in arg.0.iter() {
+                    inner.visit(visitor);
+                }
+            }
+            FieldType::Sequence(arg) | FieldType::Option(arg) | FieldType::Array(arg, _) => {
+                if let Some(inner) = arg.0.as_ref() {
+                    inner.visit(visitor);
+                }
+            }
+            _ => {}
+        }
+        visitor(self);
+    }
+
+    /// Calls the visitor for the type arguments of this type (recursively) and then for this type,
+    /// allowing the visitor to replace them.
+    pub fn visit_mut(&mut self, visitor: &mut impl FnMut(&mut FieldType)) {
+        match self {
+            FieldType::Tuple(arg) => {
+                for inner in arg.0.iter_mut() {
+                    inner.visit_mut(visitor);
+                }
+            }
+            FieldType::Sequence(arg) | FieldType::Option(arg) | FieldType::Array(arg, _) => {
+                if let Some(inner) = arg.0.as_mut() {
+                    inner.visit_mut(visitor);
+                }
+            }
+            _ => {}
+        }
+        visitor(self);
+    }
+
+    /// The name of the struct or enum that this type refers to directly, if any.
+    pub fn type_name(&self) -> Option<&str> {
+        match self {
+            FieldType::Struct(arg) => Some(arg.value()),
+            FieldType::Enum(arg) => Some(arg.value()),
+            _ => None,
+        }
+    }
+
+    /// Determines whether this type refers to the struct or enum with the provided name, either
+    /// directly or through its type arguments.
+    pub fn references(&self, name: &str) -> bool {
+        let mut found = false;
+        self.visit(&mut |field_type| found |= field_type.type_name() == Some(name));
+        found
+    }
+
+    /// Determines whether this type has a type argument that is not known (e.g. the element type
+    /// of a sequence that is always empty), either directly or through its type arguments.
+    pub(crate) fn is_indeterminate(&self) -> bool {
+        let mut found = false;
+        self.visit(&mut |field_type| {
+            found |= matches!(
+                field_type,
+                FieldType::Sequence(arg) | FieldType::Option(arg) | FieldType::Array(arg, _)
+                    if arg.value().is_none()
+            )
+        });
+        found
+    }
+
+    /// Fills the unknown type arguments of this type with those of the provided type, where both
+    /// types have the same structure.
+    pub(crate) fn fill(&mut self, other: &FieldType) {
+        match (self, other) {
+            (FieldType::Sequence(arg), FieldType::Sequence(other_arg))
+            | (FieldType::Option(arg), FieldType::Option(other_arg))
+            | (FieldType::Array(arg, _), FieldType::Array(other_arg, _)) => {
+                match (arg.0.as_mut(), other_arg.value()) {
+                    (Some(inner), Some(other_inner)) => inner.fill(other_inner),
+                    (None, Some(other_inner)) => arg.0 = Some(Box::new(other_inner.clone())),
+                    _ => {}
+                }
+            }
+            (FieldType::Tuple(arg), FieldType::Tuple(other_arg)) => {
+                for (inner, other_inner) in arg.0.iter_mut().zip(other_arg.value()) {
+                    inner.fill(other_inner);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Replaces the integers that are narrowed with the smallest integer types that hold their
+    /// values, including those in the type arguments.
+    pub(crate) fn resolve_int(&mut self) {
+        self.visit_mut(&mut |field_type| {
+            if let FieldType::Int(range) = field_type {
+                *field_type = range.field_type();
+            }
+        });
+    }
+
+    /// Replaces all references to the struct or enum with the provided name.
+    pub(crate) fn rename(&mut self, name: &str, new_name: &str) {
+        self.visit_mut(&mut |field_type| match field_type {
+            FieldType::Struct(arg) if arg.0 == name => arg.0 = new_name.to_string().into(),
+            FieldType::Enum(arg) if arg.0 == name => arg.0 = new_name.to_string().into(),
+            _ => {}
+        });
+    }
+
+    /// Merges the other type into this one, widening numeric types where allowed.
+    pub(crate) fn merge_with(
+        &mut self,
+        other: &FieldType,
+        widening: NumericWidening,
+    ) -> Result<(), Error> {
         use MergeError::*;
-        self.merge_internal(other).map_err(|err| match err {
+        self.merge_internal(other, widening).map_err(|err| match err {
             ConflictingFieldTypes => Error::message(format!(
                 "Found conflicting field types: {:?} vs {:?}.",
                 self, other
@@ -410,6 +1088,14 @@ impl FieldType {
                 "Found conflicting arguments: {:?} vs {:?}.",
                 self, other
             )),
+            PrecisionLoss => Error::message(format!(
+                "Found numeric types that can not be merged without loss of precision: {:?} vs {:?}.",
+                self, other
+            )),
+            Unrepresentable => Error::message(format!(
+                "Found integers that can not be represented by a single type: {:?} vs {:?}.",
+                self, other
+            )),
         })
     }
 }
@@ -1,28 +1,49 @@
+mod builtin;
 mod dummy;
+mod ident;
+mod inference;
+mod literal;
+mod lookup;
 mod map_serializer;
+mod pass;
+mod path;
 mod seq_serializer;
 mod struct_serializer;
 mod struct_util;
 mod struct_variant_serializer;
+mod trace;
 mod tuple_serializer;
 mod tuple_struct_serializer;
 mod tuple_util;
 mod tuple_variant_serializer;
+mod type_hint;
+mod type_override;
 
 #[cfg(test)]
 mod test;
 
-use crate::model::{FieldType, Structured};
+use crate::model::{
+    FieldType, IntRange, Lookup, MergeOptions, NumericWidening, Structured, StructuredStruct,
+};
+use crate::ser::inference::Number;
 use crate::ser::map_serializer::MapSerializer;
+pub use crate::ser::map_serializer::MapStrategy;
+pub use crate::ser::pass::TypePass;
+use crate::ser::path::DataPath;
 use crate::ser::seq_serializer::SeqSerializer;
 use crate::ser::struct_serializer::StructSerializer;
 use crate::ser::struct_variant_serializer::StructVariantSerializer;
 use crate::ser::tuple_serializer::TupleSerializer;
 use crate::ser::tuple_struct_serializer::TupleStructSerializer;
 use crate::ser::tuple_variant_serializer::TupleVariantSerializer;
+pub use crate::ser::type_hint::IntegerType;
+pub use crate::ser::type_hint::TypeHint;
+use crate::ser::type_override::ConstructorCall;
+pub use crate::ser::type_override::TypeOverride;
 use linked_hash_map::LinkedHashMap;
 use serde::Serialize;
 use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 use std::io::Write;
 
@@ -80,15 +101,48 @@ impl serde::ser::Error for Error {
     }
 }
 
+impl serde::de::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: Display,
+    {
+        Error::message(msg.to_string())
+    }
+}
+
 type CodeWriterResult = std::io::Result<()>;
 
 pub struct CodeWriter<W> {
     out: W,
+    captures: Vec<String>,
+    /// Whether markers for resolving optional fields should be written (see [`inference`]).
+    markers: bool,
+    /// Whether the code that is not captured is discarded instead of written to the output (see
+    /// [`Serializer::collect()`]).
+    discard: bool,
 }
 
 impl<W> CodeWriter<W> {
     pub fn new(out: W) -> Self {
-        Self { out }
+        Self {
+            out,
+            captures: Vec::new(),
+            markers: false,
+            discard: false,
+        }
+    }
+
+    /// Starts capturing the written code, instead of writing it to the output.
+    ///
+    /// Captures can be nested. Every call must be matched by a call to
+    /// [`end_capture()`](Self::end_capture).
+    pub fn begin_capture(&mut self) {
+        self.captures.push(String::new());
+    }
+
+    /// Ends the most recently started capture and returns the code that was written since.
+    pub fn end_capture(&mut self) -> String {
+        self.captures.pop().unwrap_or_default()
     }
 }
 
@@ -97,7 +151,29 @@ where
     W: Write,
 {
     pub fn write(&mut self, text: &str) -> CodeWriterResult {
-        self.out.write_all(text.as_bytes())
+        if let Some(capture) = self.captures.last_mut() {
+            capture.push_str(text);
+            Ok(())
+        } else if self.discard {
+            Ok(())
+        } else {
+            self.out.write_all(text.as_bytes())
+        }
+    }
+
+    pub fn str_literal(&mut self, value: &str) -> CodeWriterResult {
+        self.write(&literal::str_literal(value))
+    }
+
+    pub fn char_literal(&mut self, value: char) -> CodeWriterResult {
+        self.write(&literal::char_literal(value))
+    }
+
+    pub fn float_literal<F>(&mut self, value: F, suffix: bool) -> CodeWriterResult
+    where
+        F: literal::Float,
+    {
+        self.write(&literal::float_literal(value, suffix))
     }
 
     pub fn struct_begin(&mut self) -> CodeWriterResult {
@@ -117,6 +193,47 @@ where
         self.write(", ")
     }
 
+    /// Marks the beginning of an instance of a struct or a struct variant.
+    pub fn instance_begin_marker(&mut self, name: &str, variant: Option<&str>) -> CodeWriterResult {
+        if self.markers {
+            self.write(&inference::instance_begin(name, variant))?;
+        }
+        Ok(())
+    }
+
+    /// Marks the end of the instance that was most recently marked as begun.
+    pub fn instance_end_marker(&mut self) -> CodeWriterResult {
+        if self.markers {
+            self.write(inference::INSTANCE_END)?;
+        }
+        Ok(())
+    }
+
+    /// Marks the end of the instance that was most recently marked as begun, if it was written
+    /// through a constructor.
+    pub fn call_end_marker(&mut self) -> CodeWriterResult {
+        if self.markers {
+            self.write(inference::CALL_END)?;
+        }
+        Ok(())
+    }
+
+    /// Marks the beginning of the value of a field.
+    pub fn field_begin_marker(&mut self, field_name: &str) -> CodeWriterResult {
+        if self.markers {
+            self.write(&inference::field_begin(field_name))?;
+        }
+        Ok(())
+    }
+
+    /// Marks the end of the value of the field that was most recently marked as begun.
+    pub fn field_end_marker(&mut self) -> CodeWriterResult {
+        if self.markers {
+            self.write(inference::FIELD_END)?;
+        }
+        Ok(())
+    }
+
     pub fn tuple_begin(&mut self) -> CodeWriterResult {
         self.write("(")
     }
@@ -152,7 +269,23 @@ where
 
 /// A container for `struct`s that were generated during serialization.
 #[derive(Clone, Default, Debug)]
-pub struct Structs(LinkedHashMap<Cow<'static, str>, Structured>);
+pub struct Structs {
+    structures: LinkedHashMap<Cow<'static, str>, Structured>,
+    lookups: LinkedHashMap<Cow<'static, str>, Lookup>,
+    options: MergeOptions,
+    /// The types that are written instead of the types that are replaced by existing types, by
+    /// name. The replaced types are not declared.
+    type_paths: HashMap<String, String>,
+    /// The names of the structs that were generated for the instances of each struct, by the name
+    /// of the struct as provided by Serde (see [`Serializer::with_name_mangling()`]).
+    instances: HashMap<String, Vec<Cow<'static, str>>>,
+    /// The current names of the structs that were renamed when a conflicting instance was found,
+    /// by their former names. Former names are not used again.
+    mangled_renames: HashMap<String, String>,
+    /// Whether the types may have changed since they were checked in strict mode, in which case
+    /// they can not be written (see [`Serializer::check_types()`]).
+    unchecked: bool,
+}
 
 struct CodeWriteContext<'a, W>
 where
@@ -160,14 +293,24 @@ where
 {
     writer: &'a mut CodeWriter<W>,
     pub_fields: bool,
+    /// The types that are written instead of overridden types, by name.
+    type_paths: &'a HashMap<String, String>,
 }
 
 impl<'a, W> CodeWriteContext<'a, W>
 where
     W: Write,
 {
-    fn new(writer: &'a mut CodeWriter<W>, pub_fields: bool) -> Self {
-        Self { writer, pub_fields }
+    fn new(
+        writer: &'a mut CodeWriter<W>,
+        pub_fields: bool,
+        type_paths: &'a HashMap<String, String>,
+    ) -> Self {
+        Self {
+            writer,
+            pub_fields,
+            type_paths,
+        }
     }
 }
 
@@ -187,12 +330,21 @@ impl CodeWrite for Structured {
             Structured::Struct(inner) => {
                 writer.struct_begin()?;
                 for (field_name, field_type) in inner.fields() {
+                    if let Some(key) = inner.key(field_name) {
+                        writer.write("#[doc = ")?;
+                        writer.str_literal(&format!("Key: `{key}`"))?;
+                        writer.write("] ")?;
+                    }
                     if ctx.pub_fields {
                         writer.struct_entry_begin(&format!("pub {}", field_name))?;
                     } else {
                         writer.struct_entry_begin(field_name)?;
                     }
-                    field_type.write(CodeWriteContext::new(writer, ctx.pub_fields))?;
+                    field_type.write(CodeWriteContext::new(
+                        writer,
+                        ctx.pub_fields,
+                        ctx.type_paths,
+                    ))?;
                     writer.struct_entry_end()?;
                 }
                 writer.struct_end()?;
@@ -204,7 +356,11 @@ impl CodeWrite for Structured {
                     if ctx.pub_fields {
                         writer.write("pub ")?;
                     }
-                    field_type.write(CodeWriteContext::new(writer, ctx.pub_fields))?;
+                    field_type.write(CodeWriteContext::new(
+                        writer,
+                        ctx.pub_fields,
+                        ctx.type_paths,
+                    ))?;
                     writer.tuple_entry_end()?;
                 }
                 writer.tuple_end()?;
@@ -227,22 +383,34 @@ impl CodeWrite for FieldType {
             FieldType::U16 => writer.write("u16"),
             FieldType::U32 => writer.write("u32"),
             FieldType::U64 => writer.write("u64"),
+            FieldType::U128 => writer.write("u128"),
             FieldType::I8 => writer.write("i8"),
             FieldType::I16 => writer.write("i16"),
             FieldType::I32 => writer.write("i32"),
             FieldType::I64 => writer.write("i64"),
+            FieldType::I128 => writer.write("i128"),
             FieldType::F32 => writer.write("f32"),
             FieldType::F64 => writer.write("f64"),
             FieldType::Char => writer.write("char"),
             FieldType::Str => writer.write("&'static str"),
             FieldType::Unit => writer.write("()"),
-            FieldType::Struct(arg) => writer.write(arg.value()),
-            FieldType::Enum(arg) => writer.write(arg.value()),
+            FieldType::Struct(arg) => match ctx.type_paths.get(arg.value()) {
+                Some(path) => writer.write(path),
+                None => writer.write(arg.value()),
+            },
+            FieldType::Enum(arg) => match ctx.type_paths.get(arg.value()) {
+                Some(path) => writer.write(path),
+                None => writer.write(arg.value()),
+            },
             FieldType::Tuple(arg) => {
                 writer.tuple_begin()?;
                 for field_type in arg.value() {
                     writer.tuple_entry_begin()?;
-                    field_type.write(CodeWriteContext::new(writer, ctx.pub_fields))?;
+                    field_type.write(CodeWriteContext::new(
+                        writer,
+                        ctx.pub_fields,
+                        ctx.type_paths,
+                    ))?;
                     writer.tuple_entry_end()?;
                 }
                 writer.tuple_end()
@@ -250,20 +418,107 @@ impl CodeWrite for FieldType {
             FieldType::Sequence(arg) => {
                 writer.write("&'static ")?;
                 writer.seq_begin()?;
-                arg.value()
-                    .write(CodeWriteContext::new(writer, ctx.pub_fields))?;
+                arg.value().write(CodeWriteContext::new(
+                    writer,
+                    ctx.pub_fields,
+                    ctx.type_paths,
+                ))?;
                 writer.seq_end()
             }
             FieldType::Option(arg) => {
                 writer.write("Option<")?;
-                arg.value()
-                    .write(CodeWriteContext::new(writer, ctx.pub_fields))?;
+                arg.value().write(CodeWriteContext::new(
+                    writer,
+                    ctx.pub_fields,
+                    ctx.type_paths,
+                ))?;
                 writer.write(">")
             }
+            FieldType::Array(arg, len) => {
+                writer.write("[")?;
+                arg.value().write(CodeWriteContext::new(
+                    writer,
+                    ctx.pub_fields,
+                    ctx.type_paths,
+                ))?;
+                writer.write(&format!("; {len}]"))
+            }
+            FieldType::Path(arg) => writer.write(arg.value()),
+            FieldType::Int(range) => range.field_type().write(CodeWriteContext::new(
+                writer,
+                ctx.pub_fields,
+                ctx.type_paths,
+            )),
+        }
+    }
+}
+
+/// The error for a value that does not match the type hint for its path.
+fn type_hint_mismatch(hint: &TypeHint, path: &str) -> Error {
+    Error::message(format!(
+        "The value at '{path}' does not match the type hint {hint:?}."
+    ))
+}
+
+/// The error for writing types in strict mode that were not checked (see
+/// [`Serializer::check_types()`]).
+fn unchecked_types() -> std::io::Error {
+    std::io::Error::other(
+        "The types were not checked in strict mode. Call `Serializer::check_types()` after all \
+         values are serialized, before the types are written.",
+    )
+}
+
+/// Collects the type hints that convert the values at the provided path to the provided type, as
+/// far as hints can convert them: integers to the integer type and sequences to arrays, including
+/// those in the type arguments.
+fn pinned_type_hints(field_type: &FieldType, path: String, hints: &mut Vec<(String, TypeHint)>) {
+    match field_type {
+        FieldType::Array(arg, len) => {
+            hints.push((path.clone(), TypeHint::Array(*len)));
+            if let Some(inner) = arg.value() {
+                pinned_type_hints(inner, format!("{path}[]"), hints);
+            }
+        }
+        FieldType::Sequence(arg) => {
+            if let Some(inner) = arg.value() {
+                pinned_type_hints(inner, format!("{path}[]"), hints);
+            }
+        }
+        // A hint for an optional value applies to the value inside `Some`.
+        FieldType::Option(arg) => {
+            if let Some(inner) = arg.value() {
+                pinned_type_hints(inner, path, hints);
+            }
+        }
+        FieldType::Tuple(arg) => {
+            for (index, inner) in arg.value().iter().enumerate() {
+                pinned_type_hints(inner, format!("{path}.{index}"), hints);
+            }
+        }
+        _ => {
+            if let Some(integer_type) = IntegerType::of(field_type) {
+                hints.push((path, TypeHint::Integer(integer_type)));
+            }
         }
     }
 }
 
+/// Writes the provided type to a string.
+///
+/// # Arguments
+///
+/// * `field_type`: The type.
+/// * `type_paths`: The types that are written instead of overridden types, by name.
+fn type_string(
+    field_type: &FieldType,
+    type_paths: &HashMap<String, String>,
+) -> Result<String, Error> {
+    let mut writer = CodeWriter::new(Vec::new());
+    field_type.write(CodeWriteContext::new(&mut writer, false, type_paths))?;
+    String::from_utf8(writer.out).map_err(|err| Error::Bug(err.to_string()))
+}
+
 impl CodeWrite for Option<&FieldType> {
     fn write<W>(&self, ctx: CodeWriteContext<W>) -> CodeWriterResult
     where
@@ -278,43 +533,330 @@ impl Structs {
     pub(crate) fn merge(
         &mut self,
         name: &Cow<'static, str>,
-        structure: Structured,
+        mut structure: Structured,
     ) -> Result<(), Error> {
-        if let Some(existing_structure) = self.0.get_mut(name) {
+        self.update_renamed(&mut structure);
+        if let Some(existing_structure) = self.structures.get_mut(name) {
             existing_structure
-                .merge(&structure)
+                .merge_with(&structure, self.options)
                 .map_err(|err| err.message_prepend(format!("Error merging struct \"{}\".", &name)))
         } else {
-            self.0.insert(name.clone(), structure);
+            self.structures.insert(name.clone(), structure);
             Ok(())
         }
     }
 
+    /// Merges the provided structure into the struct with the provided name or, if the structures
+    /// conflict, into a struct with a mangled name (see [`Serializer::with_name_mangling()`]).
+    ///
+    /// If the structure conflicts with all instances so far, every instance is named after the
+    /// types that distinguish it, so the existing structs may be renamed.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the struct, as provided by Serde.
+    /// * `structure`: The structure of the instance.
+    /// * `taken`: Determines whether a name is taken by a type that is not a generated struct.
+    ///
+    /// Returns the name of the struct that the structure was merged into, along with the former
+    /// and the new names of the renamed structs.
+    pub(crate) fn merge_mangled(
+        &mut self,
+        name: Cow<'static, str>,
+        mut structure: Structured,
+        taken: impl Fn(&str) -> bool,
+    ) -> Result<(Cow<'static, str>, Renames), Error> {
+        self.update_renamed(&mut structure);
+        let mut names = self
+            .instances
+            .get(name.as_ref())
+            .cloned()
+            .unwrap_or_default();
+        if names.is_empty() && self.structures.contains_key(&name) {
+            names.push(name.clone());
+        }
+        for existing in &names {
+            let fits = self.structures.get(existing).is_some_and(|existing| {
+                existing
+                    .clone()
+                    .merge_with(&structure, self.options)
+                    .is_ok()
+            });
+            if fits {
+                let existing = existing.clone();
+                self.merge(&existing, structure)?;
+                return Ok((existing, Vec::new()));
+            }
+        }
+        if names.is_empty() {
+            self.structures.insert(name.clone(), structure);
+            self.instances.insert(name.to_string(), vec![name.clone()]);
+            return Ok((name, Vec::new()));
+        }
+
+        let mut structures: Vec<&Structured> = names
+            .iter()
+            .filter_map(|existing| self.structures.get(existing))
+            .collect();
+        structures.push(&structure);
+        let mut new_names: Vec<String> = Vec::with_capacity(structures.len());
+        for (index, base) in mangled_names(&name, &structures).into_iter().enumerate() {
+            // Former names and the names of the other instances are not used, so that the names in
+            // the code that was already written still refer to the same instances.
+            let unavailable = |candidate: &str| {
+                new_names.iter().any(|new_name| new_name == candidate)
+                    || names
+                        .iter()
+                        .enumerate()
+                        .any(|(other, existing)| other != index && existing == candidate)
+                    || self.mangled_renames.contains_key(candidate)
+            };
+            let mut candidate = base.clone();
+            let mut counter = 1;
+            while unavailable(&candidate) {
+                counter += 1;
+                candidate = format!("{base}{counter}");
+            }
+            let ours = candidate == name || names.iter().any(|existing| *existing == candidate);
+            if !ours && (self.structures.contains_key(candidate.as_str()) || taken(&candidate)) {
+                return Err(Error::message(format!(
+                    "The name '{candidate}' for an instance of struct '{name}' is already taken by \
+                     another type."
+                )));
+            }
+            new_names.push(candidate);
+        }
+
+        let renames: Vec<(String, String)> = names
+            .iter()
+            .zip(&new_names)
+            .filter(|(existing, new_name)| existing != new_name)
+            .map(|(existing, new_name)| (existing.to_string(), new_name.clone()))
+            .collect();
+        for (existing, new_name) in &renames {
+            self.rename(existing, new_name);
+            for renamed in self.mangled_renames.values_mut() {
+                if renamed == existing {
+                    renamed.clone_from(new_name);
+                }
+            }
+            self.mangled_renames
+                .insert(existing.clone(), new_name.clone());
+        }
+        let new_names: Vec<Cow<'static, str>> = new_names.into_iter().map(Cow::Owned).collect();
+        let mangled = new_names.last().cloned().unwrap_or_default();
+        self.structures.insert(mangled.clone(), structure);
+        self.instances.insert(name.to_string(), new_names);
+        Ok((mangled, renames))
+    }
+
+    /// Updates the references in the provided structure to the structs that were renamed since it
+    /// was created (see [`merge_mangled()`](Self::merge_mangled)).
+    pub(crate) fn update_renamed(&self, structure: &mut Structured) {
+        for field_type in structure.field_types_mut() {
+            for (name, new_name) in &self.mangled_renames {
+                field_type.rename(name, new_name);
+            }
+        }
+    }
+
+    /// Determines whether the provided name is, or was, that of a struct that was generated for an
+    /// instance of another struct (see [`merge_mangled()`](Self::merge_mangled)).
+    pub(crate) fn is_mangled(&self, name: &str) -> bool {
+        let current = self
+            .instances
+            .iter()
+            .any(|(base, names)| base != name && names.iter().any(|other| other == name));
+        current || (self.mangled_renames.contains_key(name) && !self.instances.contains_key(name))
+    }
+
+    /// The current names of the structs that were renamed when a conflicting instance was found,
+    /// by their former names.
+    pub(crate) fn mangled_renames(&self) -> &HashMap<String, String> {
+        &self.mangled_renames
+    }
+
+    /// Retrieves the structure of the struct with the provided name, with the types that its
+    /// fields are written as.
+    pub fn get(&self, name: &str) -> Option<Structured> {
+        self.structure(name).map(Structured::resolved)
+    }
+
+    /// Iterates over the names and structures of the structs, in the order in which they are
+    /// written. Structs that are replaced by existing types (see
+    /// [`Serializer::with_type_override()`]) are included.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Structured)> {
+        self.structures()
+            .map(|(name, structure)| (name, structure.resolved()))
+    }
+
+    /// Retrieves the structure of the struct with the provided name, as it is inferred.
+    pub(crate) fn structure(&self, name: &str) -> Option<&Structured> {
+        self.structures.get(name)
+    }
+
+    /// Iterates over the names and structures of the structs, as they are inferred.
+    pub(crate) fn structures(&self) -> impl Iterator<Item = (&str, &Structured)> {
+        self.structures
+            .iter()
+            .map(|(name, structure)| (name.as_ref(), structure))
+    }
+
+    /// Renames the struct with the provided name, if any, and all references to it. Returns whether
+    /// the struct exists.
+    fn rename(&mut self, name: &str, new_name: &str) -> bool {
+        let exists = self.structures.contains_key(name);
+        self.structures = rename_key(std::mem::take(&mut self.structures), name, new_name);
+        self.lookups = rename_key(std::mem::take(&mut self.lookups), name, new_name);
+        if let Some(names) = self.instances.remove(name) {
+            self.instances.insert(new_name.to_string(), names);
+        }
+        for names in self.instances.values_mut() {
+            for instance in names.iter_mut().filter(|instance| *instance == name) {
+                *instance = Cow::Owned(new_name.to_string());
+            }
+        }
+        if let Some(type_path) = self.type_paths.remove(name) {
+            self.type_paths.insert(new_name.to_string(), type_path);
+        }
+        for (_, structure) in self.structures.iter_mut() {
+            for field_type in structure.field_types_mut() {
+                field_type.rename(name, new_name);
+            }
+        }
+        exists
+    }
+
+    /// Removes the struct with the provided name. Returns whether the struct existed.
+    fn remove(&mut self, name: &str) -> bool {
+        self.lookups.remove(name);
+        self.type_paths.remove(name);
+        self.structures.remove(name).is_some()
+    }
+
+    /// The name of a struct that refers to the type with the provided name, if any.
+    fn referrer(&self, name: &str) -> Option<&str> {
+        self.iter()
+            .find(|(_, structure)| structure.references(name))
+            .map(|(referrer, _)| referrer)
+    }
+
+    /// Registers a lookup function for a struct that wraps a key/value table.
+    pub(crate) fn add_lookup(&mut self, name: Cow<'static, str>, lookup: Lookup) {
+        self.lookups.insert(name, lookup);
+    }
+
     /// Write the struct declarations to the provided output.
     ///
+    /// In strict mode, this fails unless the types were checked with
+    /// [`Serializer::check_types()`] after they last changed.
+    ///
     /// # Arguments
     ///
     /// * `out`: The output.
     pub fn write(&self, out: &mut impl Write) -> std::io::Result<()> {
+        if self.unchecked {
+            return Err(unchecked_types());
+        }
         let mut writer = CodeWriter::new(out);
-        for (name, structure) in self.0.iter() {
+        for (name, structure) in self.structures.iter() {
+            if self.type_paths.contains_key(name.as_ref()) {
+                continue;
+            }
             writer.write("#[derive(Clone, Debug, PartialEq)] pub struct ")?;
             writer.write(name)?;
-            structure.write(CodeWriteContext::new(&mut writer, true))?;
+            structure.write(CodeWriteContext::new(&mut writer, true, &self.type_paths))?;
 
             match structure {
                 Structured::Struct(_) => {}
                 Structured::Tuple(_) => writer.write(";")?,
                 Structured::Unit => writer.write(";")?,
             }
+
+            if let Some(lookup) = self.lookups.get(name) {
+                lookup::write_lookup(&mut writer, name, lookup, structure, &self.type_paths)?;
+            }
         }
         Ok(())
     }
 }
 
+/// The former and the new names of renamed structs.
+type Renames = Vec<(String, String)>;
+
+/// Names the conflicting instances of a struct after the types of the fields or elements that
+/// differ between them or, if the fields themselves differ, after the types of all fields, e.g.
+/// `WrapperU8` and `WrapperPalette` for `Wrapper`.
+fn mangled_names(name: &str, structures: &[&Structured]) -> Vec<String> {
+    let members: Vec<Vec<(Cow<str>, &FieldType)>> = structures
+        .iter()
+        .map(|structure| match structure {
+            Structured::Struct(structure) => structure
+                .fields()
+                .iter()
+                .map(|(field, field_type)| (Cow::Borrowed(field.as_ref()), field_type))
+                .collect(),
+            Structured::Tuple(structure) => structure
+                .elements()
+                .iter()
+                .enumerate()
+                .map(|(index, field_type)| (Cow::Owned(index.to_string()), field_type))
+                .collect(),
+            Structured::Unit => Vec::new(),
+        })
+        .collect();
+    let same_fields = members.windows(2).all(|pair| {
+        pair[0].len() == pair[1].len() && pair[0].iter().zip(&pair[1]).all(|(a, b)| a.0 == b.0)
+    });
+    let differs = |index: usize| {
+        members
+            .iter()
+            .any(|fields| fields[index].1 != members[0][index].1)
+    };
+
+    members
+        .iter()
+        .map(|fields| {
+            let mut mangled = name.to_string();
+            for (index, (_, field_type)) in fields.iter().enumerate() {
+                if !same_fields || differs(index) {
+                    mangled.push_str(&ident::type_name(field_type));
+                }
+            }
+            mangled
+        })
+        .collect()
+}
+
+/// Replaces the provided key of a map, keeping the order of the entries.
+fn rename_key<V>(
+    map: LinkedHashMap<Cow<'static, str>, V>,
+    key: &str,
+    new_key: &str,
+) -> LinkedHashMap<Cow<'static, str>, V> {
+    map.into_iter()
+        .map(|(name, value)| {
+            if name == key {
+                (Cow::Owned(new_key.to_string()), value)
+            } else {
+                (name, value)
+            }
+        })
+        .collect()
+}
+
 /// A container for `enum`s that were generated during serialization.
 #[derive(Clone, Default, Debug)]
-pub struct Enums(LinkedHashMap<Cow<'static, str>, LinkedHashMap<Cow<'static, str>, Structured>>);
+pub struct Enums {
+    enums: LinkedHashMap<Cow<'static, str>, LinkedHashMap<Cow<'static, str>, Structured>>,
+    options: MergeOptions,
+    /// The types that are written instead of the types that are replaced by existing types, by
+    /// name. The replaced types are not declared.
+    type_paths: HashMap<String, String>,
+    /// Whether the types may have changed since they were checked in strict mode, in which case
+    /// they can not be written (see [`Serializer::check_types()`]).
+    unchecked: bool,
+}
 
 impl Enums {
     pub(crate) fn merge(
@@ -323,11 +865,16 @@ impl Enums {
         variant: &Cow<'static, str>,
         structure: Structured,
     ) -> Result<(), Error> {
-        if let Some(existing_enum) = self.0.get_mut(name) {
+        if let Some(existing_enum) = self.enums.get_mut(name) {
             if let Some(existing_structure) = existing_enum.get_mut(variant) {
-                existing_structure.merge(&structure).map_err(|err| {
-                    err.message_prepend(format!("Error merging variant \"{}::{}\".", name, variant))
-                })
+                existing_structure
+                    .merge_with(&structure, self.options)
+                    .map_err(|err| {
+                        err.message_prepend(format!(
+                            "Error merging variant \"{}::{}\".",
+                            name, variant
+                        ))
+                    })
             } else {
                 existing_enum.insert(variant.clone(), structure);
                 Ok(())
@@ -335,25 +882,107 @@ impl Enums {
         } else {
             let mut new_enum = LinkedHashMap::new();
             new_enum.insert(variant.clone(), structure);
-            self.0.insert(name.clone(), new_enum);
+            self.enums.insert(name.clone(), new_enum);
             Ok(())
         }
     }
 
+    /// Iterates over the names of the enums, in the order in which they are written. Enums that are
+    /// replaced by existing types (see [`Serializer::with_type_override()`]) are included.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.enums.keys().map(|name| name.as_ref())
+    }
+
+    /// Iterates over the names and structures of the variants of the enum with the provided name,
+    /// if any, with the types that their fields are written as.
+    pub fn variants(&self, name: &str) -> impl Iterator<Item = (&str, Structured)> {
+        self.variant_structures(name)
+            .map(|(variant, structure)| (variant, structure.resolved()))
+    }
+
+    /// Iterates over the names and structures of the variants of the enum with the provided name,
+    /// if any, as they are inferred.
+    pub(crate) fn variant_structures(
+        &self,
+        name: &str,
+    ) -> impl Iterator<Item = (&str, &Structured)> {
+        self.enums
+            .get(name)
+            .into_iter()
+            .flatten()
+            .map(|(variant, structure)| (variant.as_ref(), structure))
+    }
+
+    /// Renames the enum with the provided name, if any, and all references to it. Returns whether
+    /// the enum exists.
+    fn rename(&mut self, name: &str, new_name: &str) -> bool {
+        let exists = self.enums.contains_key(name);
+        self.enums = rename_key(std::mem::take(&mut self.enums), name, new_name);
+        if let Some(type_path) = self.type_paths.remove(name) {
+            self.type_paths.insert(new_name.to_string(), type_path);
+        }
+        for (_, variants) in self.enums.iter_mut() {
+            for (_, structure) in variants.iter_mut() {
+                for field_type in structure.field_types_mut() {
+                    field_type.rename(name, new_name);
+                }
+            }
+        }
+        exists
+    }
+
+    /// Removes the enum with the provided name. Returns whether the enum existed.
+    fn remove(&mut self, name: &str) -> bool {
+        self.type_paths.remove(name);
+        self.enums.remove(name).is_some()
+    }
+
+    /// The name of an enum variant (e.g. `Event::Spawn`) that refers to the type with the provided
+    /// name, if any.
+    fn referrer(&self, name: &str) -> Option<String> {
+        self.names().find_map(|enum_name| {
+            self.variant_structures(enum_name)
+                .find(|(_, structure)| structure.references(name))
+                .map(|(variant, _)| format!("{enum_name}::{variant}"))
+        })
+    }
+
+    /// Retrieves the structure of the provided variant, with the types that its fields are written
+    /// as.
+    pub fn get(&self, name: &str, variant: &str) -> Option<Structured> {
+        self.variant(name, variant).map(Structured::resolved)
+    }
+
+    /// Retrieves the structure of the provided variant, as it is inferred.
+    pub(crate) fn variant(&self, name: &str, variant: &str) -> Option<&Structured> {
+        self.enums
+            .get(name)
+            .and_then(|variants| variants.get(variant))
+    }
+
     /// Write the enum declarations to the provided output.
     ///
+    /// In strict mode, this fails unless the types were checked with
+    /// [`Serializer::check_types()`] after they last changed.
+    ///
     /// # Arguments
     ///
     /// * `out`: The output.
     pub fn write(&self, out: &mut impl Write) -> std::io::Result<()> {
+        if self.unchecked {
+            return Err(unchecked_types());
+        }
         let mut writer = CodeWriter::new(out);
-        for (name, variants) in self.0.iter() {
+        for (name, variants) in self.enums.iter() {
+            if self.type_paths.contains_key(name.as_ref()) {
+                continue;
+            }
             writer.write("#[derive(Clone, Debug, PartialEq)] pub enum ")?;
             writer.write(name)?;
             writer.struct_begin()?;
             for (variant, structure) in variants {
                 writer.write(variant)?;
-                structure.write(CodeWriteContext::new(&mut writer, false))?;
+                structure.write(CodeWriteContext::new(&mut writer, false, &self.type_paths))?;
                 writer.write(", ")?;
             }
             writer.struct_end()?;
@@ -444,155 +1073,1857 @@ pub struct Serializer<W> {
     writer: CodeWriter<W>,
     structs: Structs,
     enums: Enums,
-    generated_struct_seed: usize,
+    /// The generated types (structs and enums) that enclose the value that is currently being
+    /// serialized. If this is empty, the type of the value can not be inferred from the generated
+    /// type declarations.
+    enclosing_types: Vec<EnclosingType>,
+    /// The names that were given to structs that are generated from maps.
+    map_names: HashSet<String>,
+    /// The names that were reserved for maps whose name is not decided on yet (see
+    /// [`reserve_map_name()`](Self::reserve_map_name)).
+    reserved_map_names: HashSet<String>,
+    /// The names of the types that were provided by Serde (after renaming), which are not used for
+    /// structs that are generated from maps.
+    serde_names: HashSet<String>,
+    /// The names of the structs that are generated from the maps at each position.
+    map_positions: HashMap<MapPosition, Cow<'static, str>>,
+    map_naming: Option<Box<MapNaming>>,
+    path: DataPath,
+    map_strategy: MapStrategy,
+    map_strategies: HashMap<String, MapStrategy>,
+    /// The number of compound values (sequences, structs, etc.) that enclose the value that is
+    /// currently being serialized.
+    compound_depth: usize,
+    narrow_integers: bool,
+    sequence_enums: bool,
+    mangle_names: bool,
+    /// The names of the structs with mangled names that occur in the output, which can not change
+    /// anymore (see [`with_name_mangling()`](Self::with_name_mangling)).
+    written_names: HashSet<String>,
+    type_overrides: HashMap<String, TypeOverride>,
+    /// The names of the types to which a built-in override was applied, which can not be generated
+    /// anymore (see [`Shape`](type_override::Shape)).
+    builtin_uses: HashSet<String>,
+    /// The names of renamed types, by the name that is provided by Serde (see
+    /// [`rename_type()`](Self::rename_type)).
+    renames: HashMap<String, String>,
+    /// The names of the enums that are generated for the heterogeneous sequences at each position.
+    sequence_enum_positions: HashMap<MapPosition, Cow<'static, str>>,
+    type_hints: HashMap<String, TypeHint>,
+    /// The type hints for fields, by the name of the enclosing type (e.g. `Event::Spawn`) and the
+    /// name of the field.
+    field_type_hints: HashMap<(String, String), TypeHint>,
+    /// The types of the fields that were rewritten by a type pass, by the name of the enclosing type
+    /// and the name of the field. The values of these fields are converted to these types, instead
+    /// of being merged into them (see [`run_pass()`](Self::run_pass)).
+    pinned_field_types: HashMap<(String, String), FieldType>,
+    /// The type hints that convert the values of the field with a pinned type that is currently
+    /// being serialized, by path (see [`pinned_type_hints()`]).
+    pinned_type_hints: HashMap<String, TypeHint>,
+    /// The type hint for the value that is currently being serialized, along with its path, until
+    /// it is applied by the serializer for that value.
+    pending_type_hint: Option<(TypeHint, String)>,
+    /// The type hint of a newtype struct, which applies to the value of the newtype struct instead.
+    newtype_type_hint: Option<TypeHint>,
+    /// The number of enclosing types of a value with a [`TypeHint::Path`], whose numbers are not
+    /// marked since their type can not be resolved.
+    unmarked_depth: Option<usize>,
+    /// The variants of the enums that are generated from strings (see [`TypeHint::StringEnum`]), by
+    /// the name of the enum and the string.
+    string_enum_variants: HashMap<String, HashMap<String, String>>,
+    /// The names of the string enums of which variants were written (not collected).
+    written_string_enums: HashSet<String>,
+    /// The names of the string enums that have more variants than allowed, whose strings are
+    /// written as strings instead.
+    string_enum_fallbacks: HashSet<String>,
+    /// The names of the string enums that have more variants than allowed while collecting, which
+    /// fall back to strings once the value is collected.
+    pending_string_enum_fallbacks: HashSet<String>,
+    strict: bool,
+    /// The names of the enums that were traced (see [`trace()`](Self::trace)).
+    traced_enums: HashSet<String>,
+    fallback_types: HashMap<String, FieldType>,
+    /// The path of the first value with an indeterminate type of each field, by the name of the
+    /// enclosing type and the name of the field. Only used in strict mode.
+    indeterminate_fields: HashMap<(String, String), String>,
+}
+
+/// A callback for naming structs that are generated from maps. See
+/// [`Serializer::with_map_naming()`].
+type MapNaming = dyn Fn(&str, &str) -> Option<String>;
+
+/// A generated type that encloses the value that is currently being serialized.
+struct EnclosingType {
+    name: Cow<'static, str>,
+    variant: Option<Cow<'static, str>>,
+    /// Whether the type is generated from a map.
+    map: bool,
+    /// The length of the route (see [`DataPath::route()`]) at the instance of the type, if the
+    /// instance is marked in the code (see [`inference`]).
+    route_start: Option<usize>,
+}
+
+/// The position of a map within the serialized data. All maps at the same position share a single
+/// generated struct, for instance the maps in all elements of a sequence. Likewise, all
+/// heterogeneous sequences at the same position share a single generated enum.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct MapPosition {
+    path: String,
+    /// The nearest enclosing type that is not generated from a map (e.g. `Event::Spawn`).
+    enclosing: String,
 }
 
 impl<W> Serializer<W> {
     pub fn new(output: W) -> Self {
-        Self {
+        let serializer = Self {
             writer: CodeWriter::new(output),
             structs: Default::default(),
             enums: Default::default(),
-            generated_struct_seed: 0,
-        }
+            enclosing_types: Vec::new(),
+            map_names: HashSet::new(),
+            reserved_map_names: HashSet::new(),
+            serde_names: HashSet::new(),
+            map_positions: HashMap::new(),
+            map_naming: None,
+            path: DataPath::default(),
+            map_strategy: MapStrategy::default(),
+            map_strategies: HashMap::new(),
+            compound_depth: 0,
+            narrow_integers: false,
+            sequence_enums: false,
+            mangle_names: false,
+            written_names: HashSet::new(),
+            type_overrides: HashMap::new(),
+            builtin_uses: HashSet::new(),
+            renames: HashMap::new(),
+            sequence_enum_positions: HashMap::new(),
+            type_hints: HashMap::new(),
+            field_type_hints: HashMap::new(),
+            pinned_field_types: HashMap::new(),
+            pinned_type_hints: HashMap::new(),
+            pending_type_hint: None,
+            newtype_type_hint: None,
+            unmarked_depth: None,
+            string_enum_variants: HashMap::new(),
+            written_string_enums: HashSet::new(),
+            string_enum_fallbacks: HashSet::new(),
+            pending_string_enum_fallbacks: HashSet::new(),
+            strict: false,
+            traced_enums: HashSet::new(),
+            fallback_types: HashMap::new(),
+            indeterminate_fields: HashMap::new(),
+        };
+        builtin::type_overrides()
+            .into_iter()
+            .fold(serializer, |serializer, (name, type_override)| {
+                serializer.with_type_override(name, type_override)
+            })
+    }
+
+    /// Sets the [`MapStrategy`] for all maps with string keys, except those for which a strategy
+    /// was set with [`with_map_strategy_at()`](Self::with_map_strategy_at).
+    pub fn with_map_strategy(mut self, strategy: MapStrategy) -> Self {
+        self.map_strategy = strategy;
+        self
+    }
+
+    /// Sets the [`MapStrategy`] for the maps with string keys at the provided path.
+    ///
+    /// The path consists of the names of the fields that lead to the map, separated by dots.
+    /// Elements of sequences and of maps that are written as slices are denoted by `[]` and
+    /// elements of tuples by their index. For instance, `levels[].enemies` refers to the
+    /// `enemies` field of every element in the `levels` sequence. The empty path refers to the
+    /// value that is passed to the serializer.
+    pub fn with_map_strategy_at(mut self, path: impl Into<String>, strategy: MapStrategy) -> Self {
+        self.map_strategies.insert(path.into(), strategy);
+        self
     }
 
-    /// Retrieves a reference to the serialization output.
-    pub fn out(&self) -> &W {
-        &self.writer.out
+    /// Sets a callback for naming the structs that are generated from maps.
+    ///
+    /// By default, such a struct is named after the type that contains the map and the field that
+    /// holds it (e.g. `LevelEnemies` for the `enemies` field of a `Level`). The callback receives
+    /// the path of the map (see [`with_map_strategy_at()`](Self::with_map_strategy_at)) and this
+    /// default name, and returns the name to use instead, if any. If a name is already in use, a
+    /// number is appended to it (e.g. `LevelEnemies2`).
+    pub fn with_map_naming(
+        mut self,
+        naming: impl Fn(&str, &str) -> Option<String> + 'static,
+    ) -> Self {
+        self.map_naming = Some(Box::new(naming));
+        self
+    }
+
+    /// Enables the inference of optional fields.
+    ///
+    /// By default, all instances of a struct must have the same fields. With this option, a field
+    /// that is missing in some instances (e.g. a key that is missing in some of the maps in a
+    /// sequence, or a field that is skipped with `#[serde(skip_serializing_if = "...")]`) results
+    /// in a field of type `Option<T>` instead. The instances that have the field get `Some(..)`
+    /// and the instances that miss it get `None`. Fields that are an `Option` already are left as
+    /// they are.
+    ///
+    /// Since the types are only known once all instances have been seen, the code for every value
+    /// that is passed to the serializer is kept in memory until that value has been serialized
+    /// completely. All instances of a struct should therefore be part of the same value.
+    pub fn with_optional_field_inference(mut self) -> Self {
+        self.writer.markers = true;
+        self.structs.options.infer_optional = true;
+        self.enums.options.infer_optional = true;
+        self
     }
 
-    /// Retrieves e mutable reference to the serialization output.
-    pub fn out_mut(&mut self) -> &mut W {
-        &mut self.writer.out
+    /// Sets the ways in which differing numeric types may be widened into a single type.
+    ///
+    /// By default, the numeric types of all instances of a field must be the same. Data from
+    /// formats like JSON often varies in this respect, for instance if a field holds `3` in one
+    /// instance and `300` or `2.5` in another. With this option, such types are widened into a
+    /// type that holds the values of all instances (see [`NumericWidening`]). Types that can only
+    /// be widened with a loss of precision (e.g. `u64` and `f64`) result in an error.
+    ///
+    /// The literals are written for the widened type (e.g. `3.0` for an integer that is widened
+    /// into a float). Like with [`with_optional_field_inference()`](Self::with_optional_field_inference),
+    /// the code for every value that is passed to the serializer is kept in memory until that
+    /// value has been serialized completely.
+    pub fn with_numeric_widening(mut self, widening: NumericWidening) -> Self {
+        self.writer.markers = true;
+        self.structs.options.widening = widening;
+        self.enums.options.widening = widening;
+        self
+    }
+
+    /// Enables the narrowing of integer types.
+    ///
+    /// By default, integers get the type with which they are serialized. With this option, the
+    /// range of the values of every integer field (or element, etc.) is tracked across all
+    /// instances instead, and the field gets the smallest integer type that holds all these values
+    /// (e.g. `u8` for a `u64` field that only holds values up to 255). The type is only signed if
+    /// there are negative values.
+    pub fn with_integer_narrowing(mut self) -> Self {
+        self.narrow_integers = true;
+        self
     }
 
-    /// Retrieves a reference to the [`Structs`].
-    pub fn structs(&self) -> &Structs {
-        &self.structs
+    /// Enables the generation of enums for heterogeneous sequences.
+    ///
+    /// By default, all elements of a sequence must have the same type. With this option, the
+    /// elements of a sequence are grouped by their type (merging types where possible) and, if
+    /// there is more than one group, an enum with a tuple variant for every group is generated.
+    /// Every element is then wrapped in its variant, e.g. `&[Element::U8(1, ), Element::Str("two",
+    /// ), ]`. The variants are named after the type of the group (e.g. `U8`, `Str`, `Seq` or the
+    /// name of a struct), with a number appended if the name is already taken by a variant with
+    /// another type. The enum is named after the enclosing type and field, followed by `Element`.
+    ///
+    /// All sequences at the same position share the enum, and once a position has an enum, the
+    /// elements of all subsequent sequences at that position are wrapped as well. A homogeneous
+    /// sequence that was serialized before, however, results in conflicting types. Note that the
+    /// code of every element is kept in memory until its sequence has been serialized completely.
+    pub fn with_sequence_enums(mut self) -> Self {
+        self.sequence_enums = true;
+        self
     }
 
-    /// Retrieves a mutable reference to the [`Structs`].
-    pub fn structs_mut(&mut self) -> &mut Structs {
-        &mut self.structs
+    /// Enables the mangling of the names of structs with conflicting instances.
+    ///
+    /// Serde only provides the name of a struct without its type arguments, so the instances of a
+    /// generic struct (e.g. `Wrapper<u8>` and `Wrapper<Palette>`) have conflicting field types. By
+    /// default, this results in an error. With this option, every instance gets a struct of its
+    /// own once a conflicting instance is found, which is named after the original struct and the
+    /// types of the fields that differ between the instances (e.g. `WrapperU8` and
+    /// `WrapperPalette`). If the fields themselves differ, the types of all fields are used
+    /// instead, and a number is appended if the instances can still not be told apart. A name that
+    /// is taken by another type results in an error. The instances are written with the names of
+    /// the structs they were merged into.
+    ///
+    /// Since the names of the structs change when a conflicting instance is found, the code of
+    /// every value is kept in memory until the value has been serialized completely. If the
+    /// conflicting instances are serialized in separate values, all values must be collected first
+    /// (see [`collect()`](Self::collect)), or the name of a struct that was already written would
+    /// change, which results in an error.
+    pub fn with_name_mangling(mut self) -> Self {
+        self.mangle_names = true;
+        self
     }
 
-    /// Retrieves a reference to the [`Enums`].
-    pub fn enums(&self) -> &Enums {
-        &self.enums
+    /// Uses an existing type instead of generating the struct or enum with the provided name.
+    ///
+    /// The type is not written by [`Structs::write()`] or [`Enums::write()`], references to it use
+    /// the path (or the type) of the override and its values are written as literals with this
+    /// path or through the constructor of the override (see [`TypeOverride`]). The instances of the
+    /// type are still merged as usual, so conflicting instances result in an error. An existing
+    /// override for the name, like a built-in one (see
+    /// [`without_type_override()`](Self::without_type_override)), is replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the type, as provided by Serde (i.e. without generics or modules).
+    /// * `type_override`: The existing type.
+    pub fn with_type_override(
+        mut self,
+        name: impl Into<String>,
+        type_override: TypeOverride,
+    ) -> Self {
+        let name = name.into();
+        let path = type_override.path().to_string();
+        self.structs.type_paths.insert(name.clone(), path.clone());
+        self.enums.type_paths.insert(name.clone(), path);
+        self.type_overrides.insert(name, type_override);
+        self
+    }
+
+    /// Removes the override for the type with the provided name, so that the type is generated
+    /// again.
+    ///
+    /// This also applies to the built-in overrides for the types of the standard library that Serde
+    /// serializes as structs or enums (`Duration`, `SystemTime`, `Range`, `RangeInclusive`,
+    /// `RangeFrom`, `RangeTo`, `PhantomData` and `Result`). These only apply to types with the same
+    /// fields or variants, so a type of the data that is named `Range` but has other fields is
+    /// generated anyway. A type with the same fields, or an enum named `Result` of which only some
+    /// variants are `Ok` and `Err`, needs this method, though.
+    pub fn without_type_override(mut self, name: &str) -> Self {
+        self.structs.type_paths.remove(name);
+        self.enums.type_paths.remove(name);
+        self.type_overrides.remove(name);
+        self
+    }
+
+    /// Sets the type of the values at the provided path (see
+    /// [`with_map_strategy_at()`](Self::with_map_strategy_at)) instead of inferring it from them.
+    ///
+    /// The values are checked against the hint while they are serialized: integers must fit into
+    /// the hinted integer type and sequences must have the hinted array length. Values that do not
+    /// match the hint (e.g. a string for an integer hint) result in an error. A hint at a path
+    /// takes precedence over a hint for a field (see [`with_type_hint()`](Self::with_type_hint)).
+    /// Hints do not apply to the value that is passed to the serializer. A hint for a newtype struct
+    /// (e.g. `struct Meters(u32)`) applies to its value.
+    pub fn with_type_hint_at(mut self, path: impl Into<String>, hint: TypeHint) -> Self {
+        self.type_hints.insert(path.into(), hint);
+        self
+    }
+
+    /// Sets the type of a field instead of inferring it from its values, like
+    /// [`with_type_hint_at()`](Self::with_type_hint_at).
+    ///
+    /// # Arguments
+    ///
+    /// * `type_name`: The name of the struct (e.g. `Frame`) or the name of the enum and the variant
+    ///   (e.g. `Event::Spawn`) that has the field.
+    /// * `field`: The name of the field, or the index of the element for tuple structs and tuple
+    ///   variants.
+    /// * `hint`: The type of the field.
+    pub fn with_type_hint(
+        mut self,
+        type_name: impl Into<String>,
+        field: impl Into<String>,
+        hint: TypeHint,
+    ) -> Self {
+        self.field_type_hints
+            .insert((type_name.into(), field.into()), hint);
+        self
+    }
+
+    /// Sets a fallback type for the values at the provided path (see
+    /// [`with_map_strategy_at()`](Self::with_map_strategy_at)), which provides the type arguments
+    /// that can not be determined from the values, e.g. the element type of a sequence that is
+    /// always empty or the type of an option that is always `None`.
+    ///
+    /// The fallback is the type of the value itself, e.g. `Option<Vec<u8>>` for a field of that
+    /// type, of which only the indeterminate type arguments are used. Without a fallback, these are
+    /// written as `()`.
+    pub fn with_fallback_type_at(mut self, path: impl Into<String>, fallback: FieldType) -> Self {
+        self.fallback_types.insert(path.into(), fallback);
+        self
     }
 
-    /// Retrieves a mutable reference to the [`Enums`].
-    pub fn enums_mut(&mut self) -> &mut Enums {
-        &mut self.enums
+    /// Enables the strict mode, which results in errors instead of types that may not match the
+    /// source data:
+    ///
+    /// * Values of enums that were not traced (see [`trace()`](Self::trace)), since the generated
+    ///   enums would only have the variants that occur in the data.
+    /// * Values of type `u64` or `i64` without a type hint (see
+    ///   [`with_type_hint_at()`](Self::with_type_hint_at)), since Serde serializes `usize` and
+    ///   `isize` as these types.
+    /// * Structs that are generated from maps whose name is already taken, which would get a number
+    ///   appended to it (see [`with_map_naming()`](Self::with_map_naming)).
+    /// * Types with type arguments that can not be determined from the data (see
+    ///   [`with_fallback_type_at()`](Self::with_fallback_type_at)). Since these are only known once
+    ///   all values have been serialized, they are reported by [`check_types()`](Self::check_types).
+    ///
+    /// The errors name the path of the offending value. [`Structs::write()`] and
+    /// [`Enums::write()`] fail until the types are checked.
+    pub fn with_strict_mode(mut self) -> Self {
+        self.strict = true;
+        self.types_changed();
+        self
     }
-}
 
-impl<W> Serializer<W>
-where
-    W: Write,
-{
-    fn write_int<I>(&mut self, value: I) -> Result<(), Error>
+    /// Adds the structs and enums that are found by tracing the [`Deserialize`] implementation of
+    /// `T` to the generated types.
+    ///
+    /// Normally, the generated types only reflect the serialized data: enums only contain the
+    /// variants that occur in the data and sequences and options without any values have an
+    /// element type of `()`. Tracing finds every variant of every enum and the element types of all
+    /// sequences and options, so that the generated types match the source types, regardless of
+    /// the data. This should be called before serializing any data.
+    ///
+    /// Types that are deserialized from any value (e.g. untagged enums) can not be traced. Structs
+    /// and variants that contain maps are not added, since the keys of maps are not known, and
+    /// recursive types can only be traced if they recurse through a sequence, an option or an enum.
+    ///
+    /// [`Deserialize`]: serde::Deserialize
+    pub fn trace<T>(&mut self) -> Result<(), Error>
     where
-        I: itoa::Integer,
+        T: serde::Deserialize<'static>,
     {
-        self.writer.write(itoa::Buffer::new().format(value))?;
+        let traced =
+            trace::trace::<T>().map_err(|err| err.message_prepend("Could not trace type."))?;
+        for (name, structure) in traced.structs {
+            self.serde_names.insert(name.to_string());
+            self.structs.merge(&name, structure)?;
+        }
+        for (name, variants) in traced.enums {
+            for (variant, structure) in variants {
+                self.enums.merge(&name, &variant, structure)?;
+            }
+            self.serde_names.insert(name.to_string());
+            self.traced_enums.insert(name.to_string());
+        }
+        let names: Vec<_> = self.type_overrides.keys().cloned().collect();
+        for name in names {
+            self.update_type_path(&name)?;
+        }
+        self.types_changed();
         Ok(())
     }
 
-    fn write_float<F>(&mut self, value: F) -> Result<(), Error>
-    where
-        F: dtoa::Float,
-    {
-        self.writer.write(dtoa::Buffer::new().format(value))?;
+    /// Merges the structure of a variant into the generated enums.
+    pub(crate) fn merge_variant(
+        &mut self,
+        name: Cow<'static, str>,
+        variant: Cow<'static, str>,
+        mut structure: Structured,
+    ) -> Result<(), Error> {
+        self.structs.update_renamed(&mut structure);
+        self.enums.merge(&name, &variant, structure)
+    }
+
+    /// Updates the type that is written for the overridden type with the provided name, if its
+    /// [`TypeOverride`] has a type template, from the merged structures of the type.
+    fn update_type_path(&mut self, name: &str) -> Result<(), Error> {
+        let Some(type_path) = self.overridden_type(name, Some("()"))? else {
+            return Ok(());
+        };
+        self.structs
+            .type_paths
+            .insert(name.to_string(), type_path.clone());
+        self.enums.type_paths.insert(name.to_string(), type_path);
         Ok(())
     }
-}
 
-impl<'a, W> serde::ser::Serializer for &'a mut Serializer<W>
-where
-    W: Write,
-{
-    type Ok = FieldType;
-    type Error = Error;
-    type SerializeSeq = SeqSerializer<'a, W>;
-    type SerializeTuple = TupleSerializer<'a, W>;
-    type SerializeTupleStruct = TupleStructSerializer<'a, W>;
-    type SerializeTupleVariant = TupleVariantSerializer<'a, W>;
-    type SerializeMap = MapSerializer<'a, W>;
-    type SerializeStruct = StructSerializer<'a, W>;
-    type SerializeStructVariant = StructVariantSerializer<'a, W>;
+    /// Renders the type template of the overridden type with the provided name, if any, from the
+    /// merged structures of the type.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the type.
+    /// * `default`: The type for the variants of an enum that do not occur in the data, if any.
+    ///   Without a default, these result in an error.
+    fn overridden_type(&self, name: &str, default: Option<&str>) -> Result<Option<String>, Error> {
+        let Some(template) = self
+            .type_overrides
+            .get(name)
+            .and_then(TypeOverride::type_template)
+        else {
+            return Ok(None);
+        };
+
+        let type_paths = &self.structs.type_paths;
+        let mut types = HashMap::new();
+        let mut variant_default = None;
+        match self.structs.structure(name) {
+            Some(Structured::Struct(structure)) => {
+                for (field, field_type) in structure.fields() {
+                    types.insert(field.to_string(), type_string(field_type, type_paths)?);
+                }
+            }
+            Some(Structured::Tuple(structure)) => {
+                for (index, field_type) in structure.elements().iter().enumerate() {
+                    types.insert(index.to_string(), type_string(field_type, type_paths)?);
+                }
+            }
+            Some(Structured::Unit) => {}
+            None => {
+                variant_default = default;
+                for (variant, structure) in self.enums.variant_structures(name) {
+                    let field_type = match structure {
+                        Structured::Tuple(structure) if structure.elements().len() == 1 => {
+                            structure.elements()[0].clone()
+                        }
+                        Structured::Tuple(structure) => {
+                            FieldType::Tuple(structure.elements().to_vec().into())
+                        }
+                        _ => FieldType::Unit,
+                    };
+                    types.insert(variant.to_string(), type_string(&field_type, type_paths)?);
+                }
+            }
+        }
 
-    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
-        let out = if v { "true" } else { "false" };
-        self.writer.write(out)?;
-        Ok(FieldType::Bool)
+        let type_path = type_override::fill(template, &types, variant_default)
+            .map_err(|err| err.message_prepend(format!("Invalid type for '{name}'.")))?;
+        Ok(Some(type_path))
     }
 
-    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
-        self.write_int(v)?;
-        Ok(FieldType::I8)
+    /// Collects the types of the provided value, without writing any code.
+    ///
+    /// Types are merged while the data is serialized, so the code of a value that was written
+    /// before a type changed may not match the final type, e.g. if a field became optional in a
+    /// later value (see [`with_optional_field_inference()`](Self::with_optional_field_inference))
+    /// or a number was widened (see [`with_numeric_widening()`](Self::with_numeric_widening)).
+    /// Collecting the types of all values before serializing them makes the types final before any
+    /// code is written, so that all code matches the [`Structs`] and [`Enums`].
+    ///
+    /// Note that the code of a value is only adapted to the final types if optional fields are
+    /// inferred or numeric types are widened, since the types do not change otherwise.
+    pub fn collect<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        W: Write,
+        T: Serialize + ?Sized,
+    {
+        self.writer.discard = true;
+        let result = value.serialize(&mut *self);
+        self.writer.discard = false;
+        let pending_fallbacks = std::mem::take(&mut self.pending_string_enum_fallbacks);
+        result?;
+
+        // The types of the value may refer to the string enums until it is complete.
+        for name in pending_fallbacks {
+            if self.written_string_enums.contains(&name) {
+                return Err(Error::message(format!(
+                    "The strings for the enum '{name}' have more distinct values than the limit, \
+                     but the enum was already written. Collect all values with `collect()` \
+                     before writing them."
+                )));
+            }
+            self.fall_back_to_str(&name);
+        }
+        Ok(())
+    }
+
+    /// Renames the struct or enum with the provided name, including all references to it in the
+    /// other structs and enums.
+    ///
+    /// Instances of the type that are serialized afterwards use the new name as well. The code that
+    /// was already written is not changed, so types should be renamed before the data is written,
+    /// e.g. after [`collect()`](Self::collect) or [`trace()`](Self::trace).
+    pub fn rename_type(&mut self, name: &str, new_name: impl Into<String>) -> Result<(), Error> {
+        let new_name = new_name.into();
+        if self.structs.structure(&new_name).is_some() || self.enums.names().any(|n| n == new_name)
+        {
+            return Err(Error::message(format!(
+                "Can not rename type '{name}' to '{new_name}', since that name is already taken."
+            )));
+        }
+        let renamed_struct = self.structs.rename(name, &new_name);
+        let renamed_enum = self.enums.rename(name, &new_name);
+        if !renamed_struct && !renamed_enum {
+            return Err(Error::message(format!("Unknown type '{name}'.")));
+        }
+        self.rename_state(name, &new_name);
+        self.types_changed();
+        Ok(())
+    }
+
+    /// Updates the state of the serializer for a type that was renamed in the [`Structs`] and
+    /// [`Enums`] (see [`rename_type()`](Self::rename_type)).
+    fn rename_state(&mut self, name: &str, new_name: &str) {
+        let new_name = new_name.to_string();
+        for renamed in self.renames.values_mut() {
+            if renamed == name {
+                renamed.clone_from(&new_name);
+            }
+        }
+        if !self.renames.values().any(|renamed| *renamed == new_name) {
+            self.renames.insert(name.to_string(), new_name.clone());
+        }
+        if let Some(type_override) = self.type_overrides.remove(name) {
+            self.type_overrides.insert(new_name.clone(), type_override);
+        }
+
+        // Renames a type or the enum of a variant (e.g. `Event::Spawn`).
+        let rename_owner = |owner: String| match owner.split_once("::") {
+            Some((enclosing, variant)) if enclosing == name => format!("{new_name}::{variant}"),
+            None if owner == name => new_name.clone(),
+            _ => owner,
+        };
+        let rename_position = |position: MapPosition| MapPosition {
+            path: position.path,
+            enclosing: rename_owner(position.enclosing),
+        };
+        let rename_value = |value: Cow<'static, str>| {
+            if value == name {
+                Cow::Owned(new_name.clone())
+            } else {
+                value
+            }
+        };
+        self.map_positions = std::mem::take(&mut self.map_positions)
+            .into_iter()
+            .map(|(position, value)| (rename_position(position), rename_value(value)))
+            .collect();
+        self.sequence_enum_positions = std::mem::take(&mut self.sequence_enum_positions)
+            .into_iter()
+            .map(|(position, value)| (rename_position(position), rename_value(value)))
+            .collect();
+        let rename_hint = |hint: &mut TypeHint| {
+            if let TypeHint::StringEnum {
+                name: enum_name, ..
+            } = hint
+            {
+                if enum_name == name {
+                    enum_name.clone_from(&new_name);
+                }
+            }
+        };
+        self.type_hints.values_mut().for_each(rename_hint);
+        self.field_type_hints.values_mut().for_each(rename_hint);
+        if let Some(variants) = self.string_enum_variants.remove(name) {
+            self.string_enum_variants.insert(new_name.clone(), variants);
+        }
+        if self.written_string_enums.remove(name) {
+            self.written_string_enums.insert(new_name.clone());
+        }
+        self.field_type_hints = std::mem::take(&mut self.field_type_hints)
+            .into_iter()
+            .map(|((owner, field), hint)| ((rename_owner(owner), field), hint))
+            .collect();
+        self.pinned_field_types = std::mem::take(&mut self.pinned_field_types)
+            .into_iter()
+            .map(|((owner, field), mut field_type)| {
+                field_type.rename(name, &new_name);
+                ((rename_owner(owner), field), field_type)
+            })
+            .collect();
+        self.indeterminate_fields = std::mem::take(&mut self.indeterminate_fields)
+            .into_iter()
+            .map(|((owner, field), path)| ((rename_owner(owner), field), path))
+            .collect();
+        if self.traced_enums.remove(name) {
+            self.traced_enums.insert(new_name.clone());
+        }
+        if self.serde_names.remove(name) {
+            self.serde_names.insert(new_name.clone());
+        }
+        if self.map_names.remove(name) {
+            self.map_names.insert(new_name);
+        }
+    }
+
+    /// Requires the types to be checked again before they are written in strict mode (see
+    /// [`check_types()`](Self::check_types)).
+    fn types_changed(&mut self) {
+        if self.strict {
+            self.structs.unchecked = true;
+            self.enums.unchecked = true;
+        }
+    }
+
+    /// Checks that the generated types have no type arguments that could not be determined from the
+    /// data and that would be written as `()`, such as the element type of a sequence that was
+    /// always empty. This only applies in strict mode (see
+    /// [`with_strict_mode()`](Self::with_strict_mode)) and must be called after all values have
+    /// been serialized, before the types are written: until the types pass the check,
+    /// [`Structs::write()`] and [`Enums::write()`] fail.
+    pub fn check_types(&mut self) -> Result<(), Error> {
+        if !self.strict {
+            return Ok(());
+        }
+
+        let mut fields: Vec<(String, String)> = Vec::new();
+        let mut add_fields = |owner: &str, structure: &Structured| match structure {
+            Structured::Struct(inner) => {
+                for (field, field_type) in inner.fields() {
+                    if field_type.is_indeterminate() {
+                        fields.push((owner.to_string(), field.to_string()));
+                    }
+                }
+            }
+            Structured::Tuple(inner) => {
+                for (index, field_type) in inner.elements().iter().enumerate() {
+                    if field_type.is_indeterminate() {
+                        fields.push((owner.to_string(), index.to_string()));
+                    }
+                }
+            }
+            Structured::Unit => {}
+        };
+        for (name, structure) in self.structs.structures() {
+            add_fields(name, structure);
+        }
+        for name in self.enums.names() {
+            for (variant, structure) in self.enums.variant_structures(name) {
+                add_fields(&format!("{name}::{variant}"), structure);
+            }
+        }
+
+        let mut errors: Vec<String> = fields
+            .into_iter()
+            .map(|(owner, field)| {
+                match self
+                    .indeterminate_fields
+                    .get(&(owner.clone(), field.clone()))
+                {
+                    Some(path) => format!(
+                    "The type of the value at '{path}' ({owner}.{field}) could not be determined."
+                ),
+                    None => format!("The type of {owner}.{field} could not be determined."),
+                }
+            })
+            .collect();
+        for name in self.type_overrides.keys() {
+            if self.enums.names().any(|taken| taken == name)
+                && self.overridden_type(name, None).is_err()
+            {
+                errors.push(format!(
+                    "The type of {name} could not be determined, since not all of its variants \
+                     occur in the data."
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            self.structs.unchecked = false;
+            self.enums.unchecked = false;
+            Ok(())
+        } else {
+            errors.sort();
+            Err(Error::message(format!(
+                "Found indeterminate types in strict mode. {}",
+                errors.join(" ")
+            )))
+        }
+    }
+
+    /// Runs a transformation pass over the structs and enums (see [`TypePass`]).
+    ///
+    /// The types are only changed if the pass does not create any conflicts, like a rename to a
+    /// name that is already taken or a field type that refers to an unknown type. As with
+    /// [`rename_type()`](Self::rename_type), the code that was already written is not changed, and
+    /// instances that are serialized afterwards are merged into the changed types.
+    ///
+    /// The types of the fields that the pass rewrites are kept as they are. The values of these
+    /// fields that are serialized afterwards are converted to them as far as possible, like with
+    /// [`with_type_hint()`](Self::with_type_hint): integers are written for the rewritten integer
+    /// type and sequences as arrays (e.g. for a `&'static [u8]` that is rewritten to `[u16; 4]`).
+    /// Other values are written as usual, so they must be valid expressions of the rewritten type.
+    pub fn run_pass(&mut self, pass: &mut impl TypePass) -> Result<(), Error> {
+        let mut structs = self.structs.clone();
+        let mut enums = self.enums.clone();
+        let changes = pass::run(pass, &mut structs, &mut enums)?;
+        self.structs = structs;
+        self.enums = enums;
+        for (owner, field, field_type) in changes.rewritten {
+            self.pinned_field_types.insert((owner, field), field_type);
+        }
+        for (name, new_name) in changes.renamed {
+            self.rename_state(&name, &new_name);
+        }
+        self.types_changed();
+        Ok(())
+    }
+
+    /// Removes the struct or enum with the provided name.
+    ///
+    /// The type can only be removed if no other struct or enum refers to it. Instances of the type
+    /// that are serialized afterwards add it again.
+    pub fn remove_type(&mut self, name: &str) -> Result<(), Error> {
+        let referrer = match self.structs.referrer(name) {
+            Some(referrer) => Some(referrer.to_string()),
+            None => self.enums.referrer(name),
+        };
+        if let Some(referrer) = referrer {
+            return Err(Error::message(format!(
+                "Can not remove type '{name}', since it is referred to by '{referrer}'."
+            )));
+        }
+
+        self.types_changed();
+        let removed_struct = self.structs.remove(name);
+        let removed_enum = self.enums.remove(name);
+        if !removed_struct && !removed_enum {
+            return Err(Error::message(format!("Unknown type '{name}'.")));
+        }
+        Ok(())
+    }
+
+    /// Retrieves a reference to the serialization output.
+    pub fn out(&self) -> &W {
+        &self.writer.out
+    }
+
+    /// Retrieves e mutable reference to the serialization output.
+    pub fn out_mut(&mut self) -> &mut W {
+        &mut self.writer.out
+    }
+
+    /// Retrieves a reference to the [`Structs`].
+    pub fn structs(&self) -> &Structs {
+        &self.structs
+    }
+
+    /// Retrieves a mutable reference to the [`Structs`].
+    pub fn structs_mut(&mut self) -> &mut Structs {
+        &mut self.structs
+    }
+
+    /// Retrieves a reference to the [`Enums`].
+    pub fn enums(&self) -> &Enums {
+        &self.enums
+    }
+
+    /// Retrieves a mutable reference to the [`Enums`].
+    pub fn enums_mut(&mut self) -> &mut Enums {
+        &mut self.enums
+    }
+}
+
+impl<W> Serializer<W>
+where
+    W: Write,
+{
+    /// Serializes a value that is located in the named field of the current value.
+    fn serialize_field_value<T>(&mut self, name: &str, value: &T) -> Result<FieldType, Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.path.push_field(name);
+        let result = self.serialize_hinted(Some(name), value);
+        self.path.pop();
+        result
+    }
+
+    /// Serializes a value that is located at the provided index of the current tuple.
+    fn serialize_index_value<T>(&mut self, index: usize, value: &T) -> Result<FieldType, Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.path.push_index(index);
+        let result = self.serialize_hinted(Some(itoa::Buffer::new().format(index)), value);
+        self.path.pop();
+        result
+    }
+
+    /// Serializes a value that is an element of the current sequence.
+    fn serialize_element_value<T>(&mut self, value: &T) -> Result<FieldType, Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.path.push_element();
+        let result = self.serialize_hinted(None, value);
+        self.path.pop();
+        result
+    }
+
+    /// Serializes the key (index 0) or the value (index 1) of an entry of the current map.
+    fn serialize_entry_value<T>(&mut self, index: usize, value: &T) -> Result<FieldType, Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.path.push_entry(index);
+        let result = self.serialize_hinted(None, value);
+        self.path.pop();
+        result
+    }
+
+    /// Serializes the value at the current path, applying the type hint for the path or for the
+    /// provided field of the enclosing type, if any (see
+    /// [`with_type_hint_at()`](Self::with_type_hint_at)). If the field has a pinned type (see
+    /// [`run_pass()`](Self::run_pass)), the value is converted to that type, which is returned.
+    fn serialize_hinted<T>(&mut self, field: Option<&str>, value: &T) -> Result<FieldType, Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let pinned = field.and_then(|field| {
+            let owner = self.enclosing_owner()?;
+            self.pinned_field_types
+                .get(&(owner, field.to_string()))
+                .cloned()
+        });
+        let Some(pinned) = pinned else {
+            return self.serialize_hinted_value(field, value);
+        };
+
+        let mut hints = Vec::new();
+        pinned_type_hints(&pinned, self.path.as_str().to_string(), &mut hints);
+        let paths: Vec<_> = hints.iter().map(|(path, _)| path.clone()).collect();
+        self.pinned_type_hints.extend(hints);
+        let result = self.serialize_hinted_value(field, value);
+        for path in paths {
+            self.pinned_type_hints.remove(&path);
+        }
+        result.map(|_| pinned)
+    }
+
+    /// Serializes the value at the current path, like
+    /// [`serialize_hinted()`](Self::serialize_hinted), without a pinned type for the field.
+    fn serialize_hinted_value<T>(
+        &mut self,
+        field: Option<&str>,
+        value: &T,
+    ) -> Result<FieldType, Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        // A hint that is still pending belongs to a value that contains this one, so it is a
+        // compound value that does not match the hint.
+        if let Some((hint, path)) = self.pending_type_hint.take() {
+            return Err(type_hint_mismatch(&hint, &path));
+        }
+
+        let path_hint = self
+            .pinned_type_hints
+            .get(self.path.as_str())
+            .or_else(|| self.type_hints.get(self.path.as_str()));
+        let hint = match (self.newtype_type_hint.take(), path_hint) {
+            (Some(hint), _) => Some(hint),
+            (None, Some(hint)) => Some(hint.clone()),
+            (None, None) => field.and_then(|field| self.field_type_hint(field)),
+        };
+        let mut field_type = match hint {
+            None => value.serialize(&mut *self)?,
+            Some(TypeHint::Path(path)) => {
+                let unmarked_depth = self.unmarked_depth.replace(self.enclosing_types.len());
+                let result = value.serialize(&mut *self);
+                self.unmarked_depth = unmarked_depth;
+                result?;
+                FieldType::Path(Cow::<'static, str>::Owned(path).into())
+            }
+            Some(hint) => {
+                self.pending_type_hint = Some((hint, self.path.as_str().to_string()));
+                let field_type = value.serialize(&mut *self)?;
+                if let Some((hint, path)) = self.pending_type_hint.take() {
+                    return Err(type_hint_mismatch(&hint, &path));
+                }
+                field_type
+            }
+        };
+
+        if let Some(fallback) = self.fallback_types.get(self.path.as_str()) {
+            field_type.fill(fallback);
+        }
+        if self.strict && field_type.is_indeterminate() {
+            if let (Some(owner), Some(field)) = (self.enclosing_owner(), field) {
+                self.indeterminate_fields
+                    .entry((owner, field.to_string()))
+                    .or_insert_with(|| self.path.as_str().to_string());
+            }
+        }
+        Ok(field_type)
+    }
+
+    /// The name of the nearest enclosing type, including the variant for enums (e.g.
+    /// `Event::Spawn`).
+    fn enclosing_owner(&self) -> Option<String> {
+        let enclosing = self.enclosing_types.last()?;
+        Some(match &enclosing.variant {
+            Some(variant) => format!("{}::{}", enclosing.name, variant),
+            None => enclosing.name.to_string(),
+        })
+    }
+
+    /// Finds the type hint for the provided field of the nearest enclosing type.
+    fn field_type_hint(&self, field: &str) -> Option<TypeHint> {
+        let owner = self.enclosing_owner()?;
+        self.field_type_hints
+            .get(&(owner, field.to_string()))
+            .cloned()
+    }
+
+    /// Checks that the values of the enum with the provided name can be serialized in strict mode
+    /// (see [`with_strict_mode()`](Self::with_strict_mode)), i.e. that the enum was traced.
+    fn check_traced(&self, name: &str) -> Result<(), Error> {
+        if !self.strict
+            || self.traced_enums.contains(name)
+            || self.type_overrides.contains_key(name)
+        {
+            return Ok(());
+        }
+        Err(Error::message(format!(
+            "The enum '{name}' at '{}' was not traced, so it would only have the variants that \
+             occur in the data.",
+            self.path.as_str()
+        )))
+    }
+
+    /// Takes the pending type hint and its path if the hint applies to the current value, i.e. if
+    /// it is of the kind that is accepted by the provided function.
+    fn take_type_hint(&mut self, applies: fn(&TypeHint) -> bool) -> Option<(TypeHint, String)> {
+        match self.pending_type_hint.take() {
+            Some((hint, path)) if applies(&hint) => Some((hint, path)),
+            other => {
+                self.pending_type_hint = other;
+                None
+            }
+        }
+    }
+
+    /// Writes a unit variant and merges it into the generated enums. Returns the type of the enum.
+    fn serialize_unit_variant_value(
+        &mut self,
+        name: Cow<'static, str>,
+        variant: Cow<'static, str>,
+    ) -> Result<FieldType, Error> {
+        self.check_builtin_variant(&name, &variant, false)?;
+        let path = self.type_path(name.clone());
+        self.writer.write(&path)?;
+        self.writer.write("::")?;
+        self.writer.write(&variant)?;
+
+        let structure = Structured::Unit;
+        self.merge_variant(name.clone(), variant, structure)?;
+        self.update_type_path(&name)?;
+        Ok(FieldType::Enum(name.into()))
+    }
+
+    /// Determines the variant of the enum with the provided name for a string (see
+    /// [`TypeHint::StringEnum`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the enum.
+    /// * `max_variants`: The largest number of variants that the enum may have.
+    /// * `value`: The string.
+    /// * `path`: The path of the string, for errors.
+    ///
+    /// Returns `None` if the enum has more variants than allowed, in which case the string is
+    /// written as a string.
+    fn string_enum_variant(
+        &mut self,
+        name: &str,
+        max_variants: usize,
+        value: &str,
+        path: &str,
+    ) -> Result<Option<String>, Error> {
+        if self.string_enum_fallbacks.contains(name) {
+            return Ok(None);
+        }
+        let taken = self.serde_names.contains(name)
+            || self.map_names.contains(name)
+            || self.reserved_map_names.contains(name)
+            || self.structs.structures.contains_key(name)
+            || self.structs.mangled_renames.contains_key(name);
+        if taken {
+            return Err(Error::message(format!(
+                "The name of the enum '{name}' for the strings at '{path}' is already taken by \
+                 another type."
+            )));
+        }
+
+        let variants = self
+            .string_enum_variants
+            .entry(name.to_string())
+            .or_default();
+        let variant = match variants.get(value) {
+            Some(variant) => variant.clone(),
+            None => {
+                let variant = ident::sanitize(&ident::pascal_case(value)).into_owned();
+                if let Some((other, _)) =
+                    variants.iter().find(|(_, existing)| **existing == variant)
+                {
+                    return Err(Error::message(format!(
+                        "The strings \"{other}\" and \"{value}\" at '{path}' result in the same \
+                         variant '{name}::{variant}'."
+                    )));
+                }
+                let exceeded = variants.len() >= max_variants;
+                variants.insert(value.to_string(), variant.clone());
+                if exceeded && self.writer.discard {
+                    // The types of the value that is collected may already refer to the enum.
+                    self.pending_string_enum_fallbacks.insert(name.to_string());
+                } else if exceeded && self.written_string_enums.contains(name) {
+                    return Err(Error::message(format!(
+                        "The strings at '{path}' have more than {max_variants} distinct values, \
+                         which is the limit for the enum '{name}', but the enum was already \
+                         written. Collect all values with `collect()` before writing them."
+                    )));
+                } else if exceeded {
+                    self.fall_back_to_str(name);
+                    return Ok(None);
+                }
+                variant
+            }
+        };
+        if !self.writer.discard {
+            self.written_string_enums.insert(name.to_string());
+        }
+        Ok(Some(variant))
+    }
+
+    /// Replaces the string enum with the provided name by `&'static str`, since it has more
+    /// variants than allowed (see [`TypeHint::StringEnum`]).
+    fn fall_back_to_str(&mut self, name: &str) {
+        self.string_enum_fallbacks.insert(name.to_string());
+        self.string_enum_variants.remove(name);
+        self.enums.remove(name);
+        let mut replace = |field_type: &mut FieldType| {
+            if matches!(field_type, FieldType::Enum(arg) if arg.value() == name) {
+                *field_type = FieldType::Str;
+            }
+        };
+        let structures =
+            self.structs
+                .structures
+                .iter_mut()
+                .map(|(_, structure)| structure)
+                .chain(
+                    self.enums.enums.iter_mut().flat_map(|(_, variants)| {
+                        variants.iter_mut().map(|(_, structure)| structure)
+                    }),
+                );
+        for structure in structures {
+            for field_type in structure.field_types_mut() {
+                field_type.visit_mut(&mut replace);
+            }
+        }
+    }
+
+    /// Determines the [`MapStrategy`] for a map at the current path.
+    fn map_strategy(&self) -> MapStrategy {
+        self.map_strategies
+            .get(self.path.as_str())
+            .copied()
+            .unwrap_or(self.map_strategy)
+    }
+
+    /// Enters a generated type.
+    fn enter_type(&mut self, name: Cow<'static, str>, variant: Option<Cow<'static, str>>) {
+        self.enclosing_types.push(EnclosingType {
+            name,
+            variant,
+            map: false,
+            route_start: Some(self.path.route().len()),
+        });
+    }
+
+    /// Enters a struct that is generated from a map.
+    fn enter_map(&mut self, name: Cow<'static, str>) {
+        self.enclosing_types.push(EnclosingType {
+            name,
+            variant: None,
+            map: true,
+            route_start: Some(self.path.route().len()),
+        });
+    }
+
+    /// Enters a struct that is generated from a map and that wraps a lookup table.
+    fn enter_lookup(&mut self, name: Cow<'static, str>) {
+        self.enclosing_types.push(EnclosingType {
+            name,
+            variant: None,
+            map: true,
+            route_start: None,
+        });
+    }
+
+    /// Leaves the most recently entered generated type.
+    fn leave_type(&mut self) {
+        self.enclosing_types.pop();
+    }
+
+    /// The current name of the type with the provided Serde name (see
+    /// [`rename_type()`](Self::rename_type)).
+    fn renamed(&self, name: &'static str) -> Cow<'static, str> {
+        match self.renames.get(name) {
+            Some(renamed) => Cow::Owned(renamed.clone()),
+            None => Cow::Borrowed(name),
+        }
+    }
+
+    /// The path with which the values of the type with the provided name are written, which differs
+    /// from the name if the type is overridden (see
+    /// [`with_type_override()`](Self::with_type_override)).
+    fn type_path(&self, name: Cow<'static, str>) -> Cow<'static, str> {
+        match self.type_overrides.get(name.as_ref()) {
+            Some(type_override) => type_override.path().to_string().into(),
+            None => name,
+        }
+    }
+
+    /// Begins an instance of a struct, writing its name. If names are mangled, the code of the
+    /// instance is captured, since its name is only known once its fields are known. The same goes
+    /// for structs with a built-in override, which only applies if the fields match (see
+    /// [`Shape`](type_override::Shape)). Returns the constructor call if the struct is overridden
+    /// with a constructor.
+    fn struct_instance_begin(&mut self, name: &str) -> Result<Option<ConstructorCall>, Error> {
+        let Some(type_override) = self.type_overrides.get(name) else {
+            if self.mangle_names {
+                self.writer.begin_capture();
+            }
+            self.writer.write(name)?;
+            self.writer.instance_begin_marker(name, None)?;
+            return Ok(None);
+        };
+
+        let constructor = type_override
+            .constructor()
+            .map(|template| ConstructorCall::begin(template, &mut self.writer));
+        if type_override.shape().is_some() {
+            // The instance is written as a literal of the generated struct until the override is
+            // known to apply.
+            if constructor.is_none() {
+                self.writer.begin_capture();
+            }
+            self.writer.write(name)?;
+        } else {
+            self.writer.write(type_override.path())?;
+        }
+        self.writer.instance_begin_marker(name, None)?;
+        Ok(constructor)
+    }
+
+    /// Ends the instance of a struct that was begun with
+    /// [`struct_instance_begin()`](Self::struct_instance_begin) and merges its structure into the
+    /// generated structs. Returns the type of the instance.
+    fn struct_instance_end(
+        &mut self,
+        name: Cow<'static, str>,
+        structure: Structured,
+        constructor: Option<ConstructorCall>,
+    ) -> Result<FieldType, Error> {
+        let shape = self
+            .type_overrides
+            .get(name.as_ref())
+            .and_then(TypeOverride::shape);
+        if let Some(shape) = shape {
+            let applies = shape.matches_struct(&structure);
+            let literal = match constructor {
+                Some(constructor) if applies => {
+                    constructor.end(&name, &mut self.writer)?;
+                    None
+                }
+                Some(constructor) => Some(constructor.into_literal(&mut self.writer)),
+                None => Some(self.writer.end_capture()),
+            };
+            if let Some(literal) = literal {
+                match literal.strip_prefix(name.as_ref()) {
+                    Some(rest) if applies => {
+                        let path = self.type_path(name.clone());
+                        self.writer.write(&path)?;
+                        self.writer.write(rest)?;
+                    }
+                    _ => {
+                        if self.mangle_names {
+                            // Captured again, since the name may be mangled now.
+                            self.writer.begin_capture();
+                        }
+                        self.writer.write(&literal)?;
+                    }
+                }
+            }
+            self.builtin_applies(&name, applies)?;
+        } else if let Some(constructor) = constructor {
+            constructor.end(&name, &mut self.writer)?;
+        }
+        if !self.mangle_names || self.type_overrides.contains_key(name.as_ref()) {
+            self.structs.merge(&name, structure)?;
+            self.update_type_path(&name)?;
+            return Ok(FieldType::Struct(name.into()));
+        }
+
+        let code = self.writer.end_capture();
+        let mut rest = code.strip_prefix(name.as_ref()).unwrap_or(&code);
+        if self.writer.markers {
+            rest = rest
+                .strip_prefix(&inference::instance_begin(&name, None))
+                .unwrap_or(rest);
+        }
+        let Self {
+            structs,
+            enums,
+            serde_names,
+            map_names,
+            reserved_map_names,
+            ..
+        } = self;
+        let taken = |name: &str| {
+            serde_names.contains(name)
+                || map_names.contains(name)
+                || reserved_map_names.contains(name)
+                || enums.enums.contains_key(name)
+        };
+        let (mangled, renames) = structs.merge_mangled(name, structure, taken)?;
+        for (former, new_name) in &renames {
+            if self.written_names.contains(former) {
+                return Err(Error::message(format!(
+                    "The struct '{former}' was already written when a conflicting instance was \
+                     found at '{}', so it can not be renamed to '{new_name}'. Collect all values \
+                     with `collect()` before writing them.",
+                    self.path.as_str()
+                )));
+            }
+            self.enums.rename(former, new_name);
+        }
+
+        // The name may still change until the outermost compound value is complete.
+        let mangled_name = if self.compound_depth > 0 {
+            Cow::Owned(inference::type_name(&mangled))
+        } else {
+            if !self.writer.discard {
+                self.written_names.insert(mangled.to_string());
+            }
+            mangled.clone()
+        };
+        self.writer.write(&mangled_name)?;
+        self.writer.instance_begin_marker(&mangled_name, None)?;
+        self.writer.write(rest)?;
+        Ok(FieldType::Struct(mangled.into()))
+    }
+
+    /// Checks the variant of an enum against the shape of its built-in override, if any (see
+    /// [`Shape`](type_override::Shape)), before the variant is written.
+    fn check_builtin_variant(
+        &mut self,
+        name: &str,
+        variant: &str,
+        newtype: bool,
+    ) -> Result<(), Error> {
+        let Some(shape) = self.type_overrides.get(name).and_then(TypeOverride::shape) else {
+            return Ok(());
+        };
+        let applies = shape.matches_variant(variant, newtype);
+        self.builtin_applies(name, applies)
+    }
+
+    /// Records whether the built-in override for the type with the provided name applies to an
+    /// instance of the type. If it does not, the override is removed, so that the type is
+    /// generated instead, unless it was already applied to other instances.
+    fn builtin_applies(&mut self, name: &str, applies: bool) -> Result<(), Error> {
+        if applies {
+            self.builtin_uses.insert(name.to_string());
+            return Ok(());
+        }
+        if self.builtin_uses.contains(name) {
+            return Err(Error::message(format!(
+                "The type '{name}' at '{}' does not match the built-in type that it was written as \
+                 before. Remove the built-in override with `without_type_override()`.",
+                self.path.as_str()
+            )));
+        }
+        self.structs.type_paths.remove(name);
+        self.enums.type_paths.remove(name);
+        self.type_overrides.remove(name);
+        Ok(())
+    }
+
+    /// Determines whether optional fields are inferred (see
+    /// [`with_optional_field_inference()`](Self::with_optional_field_inference)).
+    fn infers_optional(&self) -> bool {
+        self.structs.options.infer_optional
+    }
+
+    /// Determines whether numeric types are widened (see
+    /// [`with_numeric_widening()`](Self::with_numeric_widening)).
+    fn widening(&self) -> NumericWidening {
+        self.structs.options.widening
+    }
+
+    /// Begins a compound value. When types are inferred across instances, the code of the
+    /// outermost compound value is captured, so that it can be resolved once the value is
+    /// complete.
+    fn begin_compound(&mut self) {
+        if self.captures_compounds() {
+            if self.compound_depth == 0 {
+                self.writer.begin_capture();
+            }
+            self.compound_depth += 1;
+        }
+    }
+
+    /// Discards the state that is left behind by a value that failed to serialize, such as the
+    /// captured code of its compound values, once a new value begins at the root of the data.
+    /// Otherwise, the next value would be written into the captures that were never ended.
+    fn recover_at_root(&mut self) {
+        if !self.path.is_root() {
+            return;
+        }
+        // A new value begins, which may change the types.
+        self.types_changed();
+        self.writer.captures.clear();
+        self.compound_depth = 0;
+        self.enclosing_types.clear();
+        self.reserved_map_names.clear();
+        self.pending_type_hint = None;
+        self.newtype_type_hint = None;
+        self.pinned_type_hints.clear();
+        self.unmarked_depth = None;
+    }
+
+    /// Ends the compound value that was most recently begun with
+    /// [`begin_compound()`](Self::begin_compound).
+    fn end_compound(&mut self, field_type: &FieldType) -> Result<(), Error> {
+        if !self.captures_compounds() {
+            return Ok(());
+        }
+
+        self.compound_depth = self
+            .compound_depth
+            .checked_sub(1)
+            .ok_or_else(|| Error::Bug(String::from("Unbalanced compound values.")))?;
+        if self.compound_depth == 0 {
+            let mut code = self.writer.end_capture();
+            if self.mangle_names {
+                let written = &mut self.written_names;
+                let discard = self.writer.discard;
+                code = inference::rename(&code, self.structs.mangled_renames(), |name| {
+                    if !discard {
+                        written.insert(name.to_string());
+                    }
+                })?;
+            }
+            if self.writer.markers {
+                code = inference::resolve(&code, &self.structs, &self.enums, field_type)?;
+            }
+            self.writer.write(&code)?;
+        }
+        Ok(())
+    }
+
+    /// Determines whether the code of the outermost compound value is captured, either to resolve
+    /// the types that are inferred across instances or the names of the structs with mangled names.
+    fn captures_compounds(&self) -> bool {
+        self.writer.markers || self.mangle_names
+    }
+
+    fn map_position(&self) -> MapPosition {
+        let enclosing = self
+            .enclosing_types
+            .iter()
+            .rev()
+            .find(|enclosing| !enclosing.map)
+            .map(|enclosing| match &enclosing.variant {
+                Some(variant) => format!("{}::{}", enclosing.name, variant),
+                None => enclosing.name.to_string(),
+            })
+            .unwrap_or_default();
+        MapPosition {
+            path: self.path.as_str().to_string(),
+            enclosing,
+        }
+    }
+
+    /// Determines the name for a struct that is generated from the map at the current path.
+    ///
+    /// If a name was already decided on for a map at the same position, that name is returned
+    /// without a position. Otherwise, a candidate name is returned along with the position, which
+    /// must be passed to [`decide_map_name()`](Self::decide_map_name).
+    fn map_name(&self) -> (Cow<'static, str>, Option<MapPosition>) {
+        let position = self.map_position();
+        if let Some(name) = self.map_positions.get(&position) {
+            return (name.clone(), None);
+        }
+
+        let mut name = String::new();
+        if let Some(enclosing) = self.enclosing_types.last() {
+            name.push_str(&enclosing.name);
+            if let Some(variant) = &enclosing.variant {
+                name.push_str(variant);
+            }
+        }
+        let field = self.path.last_field().map(ident::pascal_case);
+        match field.as_deref() {
+            Some(field) if !field.is_empty() => {
+                if name.is_empty() && field.starts_with(|c: char| c.is_ascii_digit()) {
+                    name.push_str("Map");
+                }
+                name.push_str(field);
+            }
+            _ => name.push_str("Map"),
+        }
+
+        if let Some(naming) = &self.map_naming {
+            if let Some(custom) = naming(self.path.as_str(), &name) {
+                name = custom;
+            }
+        }
+        let name = ident::sanitize(&name).into_owned();
+        if self.strict {
+            // A taken name is an error once the name is decided on, unless the map shares the
+            // struct of an equivalent map.
+            return (name.into(), Some(position));
+        }
+        (self.unique_map_name(name).into(), Some(position))
+    }
+
+    /// Determines the name of the enum for the heterogeneous sequence at the current path, if
+    /// any. See [`with_sequence_enums()`](Self::with_sequence_enums).
+    ///
+    /// # Arguments
+    ///
+    /// * `create`: Whether a name should be decided on if there is no enum at the current position
+    ///   yet.
+    fn sequence_enum_name(&mut self, create: bool) -> Option<Cow<'static, str>> {
+        let position = self.map_position();
+        if let Some(name) = self.sequence_enum_positions.get(&position) {
+            return Some(name.clone());
+        }
+        if !create {
+            return None;
+        }
+
+        let mut name = String::new();
+        if let Some(enclosing) = self.enclosing_types.last() {
+            name.push_str(&enclosing.name);
+            if let Some(variant) = &enclosing.variant {
+                name.push_str(variant);
+            }
+        }
+        if let Some(field) = self.path.last_field().map(ident::pascal_case) {
+            if !name.is_empty() || !field.starts_with(|c: char| c.is_ascii_digit()) {
+                name.push_str(&field);
+            }
+        }
+        name.push_str("Element");
+        let name = ident::sanitize(&name).into_owned();
+        let name: Cow<'static, str> = self.unique_map_name(name).into();
+        self.sequence_enum_positions.insert(position, name.clone());
+        Some(name)
+    }
+
+    fn is_type_name_taken(&self, name: &str) -> bool {
+        self.map_names.contains(name)
+            || self.reserved_map_names.contains(name)
+            || self.serde_names.contains(name)
+            || self.structs.structures.contains_key(name)
+            || self.structs.mangled_renames.contains_key(name)
+            || self.enums.enums.contains_key(name)
+    }
+
+    /// Appends a number to the provided name if it is already in use.
+    fn unique_map_name(&self, base: String) -> String {
+        let mut name = base.clone();
+        let mut counter = 1;
+        while self.is_type_name_taken(&name) {
+            counter += 1;
+            name = format!("{base}{counter}");
+        }
+        name
+    }
+
+    /// Reserves the candidate name for a map (as returned by [`map_name()`](Self::map_name)) until
+    /// it is decided on, so that the maps and types inside of it do not take it. This way, the
+    /// names of the maps inside of it are derived from the name that is decided on. Names that are
+    /// taken already (only in strict mode) are not reserved.
+    fn reserve_map_name(&mut self, candidate: &str) {
+        if !self.is_type_name_taken(candidate) {
+            self.reserved_map_names.insert(candidate.to_string());
+        }
+    }
+
+    /// Determines the current name of a type that is provided by Serde (see
+    /// [`renamed()`](Self::renamed)) and reserves it, so that no struct that is generated from a
+    /// map gets that name. Fails if such a struct already has that name, since the types would be
+    /// merged.
+    fn serde_name(&mut self, name: &'static str) -> Result<Cow<'static, str>, Error> {
+        let name = self.renamed(name);
+        if self.serde_names.contains(name.as_ref()) {
+            return Ok(name);
+        }
+        if self.map_names.contains(name.as_ref()) || self.reserved_map_names.contains(name.as_ref())
+        {
+            return Err(Error::message(format!(
+                "The type '{name}' at '{}' has the same name as a struct that was generated for a \
+                 map. Trace the type before serializing or name the map with `with_map_naming()`.",
+                self.path.as_str()
+            )));
+        }
+        if self.structs.is_mangled(&name) {
+            return Err(Error::message(format!(
+                "The type '{name}' at '{}' has the same name as a struct that was generated for an \
+                 instance of another struct. Trace the type before serializing.",
+                self.path.as_str()
+            )));
+        }
+        if self.string_enum_variants.contains_key(name.as_ref()) {
+            return Err(Error::message(format!(
+                "The type '{name}' at '{}' has the same name as an enum that was generated for \
+                 strings. Choose another name in the type hint.",
+                self.path.as_str()
+            )));
+        }
+        self.serde_names.insert(name.to_string());
+        Ok(name)
+    }
+
+    /// Decides on the name for the maps at the provided position.
+    ///
+    /// # Arguments
+    ///
+    /// * `position`: The position, as returned by [`map_name()`](Self::map_name).
+    /// * `candidate`: The candidate name, as returned by [`map_name()`](Self::map_name).
+    /// * `structure`: The structure of the map. If a struct that was generated from another map has
+    ///   the same keys and compatible field types, its name is used instead of the candidate.
+    fn decide_map_name(
+        &mut self,
+        position: MapPosition,
+        candidate: Cow<'static, str>,
+        structure: Option<&StructuredStruct>,
+    ) -> Result<Cow<'static, str>, Error> {
+        let reserved = self.reserved_map_names.remove(candidate.as_ref());
+        let name = match structure.and_then(|structure| self.find_equivalent_map(structure)) {
+            Some(name) => name,
+            None if reserved => candidate,
+            None if self.is_type_name_taken(&candidate) && self.strict => {
+                return Err(Error::message(format!(
+                    "The name '{candidate}' for the map at '{}' is already taken, so a number \
+                     would be appended to it.",
+                    position.path
+                )));
+            }
+            None if self.is_type_name_taken(&candidate) => {
+                self.unique_map_name(candidate.into_owned()).into()
+            }
+            None => candidate,
+        };
+        self.map_names.insert(name.to_string());
+        self.map_positions.insert(position, name.clone());
+        Ok(name)
+    }
+
+    /// Finds a struct that was generated from a map with the same keys as the provided structure
+    /// and with field types that can be merged with those of the structure.
+    fn find_equivalent_map(&self, structure: &StructuredStruct) -> Option<Cow<'static, str>> {
+        let fields = structure.fields();
+        self.structs
+            .structures
+            .iter()
+            .filter(|(name, _)| {
+                self.map_names.contains(name.as_ref()) && !self.structs.lookups.contains_key(*name)
+            })
+            .find(|(_, existing)| match existing {
+                Structured::Struct(existing) => {
+                    existing.fields().len() == fields.len()
+                        && fields.keys().all(|key| existing.fields().contains_key(key))
+                        && existing
+                            .clone()
+                            .merge(structure, NumericWidening::NONE)
+                            .is_ok()
+                }
+                _ => false,
+            })
+            .map(|(name, _)| name.clone())
+    }
+
+    /// Writes an integer and determines its type, which is the hinted type if there is a pending
+    /// integer hint (see [`TypeHint::Integer`]).
+    fn serialize_int<I>(
+        &mut self,
+        value: I,
+        range: IntRange,
+        field_type: FieldType,
+    ) -> Result<FieldType, Error>
+    where
+        I: itoa::Integer,
+    {
+        let hint = self.take_type_hint(|hint| matches!(hint, TypeHint::Integer(_)));
+        let Some((TypeHint::Integer(hinted), path)) = hint else {
+            let wide = matches!(field_type, FieldType::U64 | FieldType::I64);
+            if self.strict && wide && !self.is_path_hinted() {
+                return Err(Error::message(format!(
+                    "The value at '{}' is a {field_type:?}, which may have been a usize or isize. \
+                     Its type must be set with a type hint in strict mode.",
+                    self.path.as_str()
+                )));
+            }
+            self.write_int(value)?;
+            return Ok(self.int_type(range, field_type));
+        };
+
+        let hinted = hinted.field_type();
+        if !range.fits(&hinted) {
+            return Err(Error::message(format!(
+                "The value {} at '{path}' does not fit into the hinted type {hinted:?}.",
+                itoa::Buffer::new().format(value)
+            )));
+        }
+        // The type is known, so the literal does not need a marker.
+        self.writer.write(itoa::Buffer::new().format(value))?;
+        Ok(hinted)
+    }
+
+    fn write_int<I>(&mut self, value: I) -> Result<(), Error>
+    where
+        I: itoa::Integer,
+    {
+        let mut buffer = itoa::Buffer::new();
+        let text = buffer.format(value);
+        if self.marks_numbers() {
+            let suffix = self.enclosing_types.is_empty();
+            let marker = inference::number(self.number_route(), Number::Int(text), suffix);
+            self.writer.write(&marker)?;
+        } else {
+            self.writer.write(text)?;
+        }
+        Ok(())
+    }
+
+    fn write_float<F>(&mut self, value: F) -> Result<(), Error>
+    where
+        F: literal::Float,
+    {
+        let suffix = self.enclosing_types.is_empty();
+        if self.marks_numbers() {
+            let number = Number::Float(F::TYPE_NAME, value.bits());
+            let marker = inference::number(self.number_route(), number, suffix);
+            self.writer.write(&marker)?;
+        } else {
+            self.writer.float_literal(value, suffix)?;
+        }
+        Ok(())
+    }
+
+    /// Determines the type of an integer value, which is narrowed to the range of the value if
+    /// integers are narrowed (see [`with_integer_narrowing()`](Self::with_integer_narrowing)).
+    fn int_type(&self, range: IntRange, field_type: FieldType) -> FieldType {
+        if self.narrow_integers {
+            FieldType::Int(range)
+        } else {
+            field_type
+        }
+    }
+
+    /// Determines whether numbers should be written as markers, so that their literals can be
+    /// written for the widened type (see [`inference`]).
+    fn marks_numbers(&self) -> bool {
+        self.compound_depth > 0
+            && self.widening() != NumericWidening::NONE
+            && !self.is_path_hinted()
+    }
+
+    /// Determines whether the current value has a [`TypeHint::Path`], which declares its type.
+    fn is_path_hinted(&self) -> bool {
+        self.unmarked_depth == Some(self.enclosing_types.len())
+    }
+
+    /// The route of the current value, relative to the nearest enclosing type with marked instances.
+    fn number_route(&self) -> &str {
+        let start = self
+            .enclosing_types
+            .iter()
+            .rev()
+            .find_map(|enclosing| enclosing.route_start)
+            .unwrap_or(0);
+        &self.path.route()[start..]
+    }
+}
+
+impl<'a, W> serde::ser::Serializer for &'a mut Serializer<W>
+where
+    W: Write,
+{
+    type Ok = FieldType;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer<'a, W>;
+    type SerializeTuple = TupleSerializer<'a, W>;
+    type SerializeTupleStruct = TupleStructSerializer<'a, W>;
+    type SerializeTupleVariant = TupleVariantSerializer<'a, W>;
+    type SerializeMap = MapSerializer<'a, W>;
+    type SerializeStruct = StructSerializer<'a, W>;
+    type SerializeStructVariant = StructVariantSerializer<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.recover_at_root();
+        let out = if v { "true" } else { "false" };
+        self.writer.write(out)?;
+        Ok(FieldType::Bool)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.recover_at_root();
+        self.serialize_int(v, IntRange::signed(v.into()), FieldType::I8)
     }
 
     fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
-        self.write_int(v)?;
-        Ok(FieldType::I16)
+        self.recover_at_root();
+        self.serialize_int(v, IntRange::signed(v.into()), FieldType::I16)
     }
 
     fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
-        self.write_int(v)?;
-        Ok(FieldType::I32)
+        self.recover_at_root();
+        self.serialize_int(v, IntRange::signed(v.into()), FieldType::I32)
     }
 
     fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
-        self.write_int(v)?;
-        Ok(FieldType::I64)
+        self.recover_at_root();
+        self.serialize_int(v, IntRange::signed(v.into()), FieldType::I64)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.recover_at_root();
+        self.serialize_int(v, IntRange::signed(v), FieldType::I128)
     }
 
     fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
-        self.write_int(v)?;
-        Ok(FieldType::U8)
+        self.recover_at_root();
+        self.serialize_int(v, IntRange::unsigned(v.into()), FieldType::U8)
     }
 
     fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
-        self.write_int(v)?;
-        Ok(FieldType::U16)
+        self.recover_at_root();
+        self.serialize_int(v, IntRange::unsigned(v.into()), FieldType::U16)
     }
 
     fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
-        self.write_int(v)?;
-        Ok(FieldType::U32)
+        self.recover_at_root();
+        self.serialize_int(v, IntRange::unsigned(v.into()), FieldType::U32)
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
-        self.write_int(v)?;
-        Ok(FieldType::U64)
+        self.recover_at_root();
+        self.serialize_int(v, IntRange::unsigned(v.into()), FieldType::U64)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        self.recover_at_root();
+        self.serialize_int(v, IntRange::unsigned(v), FieldType::U128)
     }
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.recover_at_root();
         self.write_float(v)?;
         Ok(FieldType::F32)
     }
 
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.recover_at_root();
         self.write_float(v)?;
         Ok(FieldType::F64)
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
-        let string = format!("'{v}'");
-        self.writer.write(string.as_str())?;
+        self.recover_at_root();
+        self.writer.char_literal(v)?;
         Ok(FieldType::Char)
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        self.writer.write("\"")?;
-        self.writer.write(v)?;
-        self.writer.write("\"")?;
-        Ok(FieldType::Str)
+        self.recover_at_root();
+        let hint = self.take_type_hint(|hint| matches!(hint, TypeHint::StringEnum { .. }));
+        let Some((TypeHint::StringEnum { name, max_variants }, path)) = hint else {
+            self.writer.str_literal(v)?;
+            return Ok(FieldType::Str);
+        };
+
+        match self.string_enum_variant(&name, max_variants, v, &path)? {
+            Some(variant) => self.serialize_unit_variant_value(name.into(), variant.into()),
+            None => {
+                self.writer.str_literal(v)?;
+                Ok(FieldType::Str)
+            }
+        }
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.recover_at_root();
         use serde::ser::SerializeSeq;
         let mut seq = self.serialize_seq(Some(v.len()))?;
         for byte in v {
@@ -602,6 +2933,9 @@ where
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.recover_at_root();
+        // A hint for an optional value applies to the value inside `Some`.
+        self.pending_type_hint = None;
         self.writer.write("None")?;
         Ok(FieldType::Option(None.into()))
     }
@@ -610,6 +2944,7 @@ where
     where
         T: ?Sized + Serialize,
     {
+        self.recover_at_root();
         self.writer.write("Some(")?;
         let field_type = value.serialize(&mut *self)?;
         self.writer.write(")")?;
@@ -617,18 +2952,17 @@ where
     }
 
     fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.recover_at_root();
         self.writer.write("()")?;
         Ok(FieldType::Unit)
     }
 
     fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
-        self.writer.write(name)?;
-
-        let name = Cow::Borrowed(name);
-        let structure = Structured::Unit;
-        self.structs_mut().merge(&name, structure)?;
-
-        Ok(FieldType::Struct(name.into()))
+        self.recover_at_root();
+        let name = self.serde_name(name)?;
+        let constructor = self.struct_instance_begin(&name)?;
+        self.writer.instance_end_marker()?;
+        self.struct_instance_end(name, Structured::Unit, constructor)
     }
 
     fn serialize_unit_variant(
@@ -637,13 +2971,10 @@ where
         _variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        self.writer.write(name)?;
-        self.writer.write("::")?;
-        self.writer.write(variant)?;
-
-        let structure = Structured::Unit;
-        self.enums.merge(&name.into(), &variant.into(), structure)?;
-        Ok(FieldType::Enum(Cow::Borrowed(name).into()))
+        self.recover_at_root();
+        let name = self.serde_name(name)?;
+        self.check_traced(&name)?;
+        self.serialize_unit_variant_value(name, variant.into())
     }
 
     fn serialize_newtype_struct<T>(
@@ -654,7 +2985,10 @@ where
     where
         T: ?Sized + Serialize,
     {
+        self.recover_at_root();
         use serde::ser::SerializeTupleStruct as _;
+        // A hint for a newtype struct applies to its value, e.g. to the `u32` of `Meters(u32)`.
+        self.newtype_type_hint = self.pending_type_hint.take().map(|(hint, _)| hint);
         let mut ser = self.serialize_tuple_struct(name, 1)?;
         ser.serialize_field(value)?;
         ser.end()
@@ -670,6 +3004,7 @@ where
     where
         T: ?Sized + Serialize,
     {
+        self.recover_at_root();
         use serde::ser::SerializeTupleVariant as _;
         let mut ser = self.serialize_tuple_variant(name, variant_index, variant, 1)?;
         ser.serialize_field(value)?;
@@ -677,10 +3012,18 @@ where
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        SeqSerializer::begin(self, len)
+        self.recover_at_root();
+        let array_len = match self.take_type_hint(|hint| matches!(hint, TypeHint::Array(_))) {
+            Some((TypeHint::Array(len), _)) => Some(len),
+            _ => None,
+        };
+        self.begin_compound();
+        SeqSerializer::begin(self, len, array_len)
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.recover_at_root();
+        self.begin_compound();
         TupleSerializer::begin(self, len)
     }
 
@@ -689,7 +3032,10 @@ where
         name: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleStruct, Self::Error> {
-        TupleStructSerializer::begin(self, name.into(), len)
+        self.recover_at_root();
+        self.begin_compound();
+        let name = self.serde_name(name)?;
+        TupleStructSerializer::begin(self, name, len)
     }
 
     fn serialize_tuple_variant(
@@ -699,13 +3045,17 @@ where
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        TupleVariantSerializer::begin(self, name.into(), variant.into(), len)
+        self.recover_at_root();
+        let name = self.serde_name(name)?;
+        self.check_traced(&name)?;
+        self.begin_compound();
+        TupleVariantSerializer::begin(self, name, variant.into(), len)
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        self.generated_struct_seed += 1;
-        let name = format!("Generated{}", self.generated_struct_seed);
-        MapSerializer::begin(self, name.into(), len)
+        self.recover_at_root();
+        self.begin_compound();
+        MapSerializer::begin(self, len)
     }
 
     fn serialize_struct(
@@ -713,7 +3063,10 @@ where
         name: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
-        StructSerializer::begin(self, name.into(), len)
+        self.recover_at_root();
+        self.begin_compound();
+        let name = self.serde_name(name)?;
+        StructSerializer::begin(self, name, len)
     }
 
     fn serialize_struct_variant(
@@ -723,6 +3076,10 @@ where
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        StructVariantSerializer::begin(self, name.into(), variant.into(), len)
+        self.recover_at_root();
+        let name = self.serde_name(name)?;
+        self.check_traced(&name)?;
+        self.begin_compound();
+        StructVariantSerializer::begin(self, name, variant.into(), len)
     }
 }
@@ -0,0 +1,69 @@
+use super::type_override::Shape;
+use super::TypeOverride;
+
+/// The overrides for the types of the standard library that Serde serializes as structs or enums,
+/// by the name that Serde provides for them. The overrides only apply to types with the fields or
+/// variants of the type of the standard library (see [`Shape`]).
+///
+/// `IpAddr`, `PathBuf` and the like are not included, since Serde serializes them as strings for
+/// human-readable formats.
+pub fn type_overrides() -> Vec<(&'static str, TypeOverride)> {
+    vec![
+        (
+            "Duration",
+            TypeOverride::new("::core::time::Duration")
+                .with_constructor("::core::time::Duration::new({secs}, {nanos})")
+                .with_shape(Shape::Struct(&["secs", "nanos"])),
+        ),
+        // There is no const constructor for `SystemTime`, so it is written as the duration since
+        // the Unix epoch.
+        (
+            "SystemTime",
+            TypeOverride::new("::core::time::Duration")
+                .with_constructor(
+                    "::core::time::Duration::new({secs_since_epoch}, {nanos_since_epoch})",
+                )
+                .with_shape(Shape::Struct(&["secs_since_epoch", "nanos_since_epoch"])),
+        ),
+        (
+            "Range",
+            TypeOverride::new("::core::ops::Range")
+                .with_constructor("{start}..{end}")
+                .with_type("::core::ops::Range<{start}>")
+                .with_shape(Shape::Struct(&["start", "end"])),
+        ),
+        (
+            "RangeInclusive",
+            TypeOverride::new("::core::ops::RangeInclusive")
+                .with_constructor("{start}..={end}")
+                .with_type("::core::ops::RangeInclusive<{start}>")
+                .with_shape(Shape::Struct(&["start", "end"])),
+        ),
+        (
+            "RangeFrom",
+            TypeOverride::new("::core::ops::RangeFrom")
+                .with_constructor("{start}..")
+                .with_type("::core::ops::RangeFrom<{start}>")
+                .with_shape(Shape::Struct(&["start"])),
+        ),
+        (
+            "RangeTo",
+            TypeOverride::new("::core::ops::RangeTo")
+                .with_constructor("..{end}")
+                .with_type("::core::ops::RangeTo<{end}>")
+                .with_shape(Shape::Struct(&["end"])),
+        ),
+        (
+            "PhantomData",
+            TypeOverride::new("::core::marker::PhantomData")
+                .with_type("::core::marker::PhantomData<()>")
+                .with_shape(Shape::Struct(&[])),
+        ),
+        (
+            "Result",
+            TypeOverride::new("::core::result::Result")
+                .with_type("::core::result::Result<{Ok}, {Err}>")
+                .with_shape(Shape::Enum(&["Ok", "Err"])),
+        ),
+    ]
+}
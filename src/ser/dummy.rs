@@ -4,8 +4,21 @@ use serde::ser::{
 };
 use serde::Serialize;
 
+/// A placeholder for the compound serializers that a [`serde::Serializer`] does not support.
+///
+/// It can not be constructed, but all of its methods return an error rather than panicking, should
+/// they be called regardless.
 pub struct Dummy<Ok, Error>(Ok, Error);
 
+fn unexpected_call<Error>(method: &str) -> Error
+where
+    Error: serde::ser::Error,
+{
+    Error::custom(format!(
+        "Unexpected call to {method}() on an unsupported serializer."
+    ))
+}
+
 impl<Ok, Error> SerializeSeq for Dummy<Ok, Error>
 where
     Error: serde::ser::Error,
@@ -17,11 +30,11 @@ where
     where
         T: Serialize,
     {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeSeq::serialize_element"))
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeSeq::end"))
     }
 }
 
@@ -36,11 +49,11 @@ where
     where
         T: Serialize,
     {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeTuple::serialize_element"))
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeTuple::end"))
     }
 }
 
@@ -55,11 +68,11 @@ where
     where
         T: Serialize,
     {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeTupleStruct::serialize_field"))
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeTupleStruct::end"))
     }
 }
 
@@ -74,11 +87,11 @@ where
     where
         T: Serialize,
     {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeTupleVariant::serialize_field"))
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeTupleVariant::end"))
     }
 }
 
@@ -93,18 +106,18 @@ where
     where
         T: Serialize,
     {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeMap::serialize_key"))
     }
 
     fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> Result<(), Self::Error>
     where
         T: Serialize,
     {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeMap::serialize_value"))
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeMap::end"))
     }
 }
 
@@ -123,11 +136,11 @@ where
     where
         T: Serialize,
     {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeStruct::serialize_field"))
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeStruct::end"))
     }
 }
 
@@ -146,10 +159,10 @@ where
     where
         T: Serialize,
     {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeStructVariant::serialize_field"))
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Err(unexpected_call("SerializeStructVariant::end"))
     }
 }
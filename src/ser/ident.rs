@@ -0,0 +1,124 @@
+//! Conversion of arbitrary names (e.g. map keys or names from `#[serde(rename = "...")]`) into
+//! valid Rust identifiers.
+//!
+//! Names that are already valid identifiers are used verbatim. Keywords are turned into raw
+//! identifiers (e.g. `r#type`), except for the keywords that can not be raw identifiers, which get
+//! a trailing underscore (e.g. `self_`). All other names are rewritten by replacing every character
+//! that can not occur in an identifier (i.e. that is not `XID_Continue`) with an underscore and by
+//! prefixing an underscore if the name starts with a character that can not start an identifier
+//! (e.g. `hit-box` becomes `hit_box` and `2x` becomes `_2x`). Non-ASCII letters are kept, so
+//! `日本語` is used verbatim. Since rewriting is lossy, different names can result in the same
+//! identifier. Detecting this is up to the caller.
+
+#[cfg(test)]
+mod test;
+
+use crate::model::FieldType;
+use std::borrow::Cow;
+use unicode_ident::{is_xid_continue, is_xid_start};
+
+/// The strict and reserved keywords of all editions.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// The keywords that can not be used as raw identifiers.
+const NON_RAW_KEYWORDS: &[&str] = &["crate", "self", "Self", "super"];
+
+fn is_valid(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if is_xid_start(first) || first == '_' => {}
+        _ => return false,
+    }
+    name != "_" && chars.all(is_xid_continue)
+}
+
+/// Converts the provided name into a valid Rust identifier.
+///
+/// The result is borrowed if, and only if, the name is used verbatim.
+pub fn sanitize(name: &str) -> Cow<'_, str> {
+    if NON_RAW_KEYWORDS.contains(&name) {
+        return Cow::Owned(format!("{name}_"));
+    }
+    if KEYWORDS.contains(&name) {
+        return Cow::Owned(format!("r#{name}"));
+    }
+    if is_valid(name) {
+        return Cow::Borrowed(name);
+    }
+
+    let mut out = String::with_capacity(name.len() + 1);
+    if name.starts_with(|c: char| is_xid_continue(c) && !is_xid_start(c)) {
+        out.push('_');
+    }
+    for c in name.chars() {
+        if is_xid_continue(c) {
+            out.push(c);
+        } else {
+            out.push('_');
+        }
+    }
+    if out.is_empty() || out == "_" {
+        out = String::from("__");
+    }
+    Cow::Owned(out)
+}
+
+/// Converts the provided name into `PascalCase`, by capitalizing the first letter of every word.
+/// Words are separated by any character that is not a letter or digit. These separators are
+/// dropped.
+pub fn pascal_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for word in name.split(|c: char| !c.is_alphanumeric()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            out.extend(first.to_uppercase());
+            out.extend(chars);
+        }
+    }
+    out
+}
+
+/// The name of the provided type for use in generated names, e.g. `U8`, `Str`, `Seq` or the name of
+/// a struct.
+pub fn type_name(field_type: &FieldType) -> Cow<'static, str> {
+    match field_type {
+        FieldType::Bool => "Bool".into(),
+        FieldType::U8 => "U8".into(),
+        FieldType::U16 => "U16".into(),
+        FieldType::U32 => "U32".into(),
+        FieldType::U64 => "U64".into(),
+        FieldType::U128 => "U128".into(),
+        FieldType::I8 => "I8".into(),
+        FieldType::I16 => "I16".into(),
+        FieldType::I32 => "I32".into(),
+        FieldType::I64 => "I64".into(),
+        FieldType::I128 => "I128".into(),
+        FieldType::F32 => "F32".into(),
+        FieldType::F64 => "F64".into(),
+        FieldType::Char => "Char".into(),
+        FieldType::Str => "Str".into(),
+        FieldType::Unit => "Unit".into(),
+        FieldType::Struct(arg) => last_segment(arg.value()).to_string().into(),
+        FieldType::Enum(arg) => last_segment(arg.value()).to_string().into(),
+        FieldType::Tuple(_) => "Tuple".into(),
+        FieldType::Sequence(_) => "Seq".into(),
+        FieldType::Option(_) => "Option".into(),
+        FieldType::Array(..) => "Array".into(),
+        FieldType::Path(arg) => last_segment(arg.value()).to_string().into(),
+        FieldType::Int(range) => type_name(&range.field_type()),
+    }
+}
+
+/// The last segment of the provided path, e.g. `Color` for `::ves_core::Color`.
+fn last_segment(path: &str) -> &str {
+    match path.rfind("::") {
+        Some(index) => &path[index + 2..],
+        None => path,
+    }
+}
@@ -0,0 +1,94 @@
+use super::{pascal_case, sanitize};
+use std::borrow::Cow;
+
+fn assert_ident(ident: &str) {
+    let parsed = syn::parse_str::<syn::Ident>(ident)
+        .unwrap_or_else(|err| panic!("Could not parse {ident}: {err}"));
+    assert_eq!(ident, parsed.to_string());
+}
+
+#[test]
+fn test_verbatim() {
+    for name in [
+        "width",
+        "_private",
+        "camelCase",
+        "UPPER_2",
+        "union",
+        "raw",
+        "naïve",
+        "日本語",
+    ] {
+        assert_eq!(Cow::Borrowed(name), sanitize(name));
+    }
+}
+
+#[test]
+fn test_keywords() {
+    assert_eq!("r#type", sanitize("type"));
+    assert_eq!("r#match", sanitize("match"));
+    assert_eq!("r#async", sanitize("async"));
+    assert_eq!("r#gen", sanitize("gen"));
+    assert_eq!("self_", sanitize("self"));
+    assert_eq!("Self_", sanitize("Self"));
+    assert_eq!("super_", sanitize("super"));
+    assert_eq!("crate_", sanitize("crate"));
+}
+
+#[test]
+fn test_rewritten() {
+    assert_eq!("hit_box", sanitize("hit-box"));
+    assert_eq!("move_speed", sanitize("move speed"));
+    assert_eq!("_2x", sanitize("2x"));
+    assert_eq!("__", sanitize("_"));
+    assert_eq!("__", sanitize(""));
+    assert_eq!("__", sanitize("-"));
+    assert_eq!("日本_語", sanitize("日本-語"));
+    assert_eq!("__", sanitize("🦀"));
+    assert_eq!("_\u{301}a", sanitize("\u{301}a"));
+    assert_eq!("r_type", sanitize("r#type"));
+    assert_eq!("_type", sanitize("-type"));
+}
+
+#[test]
+fn test_always_valid() {
+    let names = [
+        "type",
+        "self",
+        "Self",
+        "super",
+        "crate",
+        "hit-box",
+        "move speed",
+        "2x",
+        "_",
+        "",
+        "-",
+        "naïve",
+        "日本語",
+        "🦀",
+        "日本-語",
+        "\u{301}a",
+        "a.b.c",
+        "r#type",
+        "0",
+        "__",
+        "foo bar-baz",
+    ];
+    for name in names {
+        assert_ident(&sanitize(name));
+    }
+}
+
+#[test]
+fn test_pascal_case() {
+    assert_eq!("Enemies", pascal_case("enemies"));
+    assert_eq!("SpawnPoints", pascal_case("spawn_points"));
+    assert_eq!("HitBox", pascal_case("hit-box"));
+    assert_eq!("MoveSpeed", pascal_case("move speed"));
+    assert_eq!("AlreadyPascal", pascal_case("AlreadyPascal"));
+    assert_eq!("2x", pascal_case("2x"));
+    assert_eq!("Naïve", pascal_case("naïve"));
+    assert_eq!("ÜberSpeed", pascal_case("über speed"));
+    assert_eq!("", pascal_case("-_-"));
+}
@@ -0,0 +1,410 @@
+//! Resolution of types that are inferred across instances.
+//!
+//! Some properties of the generated types are only known once all instances of a type have been
+//! serialized, but the code for the instances is written while serializing. This is the case for
+//! optional fields (see [`Serializer::with_optional_field_inference()`]) and for widened numeric
+//! types (see [`Serializer::with_numeric_widening()`]). Therefore, the code is written with markers
+//! around every instance of a generated type and every field value, and numbers are written as
+//! markers that record their value and their route (see [`DataPath`]) within the nearest enclosing
+//! instance. Once the types are final, [`resolve()`] replaces these markers:
+//!
+//! * The values of fields that were inferred to be optional are wrapped in `Some(..)`.
+//! * Fields that are missing in an instance are added with a value of `None`, except in instances
+//!   that are written through a constructor (see [`CALL_END`]).
+//! * Numbers are written as literals for their final type (e.g. `3.0` for an integer in a field
+//!   that was widened into a float).
+//!
+//! With name mangling (see [`Serializer::with_name_mangling()`]), the names of structs are written
+//! as markers as well, since they may change until the value is complete. These are replaced by
+//! [`rename()`] before the other markers are resolved.
+//!
+//! The markers consist of control characters, which never occur in the generated code otherwise,
+//! since these are always escaped in literals.
+//!
+//! [`Serializer::with_optional_field_inference()`]: super::Serializer::with_optional_field_inference
+//! [`Serializer::with_numeric_widening()`]: super::Serializer::with_numeric_widening
+//! [`Serializer::with_name_mangling()`]: super::Serializer::with_name_mangling
+//! [`DataPath`]: super::path::DataPath
+
+#[cfg(test)]
+mod test;
+
+use super::{literal, Enums, Error, Structs};
+use crate::model::{FieldType, Structured};
+use std::collections::{HashMap, HashSet};
+
+const INSTANCE_BEGIN: char = '\u{1}';
+const FIELD_BEGIN: char = '\u{2}';
+const NAME_END: char = '\u{3}';
+const FIELD_END_CHAR: char = '\u{4}';
+const INSTANCE_END_CHAR: char = '\u{5}';
+const NUMBER: char = '\u{6}';
+const CALL_END_CHAR: char = '\u{7}';
+const TYPE_NAME: char = '\u{8}';
+
+/// The marker for the end of the value of a field.
+pub const FIELD_END: &str = "\u{4}";
+/// The marker for the end of an instance of a generated type.
+pub const INSTANCE_END: &str = "\u{5}";
+/// The marker for the end of an instance that is written through a constructor (see
+/// [`TypeOverride::with_constructor()`]), which already passes `None` for its missing fields.
+///
+/// [`TypeOverride::with_constructor()`]: super::TypeOverride::with_constructor
+pub const CALL_END: &str = "\u{7}";
+
+/// A number that is written as a marker.
+pub enum Number<'a> {
+    /// An integer, with its decimal representation.
+    Int(&'a str),
+    /// A float, with the name of its type (e.g. `f32`) and its raw bits.
+    Float(&'static str, u64),
+}
+
+/// Creates the marker for the beginning of an instance of a generated type.
+pub fn instance_begin(name: &str, variant: Option<&str>) -> String {
+    match variant {
+        Some(variant) => format!("{INSTANCE_BEGIN}{name}::{variant}{NAME_END}"),
+        None => format!("{INSTANCE_BEGIN}{name}{NAME_END}"),
+    }
+}
+
+/// Creates the marker for the name of a struct, which may still change (see [`rename()`]).
+pub fn type_name(name: &str) -> String {
+    format!("{TYPE_NAME}{name}{NAME_END}")
+}
+
+/// Creates the marker for the beginning of the value of a field.
+pub fn field_begin(field_name: &str) -> String {
+    format!("{FIELD_BEGIN}{field_name}{NAME_END}")
+}
+
+/// Creates the marker for a number.
+///
+/// # Arguments
+///
+/// * `route`: The route of the number within the nearest enclosing instance or, if there is none,
+///   within the value that is resolved.
+/// * `number`: The number.
+/// * `suffix`: Whether the literal needs a type suffix (see [`literal::float_literal()`]).
+pub fn number(route: &str, number: Number, suffix: bool) -> String {
+    let suffix = if suffix { " suffix" } else { "" };
+    match number {
+        Number::Int(text) => format!("{NUMBER}{route}{NAME_END}int {text}{suffix}{NAME_END}"),
+        Number::Float(type_name, bits) => {
+            format!("{NUMBER}{route}{NAME_END}{type_name} {bits:x}{suffix}{NAME_END}")
+        }
+    }
+}
+
+/// Changes the routes of the number markers in the provided code that are relative to the same
+/// instance as the code itself, i.e. that are not enclosed in an instance within the code.
+///
+/// This is used when the code is wrapped in an instance after it was written (e.g. in the variant
+/// of an enum that is generated for a heterogeneous sequence).
+///
+/// # Arguments
+///
+/// * `code`: The code.
+/// * `prefix`: The route of the code. Only routes that start with this prefix are changed.
+/// * `new_prefix`: The new route of the code.
+pub fn rebase(code: &str, prefix: &str, new_prefix: &str) -> Result<String, Error> {
+    let mut out = String::with_capacity(code.len());
+    let mut depth = 0usize;
+    let mut rest = code;
+    while let Some(index) = rest.find([INSTANCE_BEGIN, INSTANCE_END_CHAR, CALL_END_CHAR, NUMBER]) {
+        let marker = rest[index..].chars().next().unwrap_or_default();
+        let end = index + marker.len_utf8();
+        out.push_str(&rest[..end]);
+        rest = &rest[end..];
+
+        match marker {
+            INSTANCE_BEGIN => depth += 1,
+            INSTANCE_END_CHAR | CALL_END_CHAR => depth = depth.saturating_sub(1),
+            _ => {
+                let (route, remainder) = split_name(rest)?;
+                match route.strip_prefix(prefix) {
+                    Some(relative) if depth == 0 => {
+                        out.push_str(new_prefix);
+                        out.push_str(relative);
+                    }
+                    _ => out.push_str(route),
+                }
+                out.push(NAME_END);
+                rest = remainder;
+            }
+        }
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+/// Replaces the markers for the names of structs (see [`type_name()`]) with their current names.
+///
+/// # Arguments
+///
+/// * `code`: The code of a value.
+/// * `renames`: The current names of the structs that were renamed, by their former names.
+/// * `visit`: Called with the current name of every struct in the code.
+pub fn rename(
+    code: &str,
+    renames: &HashMap<String, String>,
+    mut visit: impl FnMut(&str),
+) -> Result<String, Error> {
+    let mut out = String::with_capacity(code.len());
+    let mut rest = code;
+    while let Some(index) = rest.find(TYPE_NAME) {
+        out.push_str(&rest[..index]);
+        let (name, remainder) = split_name(&rest[index + TYPE_NAME.len_utf8()..])?;
+        let name = renames.get(name).map_or(name, String::as_str);
+        visit(name);
+        out.push_str(name);
+        rest = remainder;
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+fn is_marker(c: char) -> bool {
+    matches!(
+        c,
+        INSTANCE_BEGIN
+            | FIELD_BEGIN
+            | NAME_END
+            | FIELD_END_CHAR
+            | INSTANCE_END_CHAR
+            | CALL_END_CHAR
+            | NUMBER
+    )
+}
+
+fn find_structure<'a>(
+    name: &str,
+    structs: &'a Structs,
+    enums: &'a Enums,
+) -> Result<&'a Structured, Error> {
+    let structure = match name.split_once("::") {
+        Some((name, variant)) => enums.variant(name, variant),
+        None => structs.structure(name),
+    };
+    structure.ok_or_else(|| Error::Bug(format!("No type found for marker of '{name}'.")))
+}
+
+/// Splits off the name that follows a marker.
+fn split_name(code: &str) -> Result<(&str, &str), Error> {
+    code.split_once(NAME_END)
+        .ok_or_else(|| Error::Bug(String::from("Unterminated name in marker.")))
+}
+
+/// Retrieves a field of a struct or an element of a tuple struct.
+fn member<'a>(structure: &'a Structured, name: &str) -> Option<&'a FieldType> {
+    match structure {
+        Structured::Struct(structure) => structure.fields().get(name),
+        Structured::Tuple(tuple) => tuple.elements().get(name.parse::<usize>().ok()?),
+        Structured::Unit => None,
+    }
+}
+
+fn skip_options(mut field_type: &FieldType) -> Option<&FieldType> {
+    while let FieldType::Option(arg) = field_type {
+        field_type = arg.value()?;
+    }
+    Some(field_type)
+}
+
+/// Determines the type of the value at the provided route.
+///
+/// # Arguments
+///
+/// * `route`: The route of the value.
+/// * `instance`: The instance that encloses the value, if any.
+/// * `root`: The type of the value that is resolved, which is used if there is no instance.
+/// * `structs`: The generated structs.
+fn route_type<'a>(
+    route: &str,
+    instance: Option<&'a Structured>,
+    root: &'a FieldType,
+    structs: &'a Structs,
+) -> Option<&'a FieldType> {
+    let mut segments = Vec::new();
+    let mut rest = route;
+    while !rest.is_empty() {
+        if let Some(remainder) = rest.strip_prefix("[]") {
+            segments.push(None);
+            rest = remainder;
+        } else {
+            let remainder = rest.strip_prefix('.')?;
+            let end = remainder.find(['.', '[']).unwrap_or(remainder.len());
+            segments.push(Some(&remainder[..end]));
+            rest = &remainder[end..];
+        }
+    }
+
+    let mut segments = segments.into_iter();
+    let mut field_type = match instance {
+        Some(structure) => member(structure, segments.next()??)?,
+        None => root,
+    };
+    for segment in segments {
+        field_type = match (segment, skip_options(field_type)?) {
+            (None, FieldType::Sequence(arg)) | (None, FieldType::Array(arg, _)) => arg.value()?,
+            (None, FieldType::Struct(name)) => {
+                // Only structs that wrap a lookup table have no marked instances. Their entries
+                // are either the only element or the `entries` field.
+                let structure = structs.structure(name.value())?;
+                let entries = member(structure, "0").or_else(|| member(structure, "entries"))?;
+                match entries {
+                    FieldType::Sequence(arg) => arg.value()?,
+                    _ => return None,
+                }
+            }
+            (Some(index), FieldType::Tuple(arg)) => {
+                arg.value().get(index.parse::<usize>().ok()?)?
+            }
+            _ => return None,
+        };
+    }
+    skip_options(field_type)
+}
+
+/// Renders the literal for a number marker with the provided type.
+fn number_literal(number: &str, field_type: &FieldType) -> Option<String> {
+    let mut parts = number.split(' ');
+    let kind = parts.next()?;
+    let value = parts.next()?;
+    let suffix = parts.next() == Some("suffix");
+
+    let float_bits = || u64::from_str_radix(value, 16).ok();
+    Some(match (kind, field_type) {
+        (
+            "int",
+            FieldType::U8
+            | FieldType::U16
+            | FieldType::U32
+            | FieldType::U64
+            | FieldType::U128
+            | FieldType::I8
+            | FieldType::I16
+            | FieldType::I32
+            | FieldType::I64
+            | FieldType::I128
+            | FieldType::Int(_),
+        ) => value.to_string(),
+        // Widening guarantees that the integer can be represented exactly.
+        ("int", FieldType::F32) => literal::float_literal(value.parse::<f32>().ok()?, suffix),
+        ("int", FieldType::F64) => literal::float_literal(value.parse::<f64>().ok()?, suffix),
+        ("f32", FieldType::F32) => {
+            literal::float_literal(f32::from_bits(float_bits()?.try_into().ok()?), suffix)
+        }
+        ("f32", FieldType::F64) => {
+            let value = f32::from_bits(float_bits()?.try_into().ok()?);
+            literal::float_literal(f64::from(value), suffix)
+        }
+        ("f64", FieldType::F64) => literal::float_literal(f64::from_bits(float_bits()?), suffix),
+        // An existing type (e.g. from a type pass), for which the number is written as usual.
+        ("int", FieldType::Path(_)) => value.to_string(),
+        ("f32", FieldType::Path(_)) => {
+            literal::float_literal(f32::from_bits(float_bits()?.try_into().ok()?), suffix)
+        }
+        ("f64", FieldType::Path(_)) => {
+            literal::float_literal(f64::from_bits(float_bits()?), suffix)
+        }
+        _ => return None,
+    })
+}
+
+/// Replaces the markers in the provided code, based on the final types.
+///
+/// # Arguments
+///
+/// * `code`: The code of a value.
+/// * `structs`: The generated structs.
+/// * `enums`: The generated enums.
+/// * `root`: The type of the value.
+pub fn resolve(
+    code: &str,
+    structs: &Structs,
+    enums: &Enums,
+    root: &FieldType,
+) -> Result<String, Error> {
+    let mut out = String::with_capacity(code.len());
+    // The instances that enclose the current position, with the fields that were found.
+    let mut instances: Vec<(&Structured, HashSet<&str>)> = Vec::new();
+    // For every enclosing field value: whether it is wrapped in `Some(..)`.
+    let mut fields: Vec<bool> = Vec::new();
+
+    let mut rest = code;
+    while let Some(index) = rest.find(is_marker) {
+        out.push_str(&rest[..index]);
+        let marker = rest[index..].chars().next().unwrap_or_default();
+        rest = &rest[index + marker.len_utf8()..];
+
+        match marker {
+            INSTANCE_BEGIN => {
+                let (name, remainder) = split_name(rest)?;
+                rest = remainder;
+                instances.push((find_structure(name, structs, enums)?, HashSet::new()));
+            }
+            FIELD_BEGIN => {
+                let (field, remainder) = split_name(rest)?;
+                rest = remainder;
+                let Some((Structured::Struct(structure), present)) = instances.last_mut() else {
+                    return Err(Error::Bug(String::from("Field marker outside of struct.")));
+                };
+                present.insert(field);
+                let wrap = structure.is_inferred(field);
+                if wrap {
+                    out.push_str("Some(");
+                }
+                fields.push(wrap);
+            }
+            FIELD_END_CHAR => {
+                let wrap = fields
+                    .pop()
+                    .ok_or_else(|| Error::Bug(String::from("Unbalanced field markers.")))?;
+                if wrap {
+                    out.push(')');
+                }
+            }
+            INSTANCE_END_CHAR | CALL_END_CHAR => {
+                let (structure, present) = instances
+                    .pop()
+                    .ok_or_else(|| Error::Bug(String::from("Unbalanced instance markers.")))?;
+                let (Structured::Struct(structure), INSTANCE_END_CHAR) = (structure, marker) else {
+                    continue;
+                };
+                for (field, field_type) in structure.fields() {
+                    if present.contains(field.as_ref()) {
+                        continue;
+                    }
+                    if !matches!(field_type, FieldType::Option(_)) {
+                        return Err(Error::Bug(format!(
+                            "Missing field '{field}' is not optional."
+                        )));
+                    }
+                    out.push_str(field);
+                    out.push_str(": None, ");
+                }
+            }
+            NUMBER => {
+                let (route, remainder) = split_name(rest)?;
+                let (number, remainder) = split_name(remainder)?;
+                rest = remainder;
+                let instance = instances.last().map(|(structure, _)| *structure);
+                let literal = route_type(route, instance, root, structs)
+                    .and_then(|field_type| number_literal(number, field_type))
+                    .ok_or_else(|| {
+                        Error::Bug(format!(
+                            "Could not resolve the type of number at '{route}'."
+                        ))
+                    })?;
+                out.push_str(&literal);
+            }
+            _ => return Err(Error::Bug(String::from("Unexpected marker."))),
+        }
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
@@ -0,0 +1,68 @@
+use super::{field_begin, instance_begin, resolve, FIELD_END, INSTANCE_END};
+use crate::model::{FieldType, MergeOptions, NumericWidening, Structured, StructuredStruct};
+use crate::ser::{Enums, Error, Structs};
+
+fn structure(fields: &[(&'static str, FieldType)]) -> StructuredStruct {
+    let mut builder = StructuredStruct::builder(None);
+    for (name, field_type) in fields {
+        builder.field((*name).into(), field_type.clone()).unwrap();
+    }
+    builder.build().unwrap()
+}
+
+fn instance(name: &str, fields: &[(&str, &str)]) -> String {
+    let mut code = format!("{name}{} {{ ", instance_begin(name, None));
+    for (field, value) in fields {
+        code.push_str(&format!(
+            "{field}: {}{value}{FIELD_END}, ",
+            field_begin(field)
+        ));
+    }
+    code.push_str(INSTANCE_END);
+    code.push_str(" }");
+    code
+}
+
+#[test]
+fn test_resolve() {
+    let mut sample = structure(&[("a", FieldType::U8), ("b", FieldType::Bool)]);
+    sample
+        .merge_inferring(&structure(&[("a", FieldType::U8)]), NumericWidening::NONE)
+        .unwrap();
+    let mut structs = Structs {
+        options: MergeOptions {
+            infer_optional: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    structs
+        .merge(&"Sample".into(), Structured::Struct(sample))
+        .unwrap();
+
+    let code = format!(
+        "&[{}, {}]",
+        instance("Sample", &[("a", "1"), ("b", "true")]),
+        instance("Sample", &[("a", "2")]),
+    );
+    let resolved = resolve(&code, &structs, &Enums::default(), &FieldType::Unit).unwrap();
+    assert_eq!(
+        "&[Sample { a: 1, b: Some(true),  }, Sample { a: 2, b: None,  }]",
+        resolved
+    );
+}
+
+#[test]
+fn test_resolve_missing_non_optional() {
+    let mut structs = Structs::default();
+    let sample = structure(&[("a", FieldType::U8)]);
+    structs
+        .merge(&"Sample".into(), Structured::Struct(sample))
+        .unwrap();
+
+    let code = instance("Sample", &[]);
+    match resolve(&code, &structs, &Enums::default(), &FieldType::Unit) {
+        Err(Error::Bug(_)) => {}
+        other => panic!("Expected a bug, but got {other:?}"),
+    }
+}
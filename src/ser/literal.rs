@@ -0,0 +1,195 @@
+//! Rendering of Rust literals.
+//!
+//! All literals produced here are valid Rust source code that evaluates to exactly the input
+//! value. Characters that are invisible or that may change the visual order of the source code
+//! (e.g. byte order marks and bidirectional control characters) are always escaped, so that the
+//! generated code can be reviewed safely.
+
+#[cfg(test)]
+mod test;
+
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+/// A floating point type for which literals can be rendered.
+pub trait Float: dtoa::Float + FromStr + Copy {
+    /// The name of the Rust type (e.g. `f32`).
+    const TYPE_NAME: &'static str;
+
+    fn is_nan(self) -> bool;
+
+    fn is_infinite(self) -> bool;
+
+    fn is_sign_negative(self) -> bool;
+
+    /// The raw bits, widened to 64 bits.
+    fn bits(self) -> u64;
+}
+
+macro_rules! impl_float {
+    ($type:ty) => {
+        impl Float for $type {
+            const TYPE_NAME: &'static str = stringify!($type);
+
+            fn is_nan(self) -> bool {
+                <$type>::is_nan(self)
+            }
+
+            fn is_infinite(self) -> bool {
+                <$type>::is_infinite(self)
+            }
+
+            fn is_sign_negative(self) -> bool {
+                <$type>::is_sign_negative(self)
+            }
+
+            fn bits(self) -> u64 {
+                self.to_bits().into()
+            }
+        }
+    };
+}
+
+impl_float!(f32);
+impl_float!(f64);
+
+/// Renders a string literal for the provided value.
+///
+/// A raw string literal (e.g. `r#"say "hi""#`) is used if the value contains quotes or backslashes
+/// and none of its characters require escaping. Otherwise a regular, escaped string literal is
+/// used.
+pub fn str_literal(value: &str) -> String {
+    if prefers_raw(value) {
+        raw_str_literal(value)
+    } else {
+        let mut out = String::with_capacity(value.len() + 2);
+        out.push('"');
+        for c in value.chars() {
+            push_escaped(&mut out, c, '"');
+        }
+        out.push('"');
+        out
+    }
+}
+
+/// Renders a character literal for the provided value.
+pub fn char_literal(value: char) -> String {
+    let mut out = String::with_capacity(3);
+    out.push('\'');
+    push_escaped(&mut out, value, '\'');
+    out.push('\'');
+    out
+}
+
+/// Renders an expression that evaluates to exactly the provided floating point value.
+///
+/// NaN and the infinities are rendered as the associated constants of the type (e.g. `f32::NAN`).
+/// Finite values are rendered as the shortest literal that parses back to the same bit pattern.
+/// Note that the sign of negative zero is preserved (`-0.0`).
+///
+/// # Arguments
+///
+/// * `value`: The value.
+/// * `suffix`: Whether to add a type suffix (e.g. `1.5f32`). This should be used if the type can
+///   not be inferred from the context in which the literal is used.
+pub fn float_literal<F>(value: F, suffix: bool) -> String
+where
+    F: Float,
+{
+    let type_name = F::TYPE_NAME;
+    if value.is_nan() {
+        // The NaN payload is not preserved.
+        return format!("{type_name}::NAN");
+    }
+
+    if value.is_infinite() {
+        return if value.is_sign_negative() {
+            format!("{type_name}::NEG_INFINITY")
+        } else {
+            format!("{type_name}::INFINITY")
+        };
+    }
+
+    let mut buffer = dtoa::Buffer::new();
+    let text = buffer.format_finite(value);
+    let round_trips = text
+        .parse::<F>()
+        .map(|parsed| parsed.bits() == value.bits())
+        .unwrap_or(false);
+
+    if !round_trips {
+        // Should not happen with a correct shortest representation, but we never want to silently
+        // emit a different value.
+        return format!("{type_name}::from_bits({:#x})", value.bits());
+    }
+
+    if suffix {
+        format!("{text}{type_name}")
+    } else {
+        String::from(text)
+    }
+}
+
+fn prefers_raw(value: &str) -> bool {
+    value.contains(['"', '\\']) && !value.chars().any(requires_escape)
+}
+
+fn raw_str_literal(value: &str) -> String {
+    // The literal must be terminated by more hashes than any `"#...` sequence in the value.
+    let mut hashes = 0;
+    let mut current = None;
+    for c in value.chars() {
+        current = match (c, current) {
+            ('"', _) => Some(0),
+            ('#', Some(count)) => Some(count + 1),
+            _ => None,
+        };
+        if let Some(count) = current {
+            hashes = hashes.max(count + 1);
+        }
+    }
+
+    let hashes = "#".repeat(hashes);
+    format!("r{hashes}\"{value}\"{hashes}")
+}
+
+/// Determines whether the character can not be represented verbatim in a raw string literal.
+fn requires_escape(c: char) -> bool {
+    c.is_control() || is_unusual(c)
+}
+
+/// Determines whether the character is invisible or affects the rendering of surrounding text.
+fn is_unusual(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}'
+            | '\u{061C}'
+            | '\u{180E}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{2028}'..='\u{202E}'
+            | '\u{2060}'..='\u{206F}'
+            | '\u{FEFF}'
+            | '\u{FFF9}'..='\u{FFFB}'
+            | '\u{FFFE}'
+            | '\u{FFFF}'
+    )
+}
+
+fn push_escaped(out: &mut String, c: char, quote: char) {
+    match c {
+        '\\' => out.push_str("\\\\"),
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        '\0' => out.push_str("\\0"),
+        c if c == quote => {
+            out.push('\\');
+            out.push(c);
+        }
+        c if requires_escape(c) => {
+            // Writing to a String can not fail.
+            let _ = write!(out, "\\u{{{:x}}}", c as u32);
+        }
+        c => out.push(c),
+    }
+}
@@ -0,0 +1,220 @@
+use super::{char_literal, float_literal, str_literal};
+
+fn parse_str(literal: &str) -> String {
+    syn::parse_str::<syn::LitStr>(literal)
+        .unwrap_or_else(|err| panic!("Could not parse {literal}: {err}"))
+        .value()
+}
+
+fn parse_char(literal: &str) -> char {
+    syn::parse_str::<syn::LitChar>(literal)
+        .unwrap_or_else(|err| panic!("Could not parse {literal}: {err}"))
+        .value()
+}
+
+const STRINGS: &[&str] = &[
+    "",
+    "Hello",
+    "Hello \"World\"",
+    "C:\\Program Files\\",
+    "say \"#hi\"#",
+    "\"##\"#",
+    "trailing quote\"",
+    "line\nbreak",
+    "carriage\r\nreturn",
+    "tab\tstop",
+    "nul\0byte",
+    "bell\u{7}",
+    "delete\u{7f}",
+    "quote \" and newline\n",
+    "\u{feff}bom",
+    "bidi \u{202e}reversed\u{202c} text",
+    "isolate \u{2066}x\u{2069}",
+    "zero\u{200b}width",
+    "separators \u{2028}\u{2029}",
+    "unicode: äöü ß 日本語 🦀",
+    "\u{301}leading combining mark",
+    "#",
+    "\"",
+    "\\",
+];
+
+#[test]
+fn test_str_round_trip() {
+    for value in STRINGS {
+        let literal = str_literal(value);
+        assert_eq!(*value, parse_str(&literal), "Literal: {literal}");
+    }
+}
+
+#[test]
+fn test_str_plain() {
+    assert_eq!("\"Hello\"", str_literal("Hello"));
+    assert_eq!("\"unicode: äöü 🦀\"", str_literal("unicode: äöü 🦀"));
+}
+
+#[test]
+fn test_str_raw() {
+    assert_eq!("r#\"Hello \"World\"\"#", str_literal("Hello \"World\""));
+    assert_eq!("r\"C:\\dir\"", str_literal("C:\\dir"));
+    assert_eq!("r###\"say \"##hi\"###", str_literal("say \"##hi"));
+}
+
+#[test]
+fn test_str_escaped() {
+    assert_eq!("\"line\\nbreak\"", str_literal("line\nbreak"));
+    assert_eq!("\"quote \\\" and\\n\"", str_literal("quote \" and\n"));
+    assert_eq!("\"\\u{feff}bom\"", str_literal("\u{feff}bom"));
+    assert_eq!("\"a\\u{202e}b\"", str_literal("a\u{202e}b"));
+    assert_eq!("\"\\u{1b}[0m\"", str_literal("\u{1b}[0m"));
+}
+
+#[test]
+fn test_str_no_unusual_characters_in_output() {
+    for value in STRINGS {
+        let literal = str_literal(value);
+        assert!(
+            !literal
+                .chars()
+                .any(|c| c.is_control() || super::is_unusual(c)),
+            "Literal contains unescaped characters: {literal:?}"
+        );
+    }
+}
+
+#[test]
+fn test_char_round_trip() {
+    let chars = [
+        'a', '\'', '"', '\\', '\n', '\r', '\t', '\0', '\u{7}', '\u{feff}', '\u{202e}', '\u{301}',
+        'ß', '🦀', '#',
+    ];
+    for value in chars {
+        let literal = char_literal(value);
+        assert_eq!(value, parse_char(&literal), "Literal: {literal}");
+    }
+}
+
+#[test]
+fn test_char() {
+    assert_eq!("'a'", char_literal('a'));
+    assert_eq!("'\\''", char_literal('\''));
+    assert_eq!("'\"'", char_literal('"'));
+    assert_eq!("'\\\\'", char_literal('\\'));
+    assert_eq!("'\\n'", char_literal('\n'));
+    assert_eq!("'\\u{200f}'", char_literal('\u{200f}'));
+}
+
+/// Parses a finite float literal (optionally with a suffix) the way the compiler does.
+fn parse_float<F>(literal: &str) -> F
+where
+    F: std::str::FromStr,
+    F::Err: std::fmt::Display,
+{
+    let (negative, literal) = match literal.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, literal),
+    };
+    let parsed = syn::parse_str::<syn::LitFloat>(literal)
+        .unwrap_or_else(|err| panic!("Could not parse {literal}: {err}"));
+    let digits = if negative {
+        format!("-{}", parsed.base10_digits())
+    } else {
+        parsed.base10_digits().to_string()
+    };
+    digits
+        .parse()
+        .unwrap_or_else(|err| panic!("Could not parse {digits}: {err}"))
+}
+
+const F32_VALUES: &[f32] = &[
+    0.0,
+    -0.0,
+    1.0,
+    -1.0,
+    12.345,
+    0.1,
+    1e20,
+    16777216.0,
+    f32::EPSILON,
+    f32::MIN_POSITIVE,
+    f32::MAX,
+    f32::MIN,
+    std::f32::consts::PI,
+];
+
+const F64_VALUES: &[f64] = &[
+    0.0,
+    -0.0,
+    1.0,
+    -12.234567,
+    0.1,
+    1e-7,
+    1e300,
+    9007199254740993.0,
+    f64::EPSILON,
+    f64::MIN_POSITIVE,
+    f64::MAX,
+    f64::MIN,
+    std::f64::consts::E,
+];
+
+fn f32_subnormals() -> impl Iterator<Item = f32> {
+    [1, 2, 0x7f, 0x12345, 0x7fffff]
+        .into_iter()
+        .flat_map(|bits| [f32::from_bits(bits), -f32::from_bits(bits)])
+}
+
+fn f64_subnormals() -> impl Iterator<Item = f64> {
+    [1, 2, 0xff, 0x123456789, 0xfffffffffffff]
+        .into_iter()
+        .flat_map(|bits| [f64::from_bits(bits), -f64::from_bits(bits)])
+}
+
+#[test]
+fn test_f32_round_trip() {
+    for value in F32_VALUES.iter().copied().chain(f32_subnormals()) {
+        assert!(value.is_subnormal() || value.is_normal() || value == 0.0);
+        let literal = float_literal(value, false);
+        let parsed: f32 = parse_float(&literal);
+        assert_eq!(value.to_bits(), parsed.to_bits(), "Literal: {literal}");
+
+        let literal = float_literal(value, true);
+        assert!(literal.ends_with("f32"), "Literal: {literal}");
+        let parsed: f32 = parse_float(&literal);
+        assert_eq!(value.to_bits(), parsed.to_bits(), "Literal: {literal}");
+    }
+}
+
+#[test]
+fn test_f64_round_trip() {
+    for value in F64_VALUES.iter().copied().chain(f64_subnormals()) {
+        let literal = float_literal(value, false);
+        let parsed: f64 = parse_float(&literal);
+        assert_eq!(value.to_bits(), parsed.to_bits(), "Literal: {literal}");
+
+        let literal = float_literal(value, true);
+        assert!(literal.ends_with("f64"), "Literal: {literal}");
+        let parsed: f64 = parse_float(&literal);
+        assert_eq!(value.to_bits(), parsed.to_bits(), "Literal: {literal}");
+    }
+}
+
+#[test]
+fn test_float_is_always_a_float_literal() {
+    // Integral values must not be rendered as integer literals.
+    assert_eq!("100.0", float_literal(100.0f32, false));
+    assert_eq!("-0.0", float_literal(-0.0f64, false));
+    assert_eq!("0.0f64", float_literal(0.0f64, true));
+    assert_eq!("1.5f32", float_literal(1.5f32, true));
+}
+
+#[test]
+fn test_float_special_values() {
+    assert_eq!("f32::NAN", float_literal(f32::NAN, false));
+    assert_eq!("f32::NAN", float_literal(-f32::NAN, true));
+    assert_eq!("f32::INFINITY", float_literal(f32::INFINITY, false));
+    assert_eq!("f32::NEG_INFINITY", float_literal(f32::NEG_INFINITY, false));
+    assert_eq!("f64::NAN", float_literal(f64::NAN, true));
+    assert_eq!("f64::INFINITY", float_literal(f64::INFINITY, true));
+    assert_eq!("f64::NEG_INFINITY", float_literal(f64::NEG_INFINITY, false));
+}
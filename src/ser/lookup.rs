@@ -0,0 +1,311 @@
+//! Generation of lookup functions for structs that wrap tables of string keys and values.
+//!
+//! For [`Lookup::PerfectHash`], the displacements of the perfect hash table are computed by
+//! [`PerfectHash::generate()`], using the "hash, displace and compress" algorithm. The hash
+//! function is implemented twice: once in [`hash()`] and once in the code that is written by
+//! [`write_lookup()`]. These implementations must be kept in sync.
+
+#[cfg(test)]
+mod test;
+
+use super::{CodeWrite, CodeWriteContext, CodeWriter, CodeWriterResult, Error};
+use crate::model::{FieldType, Lookup, Structured, StructuredStruct};
+use linked_hash_map::LinkedHashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+
+/// The average number of keys per displacement bucket.
+const LAMBDA: usize = 5;
+
+/// The number of seeds that are tried before the generation of a table is given up.
+const MAX_SEEDS: u64 = 64;
+
+/// The number of values that are tried for the first displacement of a bucket, which bounds the
+/// number of attempts for a bucket for large tables. The second displacement is tried for every
+/// slot, since the table has no spare slots: a bucket with a single key can always be placed in any
+/// free slot.
+const MAX_FIRST_DISPLACEMENT: usize = 256;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+const MIX_SALT: u64 = 0x9e3779b97f4a7c15;
+
+/// The hashes of a single key: `(g, f1, f2)`.
+///
+/// `g` selects the displacement bucket and `f1` and `f2` are combined with the displacements to
+/// select the slot.
+type Hashes = (u32, u32, u32);
+
+/// The splitmix64 finalizer.
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Hashes the key with a seeded FNV-1a hash, which is then mixed into the three hash values.
+fn hash(key: &[u8], seed: u64) -> Hashes {
+    let mut h = FNV_OFFSET ^ seed;
+    for byte in key {
+        h ^= *byte as u64;
+        h = h.wrapping_mul(FNV_PRIME);
+    }
+    let a = mix(h);
+    let b = mix(h ^ MIX_SALT);
+    ((a >> 32) as u32, a as u32, b as u32)
+}
+
+fn displace(f1: u32, f2: u32, d1: u32, d2: u32) -> u32 {
+    d2.wrapping_add(f1.wrapping_mul(d1)).wrapping_add(f2)
+}
+
+/// A perfect hash table for a set of distinct keys.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PerfectHash {
+    seed: u64,
+    disps: Vec<(u32, u32)>,
+    slots: Vec<usize>,
+}
+
+impl PerfectHash {
+    /// Generates the perfect hash table for the provided keys, which must be distinct. Fails if
+    /// the keys are not distinct or if no table was found with any of the seeds that were tried.
+    ///
+    /// The generation is deterministic: the same keys (in the same order) always result in the
+    /// same table.
+    pub fn generate<'a>(keys: impl Iterator<Item = &'a String>) -> Result<Self, Error> {
+        let keys: Vec<&[u8]> = keys.map(|key| key.as_bytes()).collect();
+        let mut distinct = HashSet::with_capacity(keys.len());
+        if let Some(key) = keys.iter().find(|key| !distinct.insert(**key)) {
+            return Err(Error::message(format!(
+                "Can not generate a perfect hash table with duplicate key \"{}\".",
+                String::from_utf8_lossy(key)
+            )));
+        }
+
+        (0..MAX_SEEDS)
+            .find_map(|seed| Self::try_generate(&keys, seed))
+            .ok_or_else(|| {
+                Error::message(format!(
+                    "Could not generate a perfect hash table for {} keys with {MAX_SEEDS} seeds.",
+                    keys.len()
+                ))
+            })
+    }
+
+    fn try_generate(keys: &[&[u8]], seed: u64) -> Option<Self> {
+        let table_len = keys.len();
+        if table_len == 0 {
+            return Some(Self {
+                seed,
+                disps: Vec::new(),
+                slots: Vec::new(),
+            });
+        }
+
+        let hashes: Vec<Hashes> = keys.iter().map(|key| hash(key, seed)).collect();
+        let buckets_len = table_len.div_ceil(LAMBDA);
+        let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); buckets_len];
+        for (index, (g, _, _)) in hashes.iter().enumerate() {
+            buckets[*g as usize % buckets_len].push(index);
+        }
+
+        // Place the largest buckets first, since they are the hardest to place.
+        let mut order: Vec<usize> = (0..buckets_len).collect();
+        order.sort_by(|a, b| buckets[*b].len().cmp(&buckets[*a].len()).then(a.cmp(b)));
+
+        let mut disps = vec![(0, 0); buckets_len];
+        let mut slots: Vec<Option<usize>> = vec![None; table_len];
+        // Marks the slots that were claimed in the current attempt, to detect collisions within
+        // a bucket without having to clear anything between attempts.
+        let mut claimed = vec![0u64; table_len];
+        let mut attempt = 0u64;
+        let mut placements = Vec::new();
+
+        'buckets: for bucket in order.iter().map(|index| &buckets[*index]) {
+            if bucket.is_empty() {
+                continue;
+            }
+
+            let max_first_displacement = table_len.min(MAX_FIRST_DISPLACEMENT) as u32;
+            for d1 in 0..max_first_displacement {
+                'displacements: for d2 in 0..table_len as u32 {
+                    attempt += 1;
+                    placements.clear();
+
+                    for key_index in bucket {
+                        let (_, f1, f2) = hashes[*key_index];
+                        let slot = displace(f1, f2, d1, d2) as usize % table_len;
+                        if slots[slot].is_some() || claimed[slot] == attempt {
+                            continue 'displacements;
+                        }
+                        claimed[slot] = attempt;
+                        placements.push((slot, *key_index));
+                    }
+
+                    for (slot, key_index) in &placements {
+                        slots[*slot] = Some(*key_index);
+                    }
+                    let (g, _, _) = hashes[bucket[0]];
+                    disps[g as usize % buckets_len] = (d1, d2);
+                    continue 'buckets;
+                }
+            }
+
+            // No displacement works for this bucket, so we need another seed.
+            return None;
+        }
+
+        Some(Self {
+            seed,
+            disps,
+            slots: slots.into_iter().collect::<Option<Vec<_>>>()?,
+        })
+    }
+
+    /// The seed of the hash function.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The displacements, indexed by bucket.
+    pub fn disps(&self) -> &[(u32, u32)] {
+        &self.disps
+    }
+
+    /// The indices of the keys (as provided to [`generate()`](Self::generate)), indexed by slot.
+    pub fn slots(&self) -> &[usize] {
+        &self.slots
+    }
+
+    /// Creates the structure of the generated struct that holds a table.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries_type`: The type of the slice of entries.
+    pub fn structure(entries_type: FieldType) -> StructuredStruct {
+        let mut fields = LinkedHashMap::new();
+        fields.insert(Cow::Borrowed("seed"), FieldType::U64);
+        fields.insert(
+            Cow::Borrowed("disps"),
+            FieldType::Sequence(
+                Some(Box::new(FieldType::Tuple(
+                    vec![FieldType::U32, FieldType::U32].into(),
+                )))
+                .into(),
+            ),
+        );
+        fields.insert(Cow::Borrowed("entries"), entries_type);
+        StructuredStruct::new(fields)
+    }
+}
+
+/// Writes the `impl` block with the lookup function for the provided struct.
+///
+/// For [`Lookup::Sorted`], the struct is expected to be a tuple struct that wraps a single
+/// `&'static [(&'static str, V)]`. For [`Lookup::PerfectHash`], the struct is expected to have the
+/// structure that is created by [`PerfectHash::structure()`].
+///
+/// # Arguments
+///
+/// * `writer`: The writer.
+/// * `name`: The name of the struct.
+/// * `lookup`: The kind of lookup.
+/// * `structure`: The structure of the struct.
+/// * `type_paths`: The types that are written instead of overridden types, by name.
+pub fn write_lookup<W>(
+    writer: &mut CodeWriter<W>,
+    name: &str,
+    lookup: &Lookup,
+    structure: &Structured,
+    type_paths: &HashMap<String, String>,
+) -> CodeWriterResult
+where
+    W: Write,
+{
+    let value_type = table_value_type(structure);
+
+    writer.write("impl ")?;
+    writer.write(name)?;
+    writer.struct_begin()?;
+
+    match lookup {
+        Lookup::Sorted => {
+            writer.write("pub fn get(&self, key: &str) -> Option<&'static ")?;
+            value_type.write(CodeWriteContext::new(writer, false, type_paths))?;
+            writer.write(">")?;
+            writer.struct_begin()?;
+            writer.write("let entries: &'static [(&'static str, ")?;
+            value_type.write(CodeWriteContext::new(writer, false, type_paths))?;
+            writer.write(")] = self.0; ")?;
+            writer.write(
+                "match entries.binary_search_by(|(entry_key, _)| (*entry_key).cmp(key)) { \
+                Ok(index) => Some(&entries[index].1), Err(_) => None, }",
+            )?;
+            writer.struct_end()?;
+        }
+        Lookup::PerfectHash => {
+            writer.write("pub const fn get(&self, key: &str) -> Option<&'static ")?;
+            value_type.write(CodeWriteContext::new(writer, false, type_paths))?;
+            writer.write(">")?;
+            writer.struct_begin()?;
+            writer.write("let entries: &'static [(&'static str, ")?;
+            value_type.write(CodeWriteContext::new(writer, false, type_paths))?;
+            writer.write(")] = self.entries; ")?;
+            writer.write(
+                "if entries.is_empty() { return None; } \
+                let key = key.as_bytes(); \
+                let (g, f1, f2) = Self::hash(key, self.seed); \
+                let (d1, d2) = self.disps[g as usize % self.disps.len()]; \
+                let slot = d2.wrapping_add(f1.wrapping_mul(d1)).wrapping_add(f2) as usize % entries.len(); \
+                let entry = &entries[slot]; \
+                let entry_key = entry.0.as_bytes(); \
+                if entry_key.len() != key.len() { return None; } \
+                let mut index = 0; \
+                while index < key.len() { \
+                if entry_key[index] != key[index] { return None; } \
+                index += 1; } \
+                Some(&entry.1)",
+            )?;
+            writer.struct_end()?;
+            writer.write(&format!(
+                " const fn mix(mut z: u64) -> u64 {{ \
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9); \
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb); \
+                z ^ (z >> 31) }} \
+                const fn hash(key: &[u8], seed: u64) -> (u32, u32, u32) {{ \
+                let mut h = {FNV_OFFSET:#x} ^ seed; \
+                let mut index = 0; \
+                while index < key.len() {{ \
+                h ^= key[index] as u64; \
+                h = h.wrapping_mul({FNV_PRIME:#x}); \
+                index += 1; }} \
+                let a = Self::mix(h); \
+                let b = Self::mix(h ^ {MIX_SALT:#x}); \
+                ((a >> 32) as u32, a as u32, b as u32) }}"
+            ))?;
+        }
+    }
+
+    writer.struct_end()
+}
+
+/// Extracts `V` from the `&'static [(&'static str, V)]` in a structure that wraps a table.
+fn table_value_type(structure: &Structured) -> Option<&FieldType> {
+    let entries = match structure {
+        Structured::Tuple(tuple) => tuple.elements().first(),
+        Structured::Struct(inner) => inner.fields().get("entries"),
+        Structured::Unit => None,
+    };
+
+    let element = match entries {
+        Some(FieldType::Sequence(arg)) => arg.value(),
+        _ => None,
+    };
+
+    match element {
+        Some(FieldType::Tuple(arg)) => arg.value().get(1),
+        _ => None,
+    }
+}
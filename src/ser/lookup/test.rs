@@ -0,0 +1,98 @@
+use super::{displace, hash, PerfectHash};
+use std::collections::HashSet;
+
+/// Determines the slot of the provided key, in the same way as the generated lookup function.
+fn slot(table: &PerfectHash, key: &str) -> usize {
+    let (g, f1, f2) = hash(key.as_bytes(), table.seed());
+    let (d1, d2) = table.disps()[g as usize % table.disps().len()];
+    displace(f1, f2, d1, d2) as usize % table.slots().len()
+}
+
+fn assert_perfect(keys: &[String]) {
+    let table = PerfectHash::generate(keys.iter()).unwrap();
+    assert_eq!(keys.len(), table.slots().len());
+
+    let distinct: HashSet<usize> = table.slots().iter().copied().collect();
+    assert_eq!(keys.len(), distinct.len(), "Slots are not a permutation.");
+
+    for (index, key) in keys.iter().enumerate() {
+        let slot = slot(&table, key);
+        assert_eq!(
+            index,
+            table.slots()[slot],
+            "Key \"{key}\" is in the wrong slot."
+        );
+    }
+}
+
+fn to_strings(keys: &[&str]) -> Vec<String> {
+    keys.iter().map(|key| key.to_string()).collect()
+}
+
+#[test]
+fn test_empty() {
+    let table = PerfectHash::generate(std::iter::empty()).unwrap();
+    assert!(table.disps().is_empty());
+    assert!(table.slots().is_empty());
+}
+
+#[test]
+fn test_single() {
+    assert_perfect(&to_strings(&["only"]));
+}
+
+#[test]
+fn test_anagrams() {
+    // These collide under any hash that ignores the order of the bytes (e.g. a sum).
+    assert_perfect(&to_strings(&["abc", "acb", "bac", "bca", "cab", "cba"]));
+}
+
+#[test]
+fn test_java_hash_code_collisions() {
+    // These collide under Java's String.hashCode() (31 * h + c).
+    let keys: Vec<String> = ["Aa", "BB"]
+        .iter()
+        .flat_map(|a| ["Aa", "BB"].iter().map(move |b| format!("{a}{b}")))
+        .flat_map(|ab| ["Aa", "BB"].iter().map(move |c| format!("{ab}{c}")))
+        .collect();
+    assert_eq!(8, keys.len());
+    assert_perfect(&keys);
+}
+
+#[test]
+fn test_prefixes_and_empty_key() {
+    assert_perfect(&to_strings(&["", "a", "aa", "aaa", "aaaa", "aaaaa", "b"]));
+}
+
+#[test]
+fn test_many_keys() {
+    let keys: Vec<String> = (0..5000).map(|index| format!("tile_{index}")).collect();
+    assert_perfect(&keys);
+}
+
+#[test]
+fn test_large_key_set() {
+    // Without spare slots, the last buckets need a wide range of displacements to find the few
+    // slots that are still free.
+    let keys: Vec<String> = (0..200_000).map(|index| format!("entry_{index}")).collect();
+    assert_perfect(&keys);
+}
+
+#[test]
+fn test_deterministic() {
+    let keys: Vec<String> = (0..100).map(|index| format!("key{index}")).collect();
+    assert_eq!(
+        PerfectHash::generate(keys.iter()).unwrap(),
+        PerfectHash::generate(keys.iter()).unwrap()
+    );
+}
+
+#[test]
+fn test_duplicate_keys() {
+    let keys = to_strings(&["a", "b", "a"]);
+    let err = PerfectHash::generate(keys.iter()).unwrap_err();
+    assert_eq!(
+        "Can not generate a perfect hash table with duplicate key \"a\".",
+        err.to_string()
+    );
+}
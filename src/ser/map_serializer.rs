@@ -1,45 +1,255 @@
 mod key_serializer;
 
+use super::CodeWriter;
 use super::Error;
+use super::MapPosition;
 use super::Serializer;
-use crate::model::{FieldType, Structured};
-use crate::ser::map_serializer::key_serializer::MapKeySerializer;
+use crate::model::{FieldType, Lookup, NumericWidening, Structured, StructuredTuple};
+use crate::ser::lookup::PerfectHash;
+use crate::ser::map_serializer::key_serializer::{MapKey, MapKeySerializer};
 use crate::ser::struct_util::StructUtil;
 use serde::Serialize;
 use std::borrow::Cow;
 use std::io::Write;
 
-pub struct MapSerializer<'a, W> {
-    serializer: &'a mut Serializer<W>,
+/// The way in which maps with string keys are written to the output.
+///
+/// Maps with other kinds of keys (e.g. integers) are always written as a slice of entries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MapStrategy {
+    /// A generated struct with one field per key, e.g. `LevelScores { alpha: 1, beta: 2 }`.
+    #[default]
+    Struct,
+    /// A slice of entries in the order of serialization, e.g. `&[("alpha", 1), ("beta", 2)]`.
+    EntrySlice,
+    /// A generated tuple struct that wraps a slice of entries, sorted by key, e.g.
+    /// `LevelScores(&[("alpha", 1), ("beta", 2)])`. The struct gets a
+    /// `get(&self, key: &str) -> Option<&'static V>` method that performs a binary search.
+    SortedTable,
+    /// A generated struct with a perfect hash table of the entries, which is computed during
+    /// serialization, e.g. `LevelScores { seed: 0, disps: &[(0, 1)], entries: &[("beta", 2),
+    /// ("alpha", 1)] }`. The struct gets a `const fn get(&self, key: &str) -> Option<&'static V>`
+    /// method that finds a value in constant time.
+    PerfectHash,
+}
+
+/// The form in which a map is written to the output.
+///
+/// The form is determined by the first key in the map, since that is the first time we learn
+/// anything about the key type.
+enum Form {
+    /// No key has been received yet.
+    Undetermined,
+    /// A map with string keys, written as a generated struct with one field per key.
+    Struct(StructForm),
+    /// A map written as a slice of entries: `&'static [(K, V)]`.
+    Table(TableForm),
+    /// A map with string keys, written as a generated struct that wraps a slice of entries and
+    /// that provides a lookup function.
+    Lookup(LookupForm),
+}
+
+struct StructForm {
     util: StructUtil,
     name: Cow<'static, str>,
-    expected_len: Option<usize>,
+    /// The position of the map, if the name of the struct is yet to be decided.
+    position: Option<MapPosition>,
+    last_key: Option<String>,
+}
+
+struct TableForm {
+    string_keys: bool,
+    key_type: Option<FieldType>,
+    value_type: Option<FieldType>,
+    awaiting_value: bool,
+    len: usize,
+}
+
+struct LookupForm {
+    lookup: Lookup,
+    name: Cow<'static, str>,
+    /// The keys and the code for the corresponding entries.
+    entries: Vec<(String, String)>,
+    value_type: Option<FieldType>,
     last_key: Option<String>,
 }
 
+pub struct MapSerializer<'a, W> {
+    serializer: &'a mut Serializer<W>,
+    strategy: MapStrategy,
+    form: Form,
+    expected_len: Option<usize>,
+}
+
 impl<'a, W> MapSerializer<'a, W>
 where
     W: Write,
 {
-    pub fn begin(
-        serializer: &'a mut Serializer<W>,
-        name: Cow<'static, str>,
-        len: Option<usize>,
-    ) -> Result<Self, Error> {
-        let writer = &mut serializer.writer;
-        writer.write(&name)?;
-
-        // TODO: Remove this unwrap and make the length optional down to the builder...
-        let util = StructUtil::begin(len.unwrap(), &mut serializer.writer)?;
-
+    pub fn begin(serializer: &'a mut Serializer<W>, len: Option<usize>) -> Result<Self, Error> {
+        let strategy = serializer.map_strategy();
         Ok(Self {
             serializer,
+            strategy,
+            form: Form::Undetermined,
+            expected_len: len,
+        })
+    }
+
+    fn begin_string_keyed(&mut self) -> Result<Form, Error> {
+        Ok(match self.strategy {
+            MapStrategy::Struct => Form::Struct(self.begin_struct()?),
+            MapStrategy::EntrySlice => Form::Table(self.begin_table(true)?),
+            MapStrategy::SortedTable => Form::Lookup(self.begin_lookup(Lookup::Sorted)?),
+            MapStrategy::PerfectHash => Form::Lookup(self.begin_lookup(Lookup::PerfectHash)?),
+        })
+    }
+
+    fn begin_struct(&mut self) -> Result<StructForm, Error> {
+        let (name, position) = self.serializer.map_name();
+        if position.is_some() {
+            self.serializer.reserve_map_name(&name);
+        }
+        let writer = &mut self.serializer.writer;
+        if position.is_some() {
+            // The name may still change, so we capture the rest of the map until it is decided.
+            writer.begin_capture();
+        } else {
+            writer.write(&name)?;
+            writer.instance_begin_marker(&name, None)?;
+        }
+
+        let util = StructUtil::begin(self.expected_len, writer)?;
+        self.serializer.enter_map(name.clone());
+
+        Ok(StructForm {
             util,
             name,
-            expected_len: len,
+            position,
             last_key: None,
         })
     }
+
+    fn begin_table(&mut self, string_keys: bool) -> Result<TableForm, Error> {
+        let writer = &mut self.serializer.writer;
+        writer.write("&")?;
+        writer.seq_begin()?;
+
+        Ok(TableForm {
+            string_keys,
+            key_type: None,
+            value_type: None,
+            awaiting_value: false,
+            len: 0,
+        })
+    }
+
+    fn begin_lookup(&mut self, lookup: Lookup) -> Result<LookupForm, Error> {
+        let name = match self.serializer.map_name() {
+            (name, Some(position)) => self.serializer.decide_map_name(position, name, None)?,
+            (name, None) => name,
+        };
+        self.serializer.enter_lookup(name.clone());
+
+        Ok(LookupForm {
+            lookup,
+            name,
+            entries: Vec::with_capacity(self.expected_len.unwrap_or(0)),
+            value_type: None,
+            last_key: None,
+        })
+    }
+
+    fn merge_entry_type(
+        existing: &mut Option<FieldType>,
+        field_type: FieldType,
+        what: &str,
+        widening: NumericWidening,
+    ) -> Result<(), Error> {
+        if let Some(existing) = existing {
+            existing
+                .merge_with(&field_type, widening)
+                .map_err(|err| err.message_prepend(format!("Could not merge {what} types in map.")))
+        } else {
+            *existing = Some(field_type);
+            Ok(())
+        }
+    }
+
+    fn check_len(&self, actual: usize) -> Result<(), Error> {
+        if let Some(expected) = self.expected_len {
+            if actual != expected {
+                return Err(Error::message(format!(
+                    "Expected map length of {expected}, but got {actual}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn write_sorted(
+        writer: &mut CodeWriter<W>,
+        name: &str,
+        entries: &[(String, String)],
+    ) -> Result<(), Error> {
+        writer.write(name)?;
+        writer.tuple_begin()?;
+        writer.tuple_entry_begin()?;
+        writer.write("&")?;
+        writer.seq_begin()?;
+        for (_, code) in entries {
+            writer.write(code)?;
+        }
+        writer.seq_end()?;
+        writer.tuple_entry_end()?;
+        writer.tuple_end()?;
+        Ok(())
+    }
+
+    fn write_hashed(
+        writer: &mut CodeWriter<W>,
+        name: &str,
+        entries: &[(String, String)],
+        table: &PerfectHash,
+    ) -> Result<(), Error> {
+        writer.write(name)?;
+        writer.struct_begin()?;
+
+        writer.struct_entry_begin("seed")?;
+        writer.write(itoa::Buffer::new().format(table.seed()))?;
+        writer.struct_entry_end()?;
+
+        writer.struct_entry_begin("disps")?;
+        writer.write("&")?;
+        writer.seq_begin()?;
+        for (d1, d2) in table.disps() {
+            writer.seq_entry_begin()?;
+            writer.tuple_begin()?;
+            writer.write(itoa::Buffer::new().format(*d1))?;
+            writer.tuple_entry_end()?;
+            writer.write(itoa::Buffer::new().format(*d2))?;
+            writer.tuple_entry_end()?;
+            writer.tuple_end()?;
+            writer.seq_entry_end()?;
+        }
+        writer.seq_end()?;
+        writer.struct_entry_end()?;
+
+        writer.struct_entry_begin("entries")?;
+        writer.write("&")?;
+        writer.seq_begin()?;
+        for index in table.slots() {
+            writer.write(&entries[*index].1)?;
+        }
+        writer.seq_end()?;
+        writer.struct_entry_end()?;
+
+        writer.struct_end()?;
+        Ok(())
+    }
+
+    fn entry_type(key_type: FieldType, value_type: Option<FieldType>) -> Option<Box<FieldType>> {
+        value_type.map(|value_type| Box::new(FieldType::Tuple(vec![key_type, value_type].into())))
+    }
 }
 
 impl<'a, W> serde::ser::SerializeMap for MapSerializer<'a, W>
@@ -53,14 +263,78 @@ where
     where
         T: Serialize,
     {
-        let key = key.serialize(MapKeySerializer)?;
-        if let Some(last_key) = &self.last_key {
-            Err(Error::message(format!(
-                "Got key \"{key}\" without receiving the value of the previous key (\"{last_key}\")."
-            )))
-        } else {
-            self.last_key = Some(key);
-            Ok(())
+        let map_key = key.serialize(MapKeySerializer).map_err(|err| {
+            err.message_prepend(format!(
+                "Unsupported key in map at '{}'.",
+                self.serializer.path
+            ))
+        })?;
+
+        if let Form::Undetermined = self.form {
+            self.form = match map_key {
+                MapKey::Field(_) => self.begin_string_keyed()?,
+                MapKey::Scalar => Form::Table(self.begin_table(false)?),
+            };
+        }
+
+        match (&mut self.form, map_key) {
+            (Form::Struct(StructForm { last_key, .. }), MapKey::Field(key))
+            | (Form::Lookup(LookupForm { last_key, .. }), MapKey::Field(key)) => {
+                if let Some(last_key) = last_key {
+                    Err(Error::message(format!(
+                        "Got key \"{key}\" without receiving the value of the previous key (\"{last_key}\")."
+                    )))
+                } else {
+                    *last_key = Some(key);
+                    Ok(())
+                }
+            }
+            (Form::Table(form), map_key) => {
+                if form.awaiting_value {
+                    return Err(Error::message(
+                        "Got key without receiving the value of the previous key.",
+                    ));
+                }
+
+                let writer = &mut self.serializer.writer;
+                let key_type = match map_key {
+                    MapKey::Field(key) if form.string_keys => {
+                        writer.seq_entry_begin()?;
+                        writer.tuple_begin()?;
+                        writer.tuple_entry_begin()?;
+                        writer.str_literal(&key)?;
+                        FieldType::Str
+                    }
+                    MapKey::Scalar if !form.string_keys => {
+                        writer.seq_entry_begin()?;
+                        writer.tuple_begin()?;
+                        writer.tuple_entry_begin()?;
+                        self.serializer.serialize_entry_value(0, key)?
+                    }
+                    MapKey::Field(key) => {
+                        return Err(Error::message(format!(
+                            "Got string key \"{key}\" in a map with non-string keys."
+                        )))
+                    }
+                    MapKey::Scalar => {
+                        return Err(Error::message(
+                            "Got a non-string key in a map with string keys.",
+                        ))
+                    }
+                };
+                self.serializer.writer.tuple_entry_end()?;
+
+                let widening = self.serializer.widening();
+                Self::merge_entry_type(&mut form.key_type, key_type, "key", widening)?;
+                form.awaiting_value = true;
+                Ok(())
+            }
+            (Form::Struct(_), MapKey::Scalar) | (Form::Lookup(_), MapKey::Scalar) => Err(
+                Error::message("Got a non-string key in a map with string keys."),
+            ),
+            (Form::Undetermined, _) => Err(Error::Bug(String::from(
+                "Map form undetermined after receiving a key.",
+            ))),
         }
     }
 
@@ -68,25 +342,159 @@ where
     where
         T: Serialize,
     {
-        if let Some(key) = self.last_key.take() {
-            self.util.element_begin(&key, &mut self.serializer.writer)?;
-            let field_type = value.serialize(&mut *self.serializer)?;
-            self.util
-                .element_end(key.into(), &mut self.serializer.writer, field_type)
-        } else {
-            Err(Error::message(
+        match &mut self.form {
+            Form::Struct(form) => {
+                if let Some(key) = form.last_key.take() {
+                    form.util.element_begin(&key, &mut self.serializer.writer)?;
+                    let field_type = self.serializer.serialize_field_value(&key, value)?;
+                    form.util.element_end(
+                        key.into(),
+                        &mut self.serializer.writer,
+                        &self.serializer.path,
+                        field_type,
+                    )
+                } else {
+                    Err(Error::message(
+                        "Got value without previously receiving a key for it.",
+                    ))
+                }
+            }
+            Form::Table(form) if form.awaiting_value => {
+                self.serializer.writer.tuple_entry_begin()?;
+                let value_type = self.serializer.serialize_entry_value(1, value)?;
+                let writer = &mut self.serializer.writer;
+                writer.tuple_entry_end()?;
+                writer.tuple_end()?;
+                writer.seq_entry_end()?;
+
+                let widening = self.serializer.widening();
+                Self::merge_entry_type(&mut form.value_type, value_type, "value", widening)?;
+                form.awaiting_value = false;
+                form.len += 1;
+                Ok(())
+            }
+            Form::Lookup(form) if form.last_key.is_some() => {
+                let key = form.last_key.take().unwrap_or_default();
+
+                // The entries are only written at the end, once they have been sorted.
+                let writer = &mut self.serializer.writer;
+                writer.begin_capture();
+                writer.seq_entry_begin()?;
+                writer.tuple_begin()?;
+                writer.tuple_entry_begin()?;
+                writer.str_literal(&key)?;
+                writer.tuple_entry_end()?;
+                writer.tuple_entry_begin()?;
+                let value_type = self.serializer.serialize_entry_value(1, value);
+                let writer = &mut self.serializer.writer;
+                writer.tuple_entry_end()?;
+                writer.tuple_end()?;
+                writer.seq_entry_end()?;
+                let code = writer.end_capture();
+
+                let widening = self.serializer.widening();
+                Self::merge_entry_type(&mut form.value_type, value_type?, "value", widening)?;
+                form.entries.push((key, code));
+                Ok(())
+            }
+            _ => Err(Error::message(
                 "Got value without previously receiving a key for it.",
-            ))
+            )),
         }
     }
 
-    fn end(self) -> Result<Self::Ok, Self::Error> {
-        let built = self.util.end(&mut self.serializer.writer).map_err(|msg| {
-            msg.message_prepend(format!("Could not build struct '{}'", self.name))
-        })?;
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        let field_type = self.finish()?;
+        self.serializer.end_compound(&field_type)?;
+        Ok(field_type)
+    }
+}
 
-        let structure = Structured::Struct(built);
-        self.serializer.structs_mut().merge(&self.name, structure)?;
-        Ok(FieldType::Struct(self.name.into()))
+impl<'a, W> MapSerializer<'a, W>
+where
+    W: Write,
+{
+    /// Finishes the map in the form that was determined for it.
+    fn finish(&mut self) -> Result<FieldType, Error> {
+        if let Form::Undetermined = self.form {
+            // An empty map: we don't know the key type, so we follow the strategy for strings.
+            self.form = self.begin_string_keyed()?;
+        }
+
+        match std::mem::replace(&mut self.form, Form::Undetermined) {
+            Form::Struct(form) => {
+                self.serializer.leave_type();
+                let built = form.util.end(&mut self.serializer.writer).map_err(|msg| {
+                    msg.message_prepend(format!("Could not build struct '{}'", form.name))
+                })?;
+
+                let name = match form.position {
+                    Some(position) => {
+                        let body = self.serializer.writer.end_capture();
+                        let name =
+                            self.serializer
+                                .decide_map_name(position, form.name, Some(&built))?;
+                        let writer = &mut self.serializer.writer;
+                        writer.write(&name)?;
+                        writer.instance_begin_marker(&name, None)?;
+                        writer.write(&body)?;
+                        name
+                    }
+                    None => form.name,
+                };
+
+                let structure = Structured::Struct(built);
+                self.serializer.structs_mut().merge(&name, structure)?;
+                Ok(FieldType::Struct(name.into()))
+            }
+            Form::Table(form) => {
+                self.serializer.writer.seq_end()?;
+                self.check_len(form.len)?;
+
+                let entry_type = form
+                    .key_type
+                    .and_then(|key_type| Self::entry_type(key_type, form.value_type));
+                Ok(FieldType::Sequence(entry_type.into()))
+            }
+            Form::Lookup(mut form) => {
+                self.serializer.leave_type();
+                self.check_len(form.entries.len())?;
+
+                form.entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                if let Some(pair) = form.entries.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                    return Err(Error::message(format!(
+                        "Found duplicate key \"{}\" in map '{}'.",
+                        pair[0].0, form.name
+                    )));
+                }
+
+                let entry_type = Self::entry_type(FieldType::Str, form.value_type);
+                let entries_type = FieldType::Sequence(entry_type.into());
+                let structure = match form.lookup {
+                    Lookup::Sorted => {
+                        Self::write_sorted(&mut self.serializer.writer, &form.name, &form.entries)?;
+                        Structured::Tuple(StructuredTuple::new(vec![entries_type]))
+                    }
+                    Lookup::PerfectHash => {
+                        let table = PerfectHash::generate(form.entries.iter().map(|(key, _)| key))?;
+                        Self::write_hashed(
+                            &mut self.serializer.writer,
+                            &form.name,
+                            &form.entries,
+                            &table,
+                        )?;
+                        Structured::Struct(PerfectHash::structure(entries_type))
+                    }
+                };
+
+                let structs = self.serializer.structs_mut();
+                structs.merge(&form.name, structure)?;
+                structs.add_lookup(form.name.clone(), form.lookup);
+                Ok(FieldType::Struct(form.name.into()))
+            }
+            Form::Undetermined => Err(Error::Bug(String::from(
+                "Map form undetermined at the end of the map.",
+            ))),
+        }
     }
 }
@@ -1,95 +1,220 @@
 use super::Error;
 use crate::ser::dummy::Dummy;
+use serde::ser::{
+    SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple, SerializeTupleStruct,
+    SerializeTupleVariant,
+};
 use serde::Serialize;
 
+/// The kind of key that was found in a map.
+pub enum MapKey {
+    /// A string key, which can be used as a field name.
+    Field(String),
+    /// A scalar key (e.g. an integer or a unit enum variant), which must be serialized as a value.
+    ///
+    /// Compound keys (e.g. tuples or structs) are treated as scalar keys as well, since they are
+    /// written in the same way as any other value.
+    Scalar,
+}
+
+/// Determines the kind of key that was found in a map, without writing any output.
+///
+/// Keys that are themselves maps are not supported.
 pub struct MapKeySerializer;
 
+/// Accepts the elements of a compound key, which is written as a value later on.
+pub struct ScalarKey;
+
+impl SerializeSeq for ScalarKey {
+    type Ok = MapKey;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, _value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(MapKey::Scalar)
+    }
+}
+
+impl SerializeTuple for ScalarKey {
+    type Ok = MapKey;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, _value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(MapKey::Scalar)
+    }
+}
+
+impl SerializeTupleStruct for ScalarKey {
+    type Ok = MapKey;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(MapKey::Scalar)
+    }
+}
+
+impl SerializeTupleVariant for ScalarKey {
+    type Ok = MapKey;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(MapKey::Scalar)
+    }
+}
+
+impl SerializeStruct for ScalarKey {
+    type Ok = MapKey;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, _value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(MapKey::Scalar)
+    }
+}
+
+impl SerializeStructVariant for ScalarKey {
+    type Ok = MapKey;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, _key: &'static str, _value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Ok(MapKey::Scalar)
+    }
+}
+
 impl serde::ser::Serializer for MapKeySerializer {
-    type Ok = String;
+    type Ok = MapKey;
     type Error = Error;
-    type SerializeSeq = Dummy<Self::Ok, Self::Error>;
-    type SerializeTuple = Dummy<Self::Ok, Self::Error>;
-    type SerializeTupleStruct = Dummy<Self::Ok, Self::Error>;
-    type SerializeTupleVariant = Dummy<Self::Ok, Self::Error>;
+    type SerializeSeq = ScalarKey;
+    type SerializeTuple = ScalarKey;
+    type SerializeTupleStruct = ScalarKey;
+    type SerializeTupleVariant = ScalarKey;
     type SerializeMap = Dummy<Self::Ok, Self::Error>;
-    type SerializeStruct = Dummy<Self::Ok, Self::Error>;
-    type SerializeStructVariant = Dummy<Self::Ok, Self::Error>;
+    type SerializeStruct = ScalarKey;
+    type SerializeStructVariant = ScalarKey;
 
     fn serialize_bool(self, _v: bool) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_i8(self, _v: i8) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_i16(self, _v: i16) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_i32(self, _v: i32) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_i64(self, _v: i64) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
+    }
+
+    fn serialize_i128(self, _v: i128) -> Result<Self::Ok, Self::Error> {
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_u8(self, _v: u8) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_u16(self, _v: u16) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_u32(self, _v: u32) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_u64(self, _v: u64) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
+    }
+
+    fn serialize_u128(self, _v: u128) -> Result<Self::Ok, Self::Error> {
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_f64(self, _v: f64) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_char(self, _v: char) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
         // TODO: We could (only) avoid a copy here if we write directly in the output, instead of
         //       returning the raw thing.
-        Ok(String::from(v))
+        Ok(MapKey::Field(String::from(v)))
     }
 
     fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_some<T: ?Sized>(self, _value: &T) -> Result<Self::Ok, Self::Error>
     where
         T: Serialize,
     {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_unit_variant(
@@ -98,18 +223,18 @@ impl serde::ser::Serializer for MapKeySerializer {
         _variant_index: u32,
         _variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_newtype_struct<T: ?Sized>(
         self,
         _name: &'static str,
-        _value: &T,
+        value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: Serialize,
     {
-        unimplemented!("Should not be called.")
+        value.serialize(self)
     }
 
     fn serialize_newtype_variant<T: ?Sized>(
@@ -122,15 +247,15 @@ impl serde::ser::Serializer for MapKeySerializer {
     where
         T: Serialize,
     {
-        unimplemented!("Should not be called.")
+        Ok(MapKey::Scalar)
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(ScalarKey)
     }
 
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(ScalarKey)
     }
 
     fn serialize_tuple_struct(
@@ -138,7 +263,7 @@ impl serde::ser::Serializer for MapKeySerializer {
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(ScalarKey)
     }
 
     fn serialize_tuple_variant(
@@ -148,11 +273,11 @@ impl serde::ser::Serializer for MapKeySerializer {
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(ScalarKey)
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        unimplemented!("Should not be called.")
+        Err(Error::message("Maps can not be used as map keys."))
     }
 
     fn serialize_struct(
@@ -160,7 +285,7 @@ impl serde::ser::Serializer for MapKeySerializer {
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(ScalarKey)
     }
 
     fn serialize_struct_variant(
@@ -170,6 +295,6 @@ impl serde::ser::Serializer for MapKeySerializer {
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        unimplemented!("Should not be called.")
+        Ok(ScalarKey)
     }
 }
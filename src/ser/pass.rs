@@ -0,0 +1,185 @@
+use super::{Enums, Error, Structs};
+use crate::model::{FieldType, Structured};
+use std::collections::HashSet;
+
+/// A transformation of the generated structs and enums, which is run with
+/// [`Serializer::run_pass()`](super::Serializer::run_pass).
+///
+/// Every method has a default implementation that leaves the types unchanged.
+pub trait TypePass {
+    /// Rewrites the type of a field or tuple element. The type arguments (e.g. the element type of
+    /// a sequence) can be rewritten with [`FieldType::visit_mut()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `owner`: The name of the struct (e.g. `Level`) or enum variant (e.g. `Event::Spawn`).
+    /// * `field`: The name of the field, or the index of the tuple element.
+    /// * `field_type`: The type of the field.
+    fn field_type(&mut self, _owner: &str, _field: &str, _field_type: &mut FieldType) {}
+
+    /// Determines whether a variant of an enum is kept.
+    fn keep_variant(&mut self, _name: &str, _variant: &str, _structure: &Structured) -> bool {
+        true
+    }
+
+    /// Determines a new name for a struct or enum, if any. All references to the type are renamed
+    /// as well.
+    fn rename(&mut self, _name: &str) -> Option<String> {
+        None
+    }
+}
+
+/// The changes that a pass made to the types.
+pub struct Changes {
+    /// The types that were renamed, with their new names.
+    pub renamed: Vec<(String, String)>,
+    /// The fields and tuple elements whose types were rewritten, by the name of the struct or enum
+    /// variant (before any renames) and the name of the field, with their new types.
+    pub rewritten: Vec<(String, String, FieldType)>,
+}
+
+/// Runs the provided pass over the provided types. Returns the changes that the pass made, or an
+/// error with the conflicts that the pass created.
+pub fn run(
+    pass: &mut impl TypePass,
+    structs: &mut Structs,
+    enums: &mut Enums,
+) -> Result<Changes, Error> {
+    let mut rewritten = Vec::new();
+    for (name, structure) in structs.structures.iter_mut() {
+        rewrite_fields(pass, name, structure, &mut rewritten);
+    }
+    for (name, variants) in enums.enums.iter_mut() {
+        for (variant, structure) in variants.iter_mut() {
+            let owner = format!("{name}::{variant}");
+            rewrite_fields(pass, &owner, structure, &mut rewritten);
+        }
+        *variants = std::mem::take(variants)
+            .into_iter()
+            .filter(|(variant, structure)| pass.keep_variant(name, variant, &structure.resolved()))
+            .collect();
+    }
+
+    let mut conflicts = Vec::new();
+    let mut renamed = Vec::new();
+    let names: Vec<String> = structs
+        .structures()
+        .map(|(name, _)| name)
+        .chain(enums.names())
+        .map(String::from)
+        .collect();
+    for name in names {
+        let Some(new_name) = pass.rename(&name).filter(|new_name| *new_name != name) else {
+            continue;
+        };
+        if structs.structure(&new_name).is_some() || enums.names().any(|taken| taken == new_name) {
+            conflicts.push(format!(
+                "Can not rename type '{name}' to '{new_name}', since that name is already taken."
+            ));
+            continue;
+        }
+        structs.rename(&name, &new_name);
+        enums.rename(&name, &new_name);
+        renamed.push((name, new_name));
+    }
+
+    let known: HashSet<&str> = structs
+        .structures()
+        .map(|(name, _)| name)
+        .chain(enums.names())
+        .chain(structs.type_paths.keys().map(String::as_str))
+        .collect();
+    let mut check_fields = |owner: &str, structure: &Structured| {
+        for (field, field_type) in fields(structure) {
+            field_type.visit(&mut |field_type| {
+                if let Some(name) = field_type.type_name().filter(|name| !known.contains(name)) {
+                    conflicts.push(format!(
+                        "Field '{field}' of '{owner}' refers to unknown type '{name}'."
+                    ));
+                }
+            });
+        }
+    };
+    for (name, structure) in structs.structures() {
+        check_fields(name, structure);
+    }
+    for name in enums.names() {
+        for (variant, structure) in enums.variant_structures(name) {
+            check_fields(&format!("{name}::{variant}"), structure);
+        }
+    }
+
+    if conflicts.is_empty() {
+        Ok(Changes { renamed, rewritten })
+    } else {
+        Err(Error::message(format!(
+            "The type pass created conflicts. {}",
+            conflicts.join(" ")
+        )))
+    }
+}
+
+/// Passes the types of the fields or elements of the provided structure to the pass, adding the
+/// fields whose types were rewritten to `rewritten`.
+fn rewrite_fields(
+    pass: &mut impl TypePass,
+    owner: &str,
+    structure: &mut Structured,
+    rewritten: &mut Vec<(String, String, FieldType)>,
+) {
+    let mut rewrite = |field: &str, field_type: &mut FieldType| {
+        if rewrite_field(pass, owner, field, field_type) {
+            rewritten.push((owner.to_string(), field.to_string(), field_type.clone()));
+        }
+    };
+    match structure {
+        Structured::Struct(inner) => {
+            for (field, field_type) in inner.fields_mut() {
+                rewrite(field, field_type);
+            }
+        }
+        Structured::Tuple(inner) => {
+            for (index, field_type) in inner.elements_mut().iter_mut().enumerate() {
+                rewrite(&index.to_string(), field_type);
+            }
+        }
+        Structured::Unit => {}
+    }
+}
+
+/// Passes the type of a field or element to the pass, with the type that it is written as. Integers
+/// that are narrowed are only replaced if the pass changes the type. Returns whether it did.
+fn rewrite_field(
+    pass: &mut impl TypePass,
+    owner: &str,
+    field: &str,
+    field_type: &mut FieldType,
+) -> bool {
+    let mut resolved = field_type.clone();
+    resolved.resolve_int();
+    let original = resolved.clone();
+    pass.field_type(owner, field, &mut resolved);
+    if resolved == original {
+        return false;
+    }
+    *field_type = resolved;
+    true
+}
+
+/// The names (or indices) and types of the fields or elements of the provided structure.
+fn fields(structure: &Structured) -> Vec<(String, &FieldType)> {
+    match structure {
+        Structured::Struct(inner) => inner
+            .fields()
+            .iter()
+            .map(|(field, field_type)| (field.to_string(), field_type))
+            .collect(),
+        Structured::Tuple(inner) => inner
+            .elements()
+            .iter()
+            .enumerate()
+            .map(|(index, field_type)| (index.to_string(), field_type))
+            .collect(),
+        Structured::Unit => Vec::new(),
+    }
+}
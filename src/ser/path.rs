@@ -0,0 +1,110 @@
+use crate::ser::ident;
+use std::fmt::{Display, Formatter};
+
+/// The location of a value within the serialized data.
+///
+/// The textual representation consists of the names of the fields that lead to the value,
+/// separated by dots. Elements of sequences and maps with non-string keys are denoted by `[]` and
+/// elements of tuples by their index. For instance, `frames[].palette` refers to the `palette`
+/// field of every element in the `frames` sequence. The root of the data is the empty path.
+///
+/// Next to the textual representation, the path keeps track of a route, which identifies the value
+/// unambiguously within the generated types. In the route, every field is prefixed by a dot and is
+/// converted into an identifier, and the keys and values of map entries are denoted by `[].0` and
+/// `[].1`, like the elements of the tuples in `&'static [(K, V)]`.
+#[derive(Clone, Debug, Default)]
+pub struct DataPath {
+    text: String,
+    route: String,
+    /// The lengths of the text and the route before every entered field or element.
+    marks: Vec<(usize, usize)>,
+}
+
+impl DataPath {
+    /// Enters a field of a struct or a map with string keys.
+    pub fn push_field(&mut self, name: &str) {
+        self.push_segment(name, &ident::sanitize(name));
+    }
+
+    /// Enters an element of a tuple.
+    pub fn push_index(&mut self, index: usize) {
+        let mut buffer = itoa::Buffer::new();
+        let index = buffer.format(index);
+        self.push_segment(index, index);
+    }
+
+    /// Enters an element of a sequence.
+    pub fn push_element(&mut self) {
+        self.mark();
+        self.text.push_str("[]");
+        self.route.push_str("[]");
+    }
+
+    /// Enters the key (index 0) or the value (index 1) of an entry of a map that is written as a
+    /// slice of entries.
+    pub fn push_entry(&mut self, index: usize) {
+        self.push_element();
+        self.route.push('.');
+        self.route.push_str(itoa::Buffer::new().format(index));
+    }
+
+    /// Leaves the most recently entered field or element.
+    pub fn pop(&mut self) {
+        if let Some((text_len, route_len)) = self.marks.pop() {
+            self.text.truncate(text_len);
+            self.route.truncate(route_len);
+        }
+    }
+
+    fn push_segment(&mut self, text: &str, route: &str) {
+        self.mark();
+        if !self.text.is_empty() {
+            self.text.push('.');
+        }
+        self.text.push_str(text);
+        self.route.push('.');
+        self.route.push_str(route);
+    }
+
+    fn mark(&mut self) {
+        self.marks.push((self.text.len(), self.route.len()));
+    }
+
+    /// The name of the most recently entered field or tuple element, ignoring any sequence or map
+    /// elements that were entered after it.
+    pub fn last_field(&self) -> Option<&str> {
+        let mut end = self.text.len();
+        for (mark, _) in self.marks.iter().rev() {
+            let segment = &self.text[*mark..end];
+            if segment != "[]" {
+                return Some(segment.strip_prefix('.').unwrap_or(segment));
+            }
+            end = *mark;
+        }
+        None
+    }
+
+    /// Determines whether this is the path of the root of the data.
+    pub fn is_root(&self) -> bool {
+        self.marks.is_empty()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The route of the value (see [`DataPath`]).
+    pub fn route(&self) -> &str {
+        &self.route
+    }
+}
+
+impl Display for DataPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.text.is_empty() {
+            write!(f, "<root>")
+        } else {
+            write!(f, "{}", self.text)
+        }
+    }
+}
@@ -1,13 +1,22 @@
+use super::ident;
+use super::inference;
 use super::Error;
 use super::Serializer;
-use crate::model::FieldType;
+use crate::model::{FieldType, Structured, StructuredTuple};
 use serde::Serialize;
+use std::borrow::Cow;
 use std::io::Write;
 
 pub struct SeqSerializer<'a, W> {
     serializer: &'a mut Serializer<W>,
     field_type: Option<FieldType>,
+    /// The code and the type of every element, if elements of different types are wrapped in a
+    /// generated enum (see [`Serializer::with_sequence_enums()`]).
+    elements: Option<Vec<(String, FieldType)>>,
     expected_len: Option<usize>,
+    /// The length of the array, if the sequence is written as an array (see
+    /// [`TypeHint::Array`](super::TypeHint::Array)).
+    array_len: Option<usize>,
     len: usize,
 }
 
@@ -15,17 +24,136 @@ impl<'a, W> SeqSerializer<'a, W>
 where
     W: Write,
 {
-    pub fn begin(serializer: &'a mut Serializer<W>, len: Option<usize>) -> Result<Self, Error> {
-        serializer.writer.write("&")?;
+    pub fn begin(
+        serializer: &'a mut Serializer<W>,
+        len: Option<usize>,
+        array_len: Option<usize>,
+    ) -> Result<Self, Error> {
+        if array_len.is_none() {
+            serializer.writer.write("&")?;
+        }
         serializer.writer.seq_begin()?;
 
+        let elements = serializer.sequence_enums.then(Vec::new);
         Ok(Self {
             serializer,
             field_type: Option::None,
+            elements,
             expected_len: len,
+            array_len,
             len: 0,
         })
     }
+
+    /// Groups the elements by their type and writes them, wrapping them in a generated enum if
+    /// there is more than one group. Returns the type of the elements, if there are any.
+    fn write_elements(
+        &mut self,
+        elements: Vec<(String, FieldType)>,
+    ) -> Result<Option<FieldType>, Error> {
+        let widening = self.serializer.widening();
+        let mut groups: Vec<FieldType> = Vec::new();
+        let mut group_indices = Vec::with_capacity(elements.len());
+        for (_, field_type) in &elements {
+            let index = groups.iter_mut().position(|group| {
+                let mut merged = group.clone();
+                let result = merged.merge_with(field_type, widening);
+                if result.is_ok() {
+                    *group = merged;
+                }
+                result.is_ok()
+            });
+            group_indices.push(index.unwrap_or_else(|| {
+                groups.push(field_type.clone());
+                groups.len() - 1
+            }));
+        }
+
+        let name = self.serializer.sequence_enum_name(groups.len() > 1);
+        let Some(name) = name else {
+            let writer = &mut self.serializer.writer;
+            for (code, _) in &elements {
+                writer.seq_entry_begin()?;
+                writer.write(code)?;
+                writer.seq_entry_end()?;
+            }
+            return Ok(groups.pop());
+        };
+
+        let mut variants: Vec<Cow<'static, str>> = Vec::with_capacity(groups.len());
+        for group in groups {
+            let variant = self.variant_name(&name, &group, &variants);
+            let structure = Structured::Tuple(StructuredTuple::new(vec![group]));
+            self.serializer
+                .merge_variant(name.clone(), variant.clone(), structure)?;
+            variants.push(variant);
+        }
+
+        // Numbers in the elements are now relative to the variant instead of the sequence.
+        let element_route = format!("{}[]", self.serializer.number_route());
+        let writer = &mut self.serializer.writer;
+        for ((code, _), index) in elements.iter().zip(group_indices) {
+            let variant = &variants[index];
+            writer.seq_entry_begin()?;
+            writer.write(&name)?;
+            writer.write("::")?;
+            writer.write(variant)?;
+            writer.instance_begin_marker(&name, Some(variant))?;
+            writer.tuple_begin()?;
+            writer.tuple_entry_begin()?;
+            if writer.markers {
+                writer.write(&inference::rebase(code, &element_route, ".0")?)?;
+            } else {
+                writer.write(code)?;
+            }
+            writer.tuple_entry_end()?;
+            writer.tuple_end()?;
+            writer.instance_end_marker()?;
+            writer.seq_entry_end()?;
+        }
+        Ok(Some(FieldType::Enum(name.into())))
+    }
+
+    /// Determines the name of the variant for the elements of the provided type. This is the name
+    /// of the type, with a number appended if an existing variant with that name has a different
+    /// type.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the enum.
+    /// * `field_type`: The type of the elements.
+    /// * `taken`: The variants that are already used for other elements of the sequence.
+    fn variant_name(
+        &self,
+        name: &str,
+        field_type: &FieldType,
+        taken: &[Cow<'static, str>],
+    ) -> Cow<'static, str> {
+        let base = ident::type_name(field_type);
+        let mut variant = base.clone();
+        let mut counter = 1;
+        loop {
+            if !taken.contains(&variant) {
+                let existing = self.serializer.enums.variant(name, &variant);
+                let fits = match existing {
+                    None => true,
+                    Some(Structured::Tuple(tuple)) => match tuple.elements() {
+                        [element] => element
+                            .clone()
+                            .merge_with(field_type, self.serializer.widening())
+                            .is_ok(),
+                        _ => false,
+                    },
+                    Some(_) => false,
+                };
+                if fits {
+                    return variant;
+                }
+            }
+            counter += 1;
+            variant = format!("{base}{counter}").into();
+        }
+    }
 }
 
 impl<'a, W> serde::ser::SerializeSeq for SeqSerializer<'a, W>
@@ -41,13 +169,21 @@ where
     {
         self.len += 1;
 
+        if let Some(elements) = &mut self.elements {
+            self.serializer.writer.begin_capture();
+            let result = self.serializer.serialize_element_value(value);
+            let code = self.serializer.writer.end_capture();
+            elements.push((code, result?));
+            return Ok(());
+        }
+
         self.serializer.writer.seq_entry_begin()?;
-        let field_type = value.serialize(&mut *self.serializer)?;
+        let field_type = self.serializer.serialize_element_value(value)?;
         self.serializer.writer.seq_entry_end()?;
 
         if let Some(existing) = &mut self.field_type {
             existing
-                .merge(&field_type)
+                .merge_with(&field_type, self.serializer.widening())
                 .map_err(|err| err.message_prepend("Could not merge entries in sequence."))?;
         } else {
             self.field_type = Some(field_type);
@@ -56,7 +192,11 @@ where
         Ok(())
     }
 
-    fn end(self) -> Result<Self::Ok, Self::Error> {
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        let element_type = match self.elements.take() {
+            Some(elements) => self.write_elements(elements)?,
+            None => self.field_type.take(),
+        };
         self.serializer.writer.seq_end()?;
 
         if let Some(expected) = self.expected_len {
@@ -68,6 +208,19 @@ where
             }
         }
 
-        Ok(FieldType::Sequence(self.field_type.map(Box::new).into()))
+        let element_type = element_type.map(Box::new).into();
+        let field_type = match self.array_len {
+            Some(array_len) if array_len != self.len => {
+                return Err(Error::message(format!(
+                    "The sequence at '{}' has {} elements, but the type hint is an array of {array_len}.",
+                    self.serializer.path.as_str(),
+                    self.len
+                )));
+            }
+            Some(array_len) => FieldType::Array(element_type, array_len),
+            None => FieldType::Sequence(element_type),
+        };
+        self.serializer.end_compound(&field_type)?;
+        Ok(field_type)
     }
 }
@@ -2,6 +2,7 @@ use super::Error;
 use super::Serializer;
 use crate::model::{FieldType, Structured};
 use crate::ser::struct_util::StructUtil;
+use crate::ser::type_override::ConstructorCall;
 use serde::Serialize;
 use std::borrow::Cow;
 use std::io::Write;
@@ -10,6 +11,7 @@ pub struct StructSerializer<'a, W> {
     serializer: &'a mut Serializer<W>,
     util: StructUtil,
     name: Cow<'static, str>,
+    constructor: Option<ConstructorCall>,
 }
 
 impl<'a, W> StructSerializer<'a, W>
@@ -21,15 +23,16 @@ where
         name: Cow<'static, str>,
         len: usize,
     ) -> Result<Self, Error> {
-        let writer = &mut serializer.writer;
-        writer.write(&name)?;
+        let constructor = serializer.struct_instance_begin(&name)?;
 
-        let util = StructUtil::begin(len, &mut serializer.writer)?;
+        let util = StructUtil::begin(Some(len), &mut serializer.writer)?;
+        serializer.enter_type(name.clone(), None);
 
         Ok(Self {
             serializer,
             util,
             name,
+            constructor,
         })
     }
 }
@@ -50,18 +53,42 @@ where
         T: Serialize,
     {
         self.util.element_begin(key, &mut self.serializer.writer)?;
-        let field_type = value.serialize(&mut *self.serializer)?;
-        self.util
-            .element_end(key.into(), &mut self.serializer.writer, field_type)
+        if let Some(constructor) = &mut self.constructor {
+            constructor.value_begin(&mut self.serializer.writer);
+        }
+        let field_type = self.serializer.serialize_field_value(key, value)?;
+        if let Some(constructor) = &mut self.constructor {
+            constructor.value_end(key, true, &mut self.serializer.writer)?;
+        }
+        self.util.element_end(
+            key.into(),
+            &mut self.serializer.writer,
+            &self.serializer.path,
+            field_type,
+        )
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        if self.serializer.infers_optional() {
+            self.util.skip(key);
+            if let Some(constructor) = &mut self.constructor {
+                constructor.skip(key);
+            }
+        }
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.serializer.leave_type();
         let built = self.util.end(&mut self.serializer.writer).map_err(|msg| {
             msg.message_prepend(format!("Could not build struct '{}'", self.name))
         })?;
 
         let structure = Structured::Struct(built);
-        self.serializer.structs_mut().merge(&self.name, structure)?;
-        Ok(FieldType::Struct(self.name.into()))
+        let field_type =
+            self.serializer
+                .struct_instance_end(self.name, structure, self.constructor)?;
+        self.serializer.end_compound(&field_type)?;
+        Ok(field_type)
     }
 }
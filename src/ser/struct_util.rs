@@ -1,5 +1,7 @@
 use super::Error;
 use crate::model::{FieldType, StructuredStruct, StructuredStructBuilder};
+use crate::ser::ident;
+use crate::ser::path::DataPath;
 use crate::ser::CodeWriter;
 use std::borrow::Cow;
 use std::io::Write;
@@ -9,7 +11,7 @@ pub struct StructUtil {
 }
 
 impl StructUtil {
-    pub fn begin<W>(len: usize, writer: &mut CodeWriter<W>) -> Result<Self, Error>
+    pub fn begin<W>(len: Option<usize>, writer: &mut CodeWriter<W>) -> Result<Self, Error>
     where
         W: Write,
     {
@@ -24,28 +26,58 @@ impl StructUtil {
     where
         W: Write,
     {
-        writer.struct_entry_begin(name)?;
+        let ident = ident::sanitize(name);
+        writer.struct_entry_begin(&ident)?;
+        writer.field_begin_marker(&ident)?;
         Ok(())
     }
 
+    /// Ends a field.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the field, as provided to [`element_begin()`](Self::element_begin).
+    /// * `writer`: The writer.
+    /// * `path`: The path of the struct, for error reporting.
+    /// * `field_type`: The type of the field.
     pub fn element_end<W>(
         &mut self,
         name: Cow<'static, str>,
         writer: &mut CodeWriter<W>,
+        path: &DataPath,
         field_type: FieldType,
     ) -> Result<(), Error>
     where
         W: Write,
     {
+        writer.field_end_marker()?;
         writer.struct_entry_end()?;
 
-        self.builder.field(name, field_type)
+        let result = match ident::sanitize(&name) {
+            Cow::Borrowed(_) => self.builder.field(name, field_type),
+            Cow::Owned(ident) => {
+                self.builder
+                    .renamed_field(ident.into(), name.into_owned(), field_type)
+            }
+        };
+        result.map_err(|err| err.message_prepend(format!("Invalid field in struct at '{path}'.")))
+    }
+
+    /// Registers a field that was skipped, so that it can be inferred to be optional.
+    pub fn skip(&mut self, name: &'static str) {
+        match ident::sanitize(name) {
+            Cow::Borrowed(_) => self.builder.skipped_field(name.into(), None),
+            Cow::Owned(ident) => self
+                .builder
+                .skipped_field(ident.into(), Some(name.to_string())),
+        }
     }
 
     pub fn end<W>(self, writer: &mut CodeWriter<W>) -> Result<StructuredStruct, Error>
     where
         W: Write,
     {
+        writer.instance_end_marker()?;
         writer.struct_end()?;
 
         self.builder
@@ -23,12 +23,16 @@ where
         variant: Cow<'static, str>,
         len: usize,
     ) -> Result<Self, Error> {
+        serializer.check_builtin_variant(&name, &variant, false)?;
+        let path = serializer.type_path(name.clone());
         let writer = &mut serializer.writer;
-        writer.write(&name)?;
+        writer.write(&path)?;
         writer.write("::")?;
         writer.write(&variant)?;
+        writer.instance_begin_marker(&name, Some(&variant))?;
 
-        let util = StructUtil::begin(len, &mut serializer.writer)?;
+        let util = StructUtil::begin(Some(len), &mut serializer.writer)?;
+        serializer.enter_type(name.clone(), Some(variant.clone()));
 
         Ok(Self {
             serializer,
@@ -55,20 +59,34 @@ where
         T: Serialize,
     {
         self.util.element_begin(key, &mut self.serializer.writer)?;
-        let field_type = value.serialize(&mut *self.serializer)?;
-        self.util
-            .element_end(key.into(), &mut self.serializer.writer, field_type)
+        let field_type = self.serializer.serialize_field_value(key, value)?;
+        self.util.element_end(
+            key.into(),
+            &mut self.serializer.writer,
+            &self.serializer.path,
+            field_type,
+        )
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        if self.serializer.infers_optional() {
+            self.util.skip(key);
+        }
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.serializer.leave_type();
         let built = self.util.end(&mut self.serializer.writer).map_err(|msg| {
             msg.message_prepend(format!("Could not build struct '{}'", self.name))
         })?;
 
         let structure = Structured::Struct(built);
         self.serializer
-            .enums_mut()
-            .merge(&self.name, &self.variant, structure)?;
-        Ok(FieldType::Enum(self.name.into()))
+            .merge_variant(self.name.clone(), self.variant, structure)?;
+        self.serializer.update_type_path(&self.name)?;
+        let field_type = FieldType::Enum(self.name.into());
+        self.serializer.end_compound(&field_type)?;
+        Ok(field_type)
     }
 }
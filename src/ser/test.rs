@@ -1,6 +1,9 @@
-use super::{FieldType, Serializer};
+use super::{FieldType, IntegerType, MapStrategy, Serializer, TypeHint, TypeOverride, TypePass};
+use crate::model::NumericWidening;
+use crate::model::Structured;
 use crate::ser::Error;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::io::Write;
 
@@ -43,10 +46,2463 @@ test_integer!(u8, 18, U8);
 test_integer!(u16, 123, U16);
 test_integer!(u32, 31278, U32);
 test_integer!(u64, 38128731, U64);
+test_integer!(u128, 340282366920938463463374607431768211455, U128);
 test_integer!(i8, -18, I8);
 test_integer!(i16, -123, I16);
 test_integer!(i32, -31278, I32);
 test_integer!(i64, -38128731, I64);
+test_integer!(i128, -85070591730234615865843651857942052864, I128);
+
+#[test]
+fn test_128_bit_integers() {
+    #[derive(Serialize)]
+    struct Asset {
+        id: u128,
+        hash: i128,
+    }
+
+    let mut serializer = create_serializer();
+    let value = vec![
+        Asset {
+            id: u128::MAX,
+            hash: i128::MIN + 1,
+        },
+        Asset { id: 1, hash: 2 },
+    ];
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Asset {id: 340282366920938463463374607431768211455, hash: -170141183460469231731687303715884105727,  }, Asset {id: 1, hash: 2,  }, ]",
+        create_string(&serializer)
+    );
+
+    let mut types_output = Vec::new();
+    serializer.structs().write(&mut types_output).unwrap();
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Asset {pub id: u128, pub hash: i128,  }",
+        String::from_utf8(types_output).unwrap()
+    );
+}
+
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+enum Direction {
+    Up,
+    Left,
+}
+
+#[test]
+fn test_map_integer_keys() {
+    #[derive(Serialize)]
+    struct Tile {
+        solid: bool,
+    }
+
+    let mut tiles = BTreeMap::new();
+    tiles.insert(7u16, Tile { solid: true });
+    tiles.insert(300u16, Tile { solid: false });
+
+    let mut serializer = create_serializer();
+    let field_type = tiles.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        FieldType::Sequence(
+            Some(Box::new(FieldType::Tuple(
+                vec![
+                    FieldType::U16,
+                    FieldType::Struct(Cow::Borrowed("Tile").into())
+                ]
+                .into()
+            )))
+            .into()
+        ),
+        field_type
+    );
+    assert_eq!(
+        "&[(7, Tile {solid: true,  }, ), (300, Tile {solid: false,  }, ), ]",
+        create_string(&serializer)
+    );
+}
+
+#[test]
+fn test_map_scalar_keys() {
+    let mut serializer = create_serializer();
+    let mut map = BTreeMap::new();
+    map.insert('x', false);
+    map.insert('\'', true);
+    map.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[('\\'', true, ), ('x', false, ), ]",
+        create_string(&serializer)
+    );
+
+    let mut serializer = create_serializer();
+    let mut map = BTreeMap::new();
+    map.insert(true, -1i128);
+    map.serialize(&mut serializer).unwrap();
+    assert_eq!("&[(true, -1, ), ]", create_string(&serializer));
+}
+
+#[test]
+fn test_map_enum_keys() {
+    #[derive(Serialize)]
+    struct Sprite(u8);
+
+    #[derive(Serialize)]
+    struct Character {
+        sprites: BTreeMap<Direction, Sprite>,
+    }
+
+    let mut sprites = BTreeMap::new();
+    sprites.insert(Direction::Up, Sprite(1));
+    sprites.insert(Direction::Left, Sprite(2));
+    let value = Character { sprites };
+
+    let mut serializer = create_serializer();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Character {sprites: &[(Direction::Up, Sprite(1, ), ), (Direction::Left, Sprite(2, ), ), ],  }",
+        create_string(&serializer)
+    );
+
+    let mut types_output = Vec::new();
+    serializer.structs().write(&mut types_output).unwrap();
+    serializer.enums().write(&mut types_output).unwrap();
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Sprite(pub u8, );\
+         #[derive(Clone, Debug, PartialEq)] pub struct Character {pub sprites: &'static [(Direction, Sprite, )],  }\
+         #[derive(Clone, Debug, PartialEq)] pub enum Direction {Up, Left,  }",
+        String::from_utf8(types_output).unwrap()
+    );
+}
+
+#[test]
+fn test_map_mixed_keys() {
+    #[derive(Serialize)]
+    #[serde(untagged)]
+    enum Key {
+        Name(&'static str),
+        Index(u8),
+    }
+
+    struct Mixed;
+
+    impl Serialize for Mixed {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            use serde::ser::SerializeMap;
+            let mut map = serializer.serialize_map(Some(2))?;
+            map.serialize_entry(&Key::Index(1), &1u8)?;
+            map.serialize_entry(&Key::Name("two"), &2u8)?;
+            map.end()
+        }
+    }
+
+    let mut serializer = create_serializer();
+    let result = Mixed.serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(msg, "Got string key \"two\" in a map with non-string keys.");
+    } else {
+        assert!(false, "Expected failure");
+    }
+}
+
+#[derive(Serialize)]
+struct Localization {
+    language: &'static str,
+    texts: BTreeMap<&'static str, &'static str>,
+}
+
+fn create_localization() -> Localization {
+    let mut texts = BTreeMap::new();
+    texts.insert("start", "Start");
+    texts.insert("quit", "Quit");
+    Localization {
+        language: "en",
+        texts,
+    }
+}
+
+#[test]
+fn test_map_strategy_entry_slice() {
+    let mut serializer = create_serializer().with_map_strategy(MapStrategy::EntrySlice);
+    create_localization().serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Localization {language: \"en\", texts: &[(\"quit\", \"Quit\", ), (\"start\", \"Start\", ), ],  }",
+        create_string(&serializer)
+    );
+
+    let mut types_output = Vec::new();
+    serializer.structs().write(&mut types_output).unwrap();
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Localization {pub language: &'static str, pub texts: &'static [(&'static str, &'static str, )],  }",
+        String::from_utf8(types_output).unwrap()
+    );
+}
+
+#[test]
+fn test_map_strategy_sorted_table() {
+    struct Unsorted;
+
+    impl Serialize for Unsorted {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.collect_map([("gamma", 3u8), ("alpha", 1u8), ("beta", 2u8)])
+        }
+    }
+
+    let mut serializer = create_serializer().with_map_strategy(MapStrategy::SortedTable);
+    Unsorted.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Map(&[(\"alpha\", 1, ), (\"beta\", 2, ), (\"gamma\", 3, ), ], )",
+        create_string(&serializer)
+    );
+
+    let mut types_output = Vec::new();
+    serializer.structs().write(&mut types_output).unwrap();
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Map(pub &'static [(&'static str, u8, )], );\
+         impl Map {pub fn get(&self, key: &str) -> Option<&'static u8> {\
+         let entries: &'static [(&'static str, u8)] = self.0; \
+         match entries.binary_search_by(|(entry_key, _)| (*entry_key).cmp(key)) { \
+         Ok(index) => Some(&entries[index].1), Err(_) => None, } } }",
+        String::from_utf8(types_output).unwrap()
+    );
+}
+
+#[test]
+fn test_map_strategy_duplicate_key() {
+    struct Duplicate;
+
+    impl Serialize for Duplicate {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.collect_map([("alpha", 1u8), ("alpha", 2u8)])
+        }
+    }
+
+    let mut serializer = create_serializer().with_map_strategy(MapStrategy::SortedTable);
+    let result = Duplicate.serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(msg, "Found duplicate key \"alpha\" in map 'Map'.");
+    } else {
+        assert!(false, "Expected failure");
+    }
+}
+
+#[test]
+fn test_map_strategy_perfect_hash() {
+    let mut serializer = create_serializer().with_map_strategy(MapStrategy::PerfectHash);
+    create_localization().serialize(&mut serializer).unwrap();
+    let output = create_string(&serializer);
+    assert!(
+        output.starts_with("Localization {language: \"en\", texts: LocalizationTexts {seed: "),
+        "Unexpected output: {output}"
+    );
+    assert!(output.contains("(\"quit\", \"Quit\", ), "));
+    assert!(output.contains("(\"start\", \"Start\", ), "));
+
+    let mut types_output = Vec::new();
+    serializer.structs().write(&mut types_output).unwrap();
+    let types_output = String::from_utf8(types_output).unwrap();
+    assert!(types_output.contains(
+        "pub struct LocalizationTexts {pub seed: u64, pub disps: &'static [(u32, u32, )], pub entries: &'static [(&'static str, &'static str, )],  }"
+    ));
+    assert!(types_output.contains(
+        "impl LocalizationTexts {pub const fn get(&self, key: &str) -> Option<&'static &'static str> {"
+    ));
+}
+
+#[test]
+fn test_map_strategy_at_path() {
+    #[derive(Serialize)]
+    struct Game {
+        localizations: Vec<Localization>,
+        settings: BTreeMap<&'static str, u8>,
+    }
+
+    let mut settings = BTreeMap::new();
+    settings.insert("volume", 11);
+    let value = Game {
+        localizations: vec![create_localization()],
+        settings,
+    };
+
+    let mut serializer =
+        create_serializer().with_map_strategy_at("localizations[].texts", MapStrategy::EntrySlice);
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Game {localizations: &[Localization {language: \"en\", texts: &[(\"quit\", \"Quit\", ), (\"start\", \"Start\", ), ],  }, ], settings: GameSettings {volume: 11,  },  }",
+        create_string(&serializer)
+    );
+}
+
+/// Serializes its entries through iterators without an exact size hint, like a `Serialize`
+/// implementation that filters a collection would.
+struct Unsized<K, V>(Vec<(K, V)>);
+
+impl<K: Serialize, V: Serialize> Serialize for Unsized<K, V> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_map(self.0.iter().filter(|_| true).map(|(k, v)| (k, v)))
+    }
+}
+
+#[test]
+fn test_map_unknown_length() {
+    let map = Unsized(vec![("width", 3u8), ("height", 4u8)]);
+    let mut serializer = create_serializer();
+    map.serialize(&mut serializer).unwrap();
+    assert_eq!("Map {width: 3, height: 4,  }", create_string(&serializer));
+
+    let map = Unsized(vec![(1u8, "one"), (2u8, "two")]);
+    let mut serializer = create_serializer();
+    map.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[(1, \"one\", ), (2, \"two\", ), ]",
+        create_string(&serializer)
+    );
+
+    for strategy in [
+        MapStrategy::EntrySlice,
+        MapStrategy::SortedTable,
+        MapStrategy::PerfectHash,
+    ] {
+        let map = Unsized(vec![("width", 3u8), ("height", 4u8)]);
+        let mut serializer = create_serializer().with_map_strategy(strategy);
+        map.serialize(&mut serializer)
+            .unwrap_or_else(|err| panic!("Could not serialize with {strategy:?}: {err}"));
+    }
+}
+
+#[test]
+fn test_map_keys_sanitized() {
+    let map = Unsized(vec![
+        ("type", 1u8),
+        ("self", 2),
+        ("2x", 3),
+        ("hit-box", 4),
+        ("move speed", 5),
+        ("plain", 6),
+    ]);
+    let mut serializer = create_serializer();
+    map.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Map {r#type: 1, self_: 2, _2x: 3, hit_box: 4, move_speed: 5, plain: 6,  }",
+        create_string(&serializer)
+    );
+
+    let mut types_output = Vec::new();
+    serializer.structs().write(&mut types_output).unwrap();
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Map {#[doc = \"Key: `type`\"] pub r#type: u8, #[doc = \"Key: `self`\"] pub self_: u8, #[doc = \"Key: `2x`\"] pub _2x: u8, #[doc = \"Key: `hit-box`\"] pub hit_box: u8, #[doc = \"Key: `move speed`\"] pub move_speed: u8, pub plain: u8,  }",
+        String::from_utf8(types_output).unwrap()
+    );
+}
+
+#[test]
+fn test_renamed_fields_sanitized() {
+    #[derive(Serialize)]
+    #[serde(rename_all = "kebab-case")]
+    struct Hitbox {
+        hit_box: u8,
+        #[serde(rename = "fn")]
+        function: u8,
+    }
+
+    let mut serializer = create_serializer();
+    Hitbox {
+        hit_box: 1,
+        function: 2,
+    }
+    .serialize(&mut serializer)
+    .unwrap();
+    assert_eq!(
+        "Hitbox {hit_box: 1, r#fn: 2,  }",
+        create_string(&serializer)
+    );
+}
+
+#[test]
+fn test_map_keys_collision() {
+    #[derive(Serialize)]
+    struct Settings {
+        keys: Unsized<&'static str, u8>,
+    }
+
+    let value = Settings {
+        keys: Unsized(vec![("hit-box", 1), ("hit_box", 2)]),
+    };
+
+    let mut serializer = create_serializer();
+    let result = value.serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "Invalid field in struct at 'keys'. Keys \"hit-box\" and \"hit_box\" both result in field 'hit_box'."
+        );
+    } else {
+        assert!(false, "Expected failure");
+    }
+
+    let mut serializer = create_serializer();
+    let result = Unsized(vec![("a b", 1u8), ("a-b", 2)]).serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "Invalid field in struct at '<root>'. Keys \"a b\" and \"a-b\" both result in field 'a_b'."
+        );
+    } else {
+        assert!(false, "Expected failure");
+    }
+}
+
+#[derive(Serialize)]
+struct Level {
+    enemies: BTreeMap<&'static str, u8>,
+    waves: Vec<BTreeMap<&'static str, u8>>,
+    events: Vec<Event>,
+    pair: (u8, BTreeMap<&'static str, u8>),
+}
+
+#[derive(Serialize)]
+enum Event {
+    Spawn {
+        #[serde(rename = "spawn-points")]
+        spawn_points: BTreeMap<&'static str, u8>,
+    },
+}
+
+fn create_level() -> Level {
+    Level {
+        enemies: BTreeMap::from([("orc", 3), ("troll", 1)]),
+        waves: vec![BTreeMap::from([("orc", 1)])],
+        events: vec![Event::Spawn {
+            spawn_points: BTreeMap::from([("north", 1)]),
+        }],
+        pair: (1, BTreeMap::from([("nested", 2)])),
+    }
+}
+
+#[test]
+fn test_map_names() {
+    let mut serializer = create_serializer();
+    create_level().serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Level {enemies: LevelEnemies {orc: 3, troll: 1,  }, \
+         waves: &[LevelWaves {orc: 1,  }, ], \
+         events: &[Event::Spawn {spawn_points: EventSpawnSpawnPoints {north: 1,  },  }, ], \
+         pair: (1, Level1 {nested: 2,  }, ),  }",
+        create_string(&serializer)
+    );
+
+    #[derive(Serialize)]
+    struct A {
+        b_c: BTreeMap<&'static str, u8>,
+        b: AB,
+    }
+
+    #[derive(Serialize)]
+    #[allow(clippy::upper_case_acronyms)]
+    struct AB {
+        c: BTreeMap<&'static str, u8>,
+    }
+
+    let value = A {
+        b_c: BTreeMap::from([("x", 1)]),
+        b: AB {
+            c: BTreeMap::from([("y", 2)]),
+        },
+    };
+    let mut serializer = create_serializer();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "A {b_c: ABC {x: 1,  }, b: AB {c: ABC2 {y: 2,  },  },  }",
+        create_string(&serializer)
+    );
+}
+
+#[test]
+fn test_map_names_stable() {
+    #[derive(Serialize)]
+    struct Game {
+        extra: Option<BTreeMap<&'static str, u8>>,
+        level: Level,
+    }
+
+    // Adding a map in front of the level must not change the names of the maps in the level.
+    for extra in [None, Some(BTreeMap::from([("x", 1)]))] {
+        let mut serializer = create_serializer();
+        Game {
+            extra,
+            level: create_level(),
+        }
+        .serialize(&mut serializer)
+        .unwrap();
+        let output = create_string(&serializer);
+        assert!(
+            output.contains("enemies: LevelEnemies {orc: 3, troll: 1,  }"),
+            "Unexpected output: {output}"
+        );
+    }
+}
+
+#[test]
+fn test_map_naming_callback() {
+    let mut serializer = create_serializer().with_map_naming(|path, name| match path {
+        "enemies" => Some(String::from("EnemyCounts")),
+        "waves[]" => Some(format!("{name}Entry")),
+        _ => None,
+    });
+    create_level().serialize(&mut serializer).unwrap();
+    let output = create_string(&serializer);
+    assert!(
+        output.starts_with(
+            "Level {enemies: EnemyCounts {orc: 3, troll: 1,  }, \
+             waves: &[LevelWavesEntry {orc: 1,  }, ], "
+        ),
+        "Unexpected output: {output}"
+    );
+}
+
+#[test]
+fn test_map_names_reserve_serde_types() {
+    #[derive(Serialize)]
+    struct LevelEnemies {
+        orc: u8,
+    }
+
+    // A type that is seen after a map with the same name would be merged with it.
+    let value = (create_level(), LevelEnemies { orc: 1 });
+    let err = value.serialize(&mut create_serializer()).unwrap_err();
+    assert_eq!(
+        "The type 'LevelEnemies' at '1' has the same name as a struct that was generated for a \
+         map. Trace the type before serializing or name the map with `with_map_naming()`.",
+        err.to_string()
+    );
+
+    // A type that is seen first keeps its name, also for the maps inside of the map.
+    #[derive(Serialize)]
+    struct Outer {
+        inner: BTreeMap<&'static str, BTreeMap<&'static str, u8>>,
+    }
+
+    #[derive(Serialize)]
+    struct OuterInner;
+
+    let value = (
+        OuterInner,
+        Outer {
+            inner: BTreeMap::from([("a", BTreeMap::from([("x", 1)]))]),
+        },
+    );
+    let mut serializer = create_serializer();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "(OuterInner, Outer {inner: OuterInner2 {a: OuterInner2A {x: 1,  },  },  }, )",
+        create_string(&serializer)
+    );
+}
+
+fn write_structs(serializer: &Serializer<Vec<u8>>) -> String {
+    let mut types_output = Vec::new();
+    serializer.structs().write(&mut types_output).unwrap();
+    String::from_utf8(types_output).unwrap()
+}
+
+fn write_enums(serializer: &Serializer<Vec<u8>>) -> String {
+    let mut types_output = Vec::new();
+    serializer.enums().write(&mut types_output).unwrap();
+    String::from_utf8(types_output).unwrap()
+}
+
+#[test]
+fn test_map_sequence_shares_struct() {
+    let value = vec![
+        Unsized(vec![("a", 1u8), ("b", 2)]),
+        Unsized(vec![("b", 3), ("a", 4)]),
+    ];
+    let mut serializer = create_serializer();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Map {a: 1, b: 2,  }, Map {b: 3, a: 4,  }, ]",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Map {pub a: u8, pub b: u8,  }",
+        write_structs(&serializer)
+    );
+
+    let value = vec![Unsized(vec![("a", 1u8)]), Unsized(vec![("b", 2)])];
+    let mut serializer = create_serializer();
+    let result = value.serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "Error merging struct \"Map\". Conflicting fields: [\"a\"] vs [\"b\"]."
+        );
+    } else {
+        assert!(false, "Expected failure");
+    }
+}
+
+#[test]
+fn test_map_nested_in_sequence_shares_struct() {
+    #[derive(Serialize)]
+    struct Wave {
+        enemies: Unsized<&'static str, Unsized<&'static str, u8>>,
+    }
+
+    let value = vec![
+        Wave {
+            enemies: Unsized(vec![("orc", Unsized(vec![("hp", 10)]))]),
+        },
+        Wave {
+            enemies: Unsized(vec![("orc", Unsized(vec![("hp", 12)]))]),
+        },
+    ];
+    let mut serializer = create_serializer();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Wave {enemies: WaveEnemies {orc: WaveEnemiesOrc {hp: 10,  },  },  }, \
+         Wave {enemies: WaveEnemies {orc: WaveEnemiesOrc {hp: 12,  },  },  }, ]",
+        create_string(&serializer)
+    );
+}
+
+#[test]
+fn test_map_identical_keys_deduplicated() {
+    #[derive(Serialize)]
+    struct Line {
+        start: BTreeMap<&'static str, i16>,
+        end: BTreeMap<&'static str, i16>,
+        style: BTreeMap<&'static str, &'static str>,
+        variants: Vec<Shape>,
+    }
+
+    #[derive(Serialize)]
+    enum Shape {
+        Dot {
+            at: BTreeMap<&'static str, i16>,
+        },
+        Text {
+            at: BTreeMap<&'static str, &'static str>,
+        },
+    }
+
+    let point = |x, y| BTreeMap::from([("x", x), ("y", y)]);
+    let value = Line {
+        start: point(0, 0),
+        end: point(3, -4),
+        style: BTreeMap::from([("x", "dashed"), ("y", "none")]),
+        variants: vec![
+            Shape::Dot { at: point(1, 1) },
+            Shape::Text {
+                at: BTreeMap::from([("x", "left"), ("y", "top")]),
+            },
+        ],
+    };
+
+    let mut serializer = create_serializer();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Line {start: LineStart {x: 0, y: 0,  }, end: LineStart {x: 3, y: -4,  }, \
+         style: LineStyle {x: \"dashed\", y: \"none\",  }, \
+         variants: &[Shape::Dot {at: LineStart {x: 1, y: 1,  },  }, \
+         Shape::Text {at: LineStyle {x: \"left\", y: \"top\",  },  }, ],  }",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct LineStart {pub x: i16, pub y: i16,  }\
+         #[derive(Clone, Debug, PartialEq)] pub struct LineStyle {pub x: &'static str, pub y: &'static str,  }\
+         #[derive(Clone, Debug, PartialEq)] pub struct Line {pub start: LineStart, pub end: LineStart, pub style: LineStyle, pub variants: &'static [Shape],  }",
+        write_structs(&serializer)
+    );
+}
+
+#[test]
+fn test_optional_field_inference_maps() {
+    let value = vec![
+        Unsized(vec![("a", 1u8), ("b", 2)]),
+        Unsized(vec![("b", 3)]),
+        Unsized(vec![("c", 4), ("a", 5)]),
+    ];
+    let mut serializer = create_serializer().with_optional_field_inference();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Map {a: Some(1), b: Some(2), c: None,  }, Map {b: Some(3), a: None, c: None,  }, \
+         Map {c: Some(4), a: Some(5), b: None,  }, ]",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Map {pub a: Option<u8>, pub b: Option<u8>, pub c: Option<u8>,  }",
+        write_structs(&serializer)
+    );
+}
+
+#[test]
+fn test_optional_field_inference_skipped() {
+    #[derive(Serialize)]
+    struct Sprite {
+        name: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        palette: Option<u8>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<&'static str>,
+    }
+
+    #[derive(Serialize)]
+    enum Command {
+        Draw {
+            sprite: Sprite,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            flip: Option<bool>,
+        },
+    }
+
+    let sprite = |name, palette, tags| Sprite {
+        name,
+        palette,
+        tags,
+    };
+    let value = vec![
+        Command::Draw {
+            sprite: sprite("hero", None, vec![]),
+            flip: None,
+        },
+        Command::Draw {
+            sprite: sprite("enemy", Some(2), vec!["boss"]),
+            flip: Some(true),
+        },
+    ];
+    let mut serializer = create_serializer().with_optional_field_inference();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Command::Draw {sprite: Sprite {name: \"hero\", palette: None, tags: None,  }, flip: None,  }, \
+         Command::Draw {sprite: Sprite {name: \"enemy\", palette: Some(2), tags: Some(&[\"boss\", ]),  }, \
+         flip: Some(true),  }, ]",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Sprite {pub name: &'static str, pub palette: Option<u8>, pub tags: Option<&'static [&'static str]>,  }",
+        write_structs(&serializer)
+    );
+}
+
+#[test]
+fn test_optional_field_inference_disabled() {
+    #[derive(Serialize)]
+    struct Sample {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        value: Option<u8>,
+    }
+
+    let value = vec![Sample { value: Some(1) }, Sample { value: None }];
+    let mut serializer = create_serializer();
+    let result = value.serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "Error merging struct \"Sample\". Conflicting fields: [\"value\"] vs []."
+        );
+    } else {
+        assert!(false, "Expected failure");
+    }
+}
+
+/// A number that varies in type, like the numbers in JSON data.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Num {
+    U8(u8),
+    U16(u16),
+    U64(u64),
+    U128(u128),
+    I8(i8),
+    I128(i128),
+    F32(f32),
+    F64(f64),
+}
+
+#[test]
+fn test_numeric_widening_integers() {
+    #[derive(Serialize)]
+    struct Sample {
+        value: Num,
+        count: Num,
+    }
+
+    let value = vec![
+        Sample {
+            value: Num::U8(3),
+            count: Num::U8(1),
+        },
+        Sample {
+            value: Num::U16(300),
+            count: Num::U16(2),
+        },
+        Sample {
+            value: Num::I8(-1),
+            count: Num::U8(3),
+        },
+    ];
+    let mut serializer = create_serializer().with_numeric_widening(NumericWidening::ALL);
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Sample {value: 3, count: 1,  }, Sample {value: 300, count: 2,  }, \
+         Sample {value: -1, count: 3,  }, ]",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Sample {pub value: i32, pub count: u16,  }",
+        write_structs(&serializer)
+    );
+
+    let value = vec![Num::U64(u64::MAX), Num::I8(-1)];
+    let mut serializer = create_serializer().with_numeric_widening(NumericWidening::ALL);
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!("&[18446744073709551615, -1, ]", create_string(&serializer));
+}
+
+#[test]
+fn test_numeric_widening_floats() {
+    #[derive(Serialize)]
+    struct Point(Num, Num);
+
+    let value = vec![
+        Point(Num::U8(1), Num::F32(0.5)),
+        Point(Num::F32(2.5), Num::F64(0.25)),
+    ];
+    let mut serializer = create_serializer().with_numeric_widening(NumericWidening::ALL);
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Point(1.0, 0.5, ), Point(2.5, 0.25, ), ]",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Point(pub f32, pub f64, );",
+        write_structs(&serializer)
+    );
+
+    // The literals of widened floats keep the exact value of the original type.
+    let value = vec![Num::F32(0.1), Num::U16(7), Num::F64(1.5)];
+    let mut serializer = create_serializer().with_numeric_widening(NumericWidening::ALL);
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[0.10000000149011612f64, 7.0f64, 1.5f64, ]",
+        create_string(&serializer)
+    );
+}
+
+#[test]
+fn test_numeric_widening_maps() {
+    let value = vec![
+        Unsized(vec![(Num::U8(1), Num::U8(2))]),
+        Unsized(vec![(Num::U16(300), Num::F32(1.5))]),
+    ];
+    let mut serializer = create_serializer().with_numeric_widening(NumericWidening::ALL);
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[&[(1, 2.0f32, ), ], &[(300, 1.5f32, ), ], ]",
+        create_string(&serializer)
+    );
+
+    #[derive(Serialize)]
+    struct Level {
+        scores: Unsized<&'static str, Num>,
+    }
+
+    let value = Level {
+        scores: Unsized(vec![("alpha", Num::U8(1)), ("beta", Num::F32(0.5))]),
+    };
+    let mut serializer = create_serializer()
+        .with_numeric_widening(NumericWidening::ALL)
+        .with_map_strategy(MapStrategy::SortedTable);
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Level {scores: LevelScores(&[(\"alpha\", 1.0, ), (\"beta\", 0.5, ), ], ),  }",
+        create_string(&serializer)
+    );
+}
+
+#[test]
+fn test_numeric_widening_errors() {
+    let value = vec![Num::U64(1), Num::F64(0.5)];
+    let mut serializer = create_serializer().with_numeric_widening(NumericWidening::ALL);
+    let result = value.serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "Could not merge entries in sequence. Found numeric types that can not be merged without loss of precision: U64 vs F64."
+        );
+    } else {
+        assert!(false, "Expected failure");
+    }
+
+    let widening = NumericWidening {
+        larger: true,
+        ..NumericWidening::NONE
+    };
+    let value = vec![Num::U8(1), Num::U16(2), Num::I8(-1)];
+    let mut serializer = create_serializer().with_numeric_widening(widening);
+    let result = value.serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "Could not merge entries in sequence. Found conflicting field types: U16 vs I8."
+        );
+    } else {
+        assert!(false, "Expected failure");
+    }
+
+    let value = vec![Num::U8(1), Num::U16(2)];
+    let mut serializer = create_serializer();
+    let result = value.serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "Could not merge entries in sequence. Found conflicting field types: U8 vs U16."
+        );
+    } else {
+        assert!(false, "Expected failure");
+    }
+}
+
+#[test]
+fn test_recover_after_error() {
+    // The failed value leaves its code captured, which must not swallow the next value.
+    let mut serializer = create_serializer().with_numeric_widening(NumericWidening::ALL);
+    let value = vec![Num::U64(1), Num::F64(0.5)];
+    assert!(value.serialize(&mut serializer).is_err());
+    vec![Num::U8(1)].serialize(&mut serializer).unwrap();
+    assert_eq!("&[1, ]", create_string(&serializer));
+}
+
+#[test]
+fn test_integer_narrowing() {
+    #[derive(Serialize)]
+    struct Sample {
+        id: u32,
+        offset: i64,
+        values: Vec<u64>,
+    }
+
+    let value = vec![
+        Sample {
+            id: 3,
+            offset: 100,
+            values: vec![1, 2],
+        },
+        Sample {
+            id: 255,
+            offset: -200,
+            values: vec![70000],
+        },
+    ];
+    let mut serializer = create_serializer().with_integer_narrowing();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Sample {id: 3, offset: 100, values: &[1, 2, ],  }, \
+         Sample {id: 255, offset: -200, values: &[70000, ],  }, ]",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Sample {pub id: u8, pub offset: i16, \
+         pub values: &'static [u32],  }",
+        write_structs(&serializer)
+    );
+
+    // The narrowed types are inspected as the integer types that they are written as.
+    let Some(Structured::Struct(sample)) = serializer.structs().get("Sample") else {
+        panic!("Expected a struct.");
+    };
+    let field_types: Vec<_> = sample.fields().values().cloned().collect();
+    assert_eq!(
+        vec![
+            FieldType::U8,
+            FieldType::I16,
+            FieldType::Sequence(Some(Box::new(FieldType::U32)).into())
+        ],
+        field_types
+    );
+
+    let value = vec![u128::MAX];
+    let mut serializer = create_serializer().with_integer_narrowing();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[340282366920938463463374607431768211455, ]",
+        create_string(&serializer)
+    );
+}
+
+#[test]
+fn test_integer_narrowing_widening() {
+    let value = vec![Num::U64(3), Num::I8(-1), Num::F32(0.5)];
+    let mut serializer = create_serializer()
+        .with_integer_narrowing()
+        .with_numeric_widening(NumericWidening::ALL);
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!("&[3.0f32, -1.0f32, 0.5f32, ]", create_string(&serializer));
+
+    let value = vec![Num::U64(u64::MAX), Num::I8(-1)];
+    let mut serializer = create_serializer().with_integer_narrowing();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!("&[18446744073709551615, -1, ]", create_string(&serializer));
+}
+
+#[test]
+fn test_integer_narrowing_errors() {
+    let value = vec![Num::I128(i128::MIN), Num::U128(u128::MAX)];
+    let mut serializer = create_serializer().with_integer_narrowing();
+    let result = value.serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "Could not merge entries in sequence. Found integers that can not be represented by a single type: \
+             Int(IntRange { min: -170141183460469231731687303715884105728, max: 0 }) vs \
+             Int(IntRange { min: 0, max: 340282366920938463463374607431768211455 })."
+        );
+    } else {
+        assert!(false, "Expected failure");
+    }
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Dynamic {
+    Num(Num),
+    Text(&'static str),
+    Point { x: u8, y: u8 },
+    List(Vec<u8>),
+    Words(Vec<&'static str>),
+}
+
+#[test]
+fn test_sequence_enums() {
+    #[derive(Serialize)]
+    struct Level {
+        items: Vec<Dynamic>,
+    }
+
+    let value = vec![
+        Level {
+            items: vec![
+                Dynamic::Num(Num::U8(1)),
+                Dynamic::Text("two"),
+                Dynamic::Num(Num::U8(3)),
+            ],
+        },
+        Level {
+            items: vec![
+                Dynamic::List(vec![4]),
+                Dynamic::Words(vec!["five"]),
+                Dynamic::Text("six"),
+            ],
+        },
+        Level {
+            items: vec![Dynamic::Num(Num::U8(7))],
+        },
+    ];
+    let mut serializer = create_serializer().with_sequence_enums();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Level {items: &[LevelItemsElement::U8(1, ), LevelItemsElement::Str(\"two\", ), \
+         LevelItemsElement::U8(3, ), ],  }, \
+         Level {items: &[LevelItemsElement::Seq(&[4, ], ), LevelItemsElement::Seq2(&[\"five\", ], ), \
+         LevelItemsElement::Str(\"six\", ), ],  }, \
+         Level {items: &[LevelItemsElement::U8(7, ), ],  }, ]",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Level {pub items: &'static [LevelItemsElement],  }",
+        write_structs(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub enum LevelItemsElement {U8(u8, ), Str(&'static str, ), \
+         Seq(&'static [u8], ), Seq2(&'static [&'static str], ),  }",
+        write_enums(&serializer)
+    );
+
+    // Homogeneous sequences are not wrapped.
+    let value = vec![Dynamic::Text("one"), Dynamic::Text("two")];
+    let mut serializer = create_serializer().with_sequence_enums();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!("&[\"one\", \"two\", ]", create_string(&serializer));
+    assert_eq!("", write_enums(&serializer));
+}
+
+#[test]
+fn test_sequence_enums_structs() {
+    let value = vec![
+        Dynamic::Point { x: 1, y: 2 },
+        Dynamic::Num(Num::U16(300)),
+        Dynamic::Point { x: 3, y: 4 },
+    ];
+    let mut serializer = create_serializer().with_sequence_enums();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Element::Dynamic(Dynamic {x: 1, y: 2,  }, ), Element::U16(300, ), \
+         Element::Dynamic(Dynamic {x: 3, y: 4,  }, ), ]",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Dynamic {pub x: u8, pub y: u8,  }",
+        write_structs(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub enum Element {Dynamic(Dynamic, ), U16(u16, ),  }",
+        write_enums(&serializer)
+    );
+}
+
+#[test]
+fn test_sequence_enums_widening() {
+    let value = vec![
+        Dynamic::Num(Num::U8(1)),
+        Dynamic::Text("two"),
+        Dynamic::Num(Num::F32(0.5)),
+        Dynamic::List(vec![3]),
+    ];
+    let mut serializer = create_serializer()
+        .with_sequence_enums()
+        .with_numeric_widening(NumericWidening::ALL);
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Element::F32(1.0f32, ), Element::Str(\"two\", ), Element::F32(0.5f32, ), \
+         Element::Seq(&[3, ], ), ]",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub enum Element {F32(f32, ), Str(&'static str, ), \
+         Seq(&'static [u8], ),  }",
+        write_enums(&serializer)
+    );
+
+    #[derive(Serialize)]
+    struct Level {
+        items: Vec<(u8, Dynamic)>,
+    }
+
+    let value = Level {
+        items: vec![
+            (1, Dynamic::Num(Num::U8(2))),
+            (3, Dynamic::Text("four")),
+            (5, Dynamic::Num(Num::F64(0.5))),
+        ],
+    };
+    let mut serializer = create_serializer()
+        .with_sequence_enums()
+        .with_numeric_widening(NumericWidening::ALL);
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Level {items: &[LevelItemsElement::Tuple((1, 2.0, ), ), \
+         LevelItemsElement::Tuple2((3, \"four\", ), ), LevelItemsElement::Tuple((5, 0.5, ), ), ],  }",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub enum LevelItemsElement {Tuple((u8, f64, ), ), \
+         Tuple2((u8, &'static str, ), ),  }",
+        write_enums(&serializer)
+    );
+}
+
+#[test]
+fn test_name_mangling() {
+    #[derive(Serialize)]
+    struct Palette(u8, u8);
+
+    #[derive(Serialize)]
+    struct Wrapper<T> {
+        value: T,
+    }
+
+    #[derive(Serialize)]
+    struct Pair<A, B>(A, B);
+
+    #[derive(Serialize)]
+    struct Level {
+        id: Wrapper<u8>,
+        palette: Wrapper<Palette>,
+        ids: Vec<Wrapper<u8>>,
+        pairs: (Pair<u8, &'static str>, Pair<&'static str, &'static str>),
+    }
+
+    let value = Level {
+        id: Wrapper { value: 1 },
+        palette: Wrapper {
+            value: Palette(2, 3),
+        },
+        ids: vec![Wrapper { value: 4 }],
+        pairs: (Pair(5, "six"), Pair("seven", "eight")),
+    };
+    let mut serializer = create_serializer().with_name_mangling();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Level {id: WrapperU8 {value: 1,  }, palette: WrapperPalette {value: Palette(2, 3, ),  }, \
+         ids: &[WrapperU8 {value: 4,  }, ], pairs: (PairU8(5, \"six\", ), PairStr(\"seven\", \"eight\", ), ),  }",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct WrapperU8 {pub value: u8,  }\
+         #[derive(Clone, Debug, PartialEq)] pub struct Palette(pub u8, pub u8, );\
+         #[derive(Clone, Debug, PartialEq)] pub struct WrapperPalette {pub value: Palette,  }\
+         #[derive(Clone, Debug, PartialEq)] pub struct PairU8(pub u8, pub &'static str, );\
+         #[derive(Clone, Debug, PartialEq)] pub struct PairStr(pub &'static str, pub &'static str, );\
+         #[derive(Clone, Debug, PartialEq)] pub struct Level {pub id: WrapperU8, pub palette: WrapperPalette, \
+         pub ids: &'static [WrapperU8], pub pairs: (PairU8, PairStr, ),  }",
+        write_structs(&serializer)
+    );
+
+    // The names are also mangled when instances are marked.
+    let expected = create_string(&serializer);
+    let mut serializer = create_serializer()
+        .with_name_mangling()
+        .with_optional_field_inference();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(expected, create_string(&serializer));
+
+    let mut serializer = create_serializer();
+    let result = value.serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "Error merging struct \"Wrapper\". Could not merge field \"value\". Found conflicting field types: U8 vs Struct(StructArg(\"Palette\"))."
+        );
+    } else {
+        assert!(false, "Expected failure");
+    }
+
+    // The names do not depend on the order of the instances.
+    let mut serializer = create_serializer().with_name_mangling();
+    (
+        Wrapper {
+            value: Palette(2, 3),
+        },
+        Wrapper { value: 1u8 },
+    )
+        .serialize(&mut serializer)
+        .unwrap();
+    assert_eq!(
+        "(WrapperPalette {value: Palette(2, 3, ),  }, WrapperU8 {value: 1,  }, )",
+        create_string(&serializer)
+    );
+
+    // A struct that was written in a previous value can not be renamed, unless all values are
+    // collected first.
+    let mut serializer = create_serializer().with_name_mangling();
+    vec![Wrapper { value: 1u8 }]
+        .serialize(&mut serializer)
+        .unwrap();
+    let result = vec![Wrapper { value: "one" }].serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "The struct 'Wrapper' was already written when a conflicting instance was found at \
+             '[]', so it can not be renamed to 'WrapperU8'. Collect all values with `collect()` \
+             before writing them."
+        );
+    } else {
+        assert!(false, "Expected failure");
+    }
+
+    let mut serializer = create_serializer().with_name_mangling();
+    serializer.collect(&vec![Wrapper { value: 1u8 }]).unwrap();
+    serializer.collect(&vec![Wrapper { value: "one" }]).unwrap();
+    vec![Wrapper { value: 1u8 }]
+        .serialize(&mut serializer)
+        .unwrap();
+    vec![Wrapper { value: "one" }]
+        .serialize(&mut serializer)
+        .unwrap();
+    assert_eq!(
+        "&[WrapperU8 {value: 1,  }, ]&[WrapperStr {value: \"one\",  }, ]",
+        create_string(&serializer)
+    );
+
+    // A mangled name that is taken by another type results in an error.
+    #[derive(Serialize)]
+    struct WrapperStr;
+
+    let mut serializer = create_serializer().with_name_mangling();
+    let value = (WrapperStr, Wrapper { value: 1u8 }, Wrapper { value: "one" });
+    let result = value.serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "The name 'WrapperStr' for an instance of struct 'Wrapper' is already taken by another \
+             type."
+        );
+    } else {
+        assert!(false, "Expected failure");
+    }
+}
+
+#[test]
+fn test_type_overrides() {
+    #[derive(Serialize)]
+    struct Color {
+        r: u8,
+        g: u8,
+        b: u8,
+    }
+
+    #[derive(Serialize)]
+    struct Fixed(i32);
+
+    #[derive(Serialize)]
+    enum Direction {
+        Up,
+        Offset(i8, i8),
+    }
+
+    #[derive(Serialize)]
+    struct Sprite {
+        color: Color,
+        scale: Fixed,
+        directions: Vec<Direction>,
+    }
+
+    let value = Sprite {
+        color: Color { r: 1, g: 2, b: 3 },
+        scale: Fixed(256),
+        directions: vec![Direction::Up, Direction::Offset(-1, 1)],
+    };
+
+    // Overridden types are written as literals with the path of the existing type.
+    let mut serializer = create_serializer()
+        .with_type_override("Color", TypeOverride::new("::ves_core::Color"))
+        .with_type_override("Direction", TypeOverride::new("crate::Direction"));
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Sprite {color: ::ves_core::Color {r: 1, g: 2, b: 3,  }, scale: Fixed(256, ), \
+         directions: &[crate::Direction::Up, crate::Direction::Offset(-1, 1, ), ],  }",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Fixed(pub i32, );\
+         #[derive(Clone, Debug, PartialEq)] pub struct Sprite {pub color: ::ves_core::Color, \
+         pub scale: Fixed, pub directions: &'static [crate::Direction],  }",
+        write_structs(&serializer)
+    );
+    assert_eq!("", write_enums(&serializer));
+
+    // Constructors are filled with the values of the fields or elements.
+    let constructor_serializer = || {
+        create_serializer()
+            .with_type_override(
+                "Color",
+                TypeOverride::new("Color").with_constructor("Color::rgb({r}, {g}, {b})"),
+            )
+            .with_type_override(
+                "Fixed",
+                TypeOverride::new("Fixed").with_constructor("Fixed::from_bits({0}) /* {{0}} */"),
+            )
+    };
+    let expected = "Sprite {color: Color::rgb(1, 2, 3), scale: Fixed::from_bits(256) /* {0} */, \
+                    directions: &[Direction::Up, Direction::Offset(-1, 1, ), ],  }";
+    let mut serializer = constructor_serializer();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(expected, create_string(&serializer));
+
+    // The values are still inferred when instances are marked.
+    let mut serializer = constructor_serializer().with_optional_field_inference();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(expected, create_string(&serializer));
+
+    let mut serializer = create_serializer().with_type_override(
+        "Color",
+        TypeOverride::new("Color").with_constructor("Color::rgba({r}, {g}, {b}, {a})"),
+    );
+    let result = value.serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "Invalid constructor for struct 'Color'. Unknown field 'a'."
+        );
+    } else {
+        panic!("Expected error.");
+    }
+}
+
+#[test]
+fn test_type_override_skipped_fields() {
+    #[derive(Serialize)]
+    struct Color {
+        r: u8,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        a: Option<u8>,
+    }
+
+    let serializer = || {
+        create_serializer()
+            .with_optional_field_inference()
+            .with_type_override(
+                "Color",
+                TypeOverride::new("Color").with_constructor("Color::new({r}, {a})"),
+            )
+    };
+
+    // Skipped fields are passed as `None`, since they are inferred to be optional.
+    let value = vec![Color { r: 1, a: None }, Color { r: 2, a: Some(3) }];
+    let mut serializer = serializer();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Color::new(1, None), Color::new(2, Some(3)), ]",
+        create_string(&serializer)
+    );
+
+    // The next value is written normally after a constructor fails.
+    let mut serializer = create_serializer()
+        .with_optional_field_inference()
+        .with_type_override(
+            "Color",
+            TypeOverride::new("Color").with_constructor("Color::new({r}, {g})"),
+        );
+    assert!(value.serialize(&mut serializer).is_err());
+    vec![1u8].serialize(&mut serializer).unwrap();
+    assert_eq!("&[1, ]", create_string(&serializer));
+}
+
+#[test]
+fn test_builtin_types() {
+    use std::marker::PhantomData;
+    use std::ops::{Range, RangeInclusive};
+    use std::time::Duration;
+
+    #[derive(Serialize)]
+    struct Animation {
+        delay: Duration,
+        frames: Range<u8>,
+        loops: RangeInclusive<i16>,
+        marker: PhantomData<u64>,
+        results: Vec<Result<u8, &'static str>>,
+    }
+
+    let value = Animation {
+        delay: Duration::from_millis(1500),
+        frames: 2..5,
+        loops: -1..=3,
+        marker: PhantomData,
+        results: vec![Ok(1), Err("two")],
+    };
+    let mut serializer = create_serializer();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Animation {delay: ::core::time::Duration::new(1, 500000000), frames: 2..5, loops: -1..=3, \
+         marker: ::core::marker::PhantomData, results: &[::core::result::Result::Ok(1, ), \
+         ::core::result::Result::Err(\"two\", ), ],  }",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Animation {pub delay: ::core::time::Duration, \
+         pub frames: ::core::ops::Range<u8>, pub loops: ::core::ops::RangeInclusive<i16>, \
+         pub marker: ::core::marker::PhantomData<()>, \
+         pub results: &'static [::core::result::Result<u8, &'static str>],  }",
+        write_structs(&serializer)
+    );
+    assert_eq!("", write_enums(&serializer));
+
+    // Variants that do not occur in the data have the unit type.
+    #[derive(Serialize)]
+    struct Outcome {
+        value: Result<Range<u8>, u8>,
+        timeout: Option<Duration>,
+    }
+
+    let value = Outcome {
+        value: Ok(1..2),
+        timeout: Some(Duration::from_secs(3)),
+    };
+    let mut serializer = create_serializer().with_optional_field_inference();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Outcome {value: ::core::result::Result::Ok(1..2, ), \
+         timeout: Some(::core::time::Duration::new(3, 0)),  }",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Outcome {\
+         pub value: ::core::result::Result<::core::ops::Range<u8>, ()>, \
+         pub timeout: Option<::core::time::Duration>,  }",
+        write_structs(&serializer)
+    );
+
+    // Types with the same names but other fields or variants are generated.
+    mod custom {
+        #[derive(serde::Serialize)]
+        pub struct Duration {
+            pub ms: u32,
+        }
+
+        #[derive(serde::Serialize)]
+        pub enum Result {
+            Pass,
+            Fail(u8),
+        }
+
+        #[derive(serde::Serialize)]
+        pub struct Range {
+            pub start: u8,
+            pub end: u8,
+        }
+
+        #[derive(serde::Serialize)]
+        pub struct Test {
+            pub timeout: Duration,
+            pub results: Vec<Result>,
+        }
+    }
+
+    let value = custom::Test {
+        timeout: custom::Duration { ms: 1500 },
+        results: vec![custom::Result::Pass, custom::Result::Fail(2)],
+    };
+    let mut serializer = create_serializer();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Test {timeout: Duration {ms: 1500,  }, results: &[Result::Pass, Result::Fail(2, ), ],  }",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Duration {pub ms: u32,  }\
+         #[derive(Clone, Debug, PartialEq)] pub struct Test {pub timeout: Duration, \
+         pub results: &'static [Result],  }",
+        write_structs(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub enum Result {Pass, Fail(u8, ),  }",
+        write_enums(&serializer)
+    );
+
+    // A type that does not match the built-in type after it was written as such is an error.
+    let mut serializer = create_serializer();
+    Ok::<u8, u8>(1).serialize(&mut serializer).unwrap();
+    match custom::Result::Pass.serialize(&mut serializer) {
+        Ok(_) => assert!(false, "Expected failure"),
+        Err(err) => assert_eq!(
+            "The type 'Result' at '' does not match the built-in type that it was written as \
+             before. Remove the built-in override with `without_type_override()`.",
+            err.to_string()
+        ),
+    }
+
+    // Types with the same fields can be generated by removing the override.
+    let mut serializer = create_serializer().without_type_override("Range");
+    let value = custom::Range { start: 1, end: 2 };
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!("Range {start: 1, end: 2,  }", create_string(&serializer));
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Range {pub start: u8, pub end: u8,  }",
+        write_structs(&serializer)
+    );
+}
+
+#[test]
+fn test_collect() {
+    let values = [Unsized(vec![("a", 1u8)]), Unsized(vec![("a", 2), ("b", 3)])];
+
+    // Without collecting, the first value does not match the final type.
+    let mut serializer = create_serializer().with_optional_field_inference();
+    for value in &values {
+        value.serialize(&mut serializer).unwrap();
+    }
+    assert_eq!(
+        "Map {a: 1,  }Map {a: 2, b: Some(3),  }",
+        create_string(&serializer)
+    );
+
+    let mut serializer = create_serializer().with_optional_field_inference();
+    for value in &values {
+        serializer.collect(value).unwrap();
+    }
+    assert_eq!("", create_string(&serializer));
+    for value in &values {
+        value.serialize(&mut serializer).unwrap();
+    }
+    assert_eq!(
+        "Map {a: 1, b: None,  }Map {a: 2, b: Some(3),  }",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Map {pub a: u8, pub b: Option<u8>,  }",
+        write_structs(&serializer)
+    );
+
+    let mut serializer = create_serializer().with_numeric_widening(NumericWidening::ALL);
+    #[derive(Serialize)]
+    struct Scale {
+        value: Num,
+    }
+
+    let values = [
+        Scale { value: Num::U8(1) },
+        Scale {
+            value: Num::F32(2.5),
+        },
+    ];
+    for value in &values {
+        serializer.collect(value).unwrap();
+    }
+    for value in &values {
+        value.serialize(&mut serializer).unwrap();
+    }
+    assert_eq!(
+        "Scale {value: 1.0,  }Scale {value: 2.5,  }",
+        create_string(&serializer)
+    );
+}
+
+#[test]
+fn test_type_model() {
+    let mut serializer = create_serializer();
+    serializer.collect(&create_level()).unwrap();
+
+    let names: Vec<_> = serializer.structs().iter().map(|(name, _)| name).collect();
+    assert_eq!(
+        vec![
+            "LevelEnemies",
+            "LevelWaves",
+            "EventSpawnSpawnPoints",
+            "Level1",
+            "Level"
+        ],
+        names
+    );
+    let Some(Structured::Struct(level)) = serializer.structs().get("Level") else {
+        panic!("Expected a struct.");
+    };
+    let events = &level.fields()["events"];
+    assert!(events.references("Event"));
+    let enums: Vec<_> = serializer.enums().names().collect();
+    assert_eq!(vec!["Event"], enums);
+    let variants: Vec<_> = serializer.enums().variants("Event").collect();
+    assert_eq!(1, variants.len());
+    assert_eq!("Spawn", variants[0].0);
+    assert!(variants[0].1.references("EventSpawnSpawnPoints"));
+
+    // Renamed types are used for the data that is serialized afterwards.
+    serializer.rename_type("Level", "Stage").unwrap();
+    serializer.rename_type("Event", "Trigger").unwrap();
+    serializer.rename_type("LevelEnemies", "Enemies").unwrap();
+    create_level().serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Stage {enemies: Enemies {orc: 3, troll: 1,  }, \
+         waves: &[LevelWaves {orc: 1,  }, ], \
+         events: &[Trigger::Spawn {spawn_points: EventSpawnSpawnPoints {north: 1,  },  }, ], \
+         pair: (1, Level1 {nested: 2,  }, ),  }",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Enemies {pub orc: u8, pub troll: u8,  }\
+         #[derive(Clone, Debug, PartialEq)] pub struct LevelWaves {pub orc: u8,  }\
+         #[derive(Clone, Debug, PartialEq)] pub struct EventSpawnSpawnPoints {pub north: u8,  }\
+         #[derive(Clone, Debug, PartialEq)] pub struct Level1 {pub nested: u8,  }\
+         #[derive(Clone, Debug, PartialEq)] pub struct Stage {pub enemies: Enemies, \
+         pub waves: &'static [LevelWaves], pub events: &'static [Trigger], pub pair: (u8, Level1, ),  }",
+        write_structs(&serializer)
+    );
+
+    let result = serializer.rename_type("Stage", "Level1");
+    assert_eq!(
+        "Can not rename type 'Stage' to 'Level1', since that name is already taken.",
+        result.unwrap_err().to_string()
+    );
+    let result = serializer.remove_type("Enemies");
+    assert_eq!(
+        "Can not remove type 'Enemies', since it is referred to by 'Stage'.",
+        result.unwrap_err().to_string()
+    );
+    let result = serializer.remove_type("EventSpawnSpawnPoints");
+    assert_eq!(
+        "Can not remove type 'EventSpawnSpawnPoints', since it is referred to by 'Trigger::Spawn'.",
+        result.unwrap_err().to_string()
+    );
+    serializer.remove_type("Stage").unwrap();
+    assert!(serializer.structs().get("Stage").is_none());
+    assert_eq!(
+        "Unknown type 'Stage'.",
+        serializer.remove_type("Stage").unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn test_type_pass() {
+    #[derive(Serialize)]
+    enum Shape {
+        Circle(u8),
+        Square(u8),
+    }
+
+    #[derive(Serialize)]
+    struct Scene {
+        shapes: Vec<Shape>,
+        ids: Vec<u8>,
+    }
+
+    struct Pass;
+
+    impl TypePass for Pass {
+        fn field_type(&mut self, owner: &str, field: &str, field_type: &mut FieldType) {
+            if owner == "Scene" && field == "ids" {
+                field_type.visit_mut(&mut |field_type| {
+                    if *field_type == FieldType::U8 {
+                        *field_type = FieldType::U16;
+                    }
+                });
+            }
+        }
+
+        fn keep_variant(&mut self, _name: &str, variant: &str, _structure: &Structured) -> bool {
+            variant != "Square"
+        }
+
+        fn rename(&mut self, name: &str) -> Option<String> {
+            (name == "Shape").then(|| String::from("Form"))
+        }
+    }
+
+    let value = Scene {
+        shapes: vec![Shape::Circle(1)],
+        ids: vec![2],
+    };
+    // The instances that are serialized afterwards are merged into the changed types.
+    let mut serializer = create_serializer().with_numeric_widening(NumericWidening::ALL);
+    serializer.collect(&Shape::Square(3)).unwrap();
+    serializer.collect(&value).unwrap();
+    serializer.run_pass(&mut Pass).unwrap();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Scene {shapes: &[Form::Circle(1, ), ], ids: &[2, ],  }",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Scene {pub shapes: &'static [Form], \
+         pub ids: &'static [u16],  }",
+        write_structs(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub enum Form {Circle(u8, ),  }",
+        write_enums(&serializer)
+    );
+
+    struct Conflicting;
+
+    impl TypePass for Conflicting {
+        fn field_type(&mut self, _owner: &str, field: &str, field_type: &mut FieldType) {
+            if field == "ids" {
+                *field_type = FieldType::Struct(Cow::Borrowed("Missing").into());
+            }
+        }
+
+        fn rename(&mut self, name: &str) -> Option<String> {
+            (name == "Scene").then(|| String::from("Form"))
+        }
+    }
+
+    // Conflicting passes are reported and do not change the types.
+    let expected = write_structs(&serializer);
+    let result = serializer.run_pass(&mut Conflicting);
+    assert_eq!(
+        "The type pass created conflicts. \
+         Can not rename type 'Scene' to 'Form', since that name is already taken. \
+         Field 'ids' of 'Scene' refers to unknown type 'Missing'.",
+        result.unwrap_err().to_string()
+    );
+    assert_eq!(expected, write_structs(&serializer));
+
+    struct ToArray;
+
+    impl TypePass for ToArray {
+        fn field_type(&mut self, owner: &str, field: &str, field_type: &mut FieldType) {
+            if owner == "Scene" && field == "ids" {
+                *field_type = FieldType::Array(Some(Box::new(FieldType::U16)).into(), 1);
+            }
+        }
+    }
+
+    // The values of rewritten fields are converted to their new types, without widening.
+    let mut serializer = create_serializer();
+    serializer.collect(&value).unwrap();
+    serializer.run_pass(&mut ToArray).unwrap();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Scene {shapes: &[Shape::Circle(1, ), ], ids: [2, ],  }",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Scene {pub shapes: &'static [Shape], \
+         pub ids: [u16; 1],  }",
+        write_structs(&serializer)
+    );
+
+    let value = Scene {
+        shapes: vec![],
+        ids: vec![2, 3],
+    };
+    let err = value.serialize(&mut serializer).unwrap_err();
+    assert_eq!(
+        "The sequence at 'ids' has 2 elements, but the type hint is an array of 1.",
+        err.to_string()
+    );
+
+    #[derive(Serialize)]
+    struct Sample {
+        level: u8,
+        ratio: f32,
+    }
+
+    struct ToPath;
+
+    impl TypePass for ToPath {
+        fn field_type(&mut self, _owner: &str, field: &str, field_type: &mut FieldType) {
+            let path = match field {
+                "level" => "Level",
+                _ => "Ratio",
+            };
+            *field_type = FieldType::Path(Cow::Borrowed(path).into());
+        }
+    }
+
+    // Numbers in fields that are rewritten to existing types are written as usual, also when they
+    // are widened.
+    let value = vec![
+        Sample {
+            level: 3,
+            ratio: 0.5,
+        },
+        Sample {
+            level: 4,
+            ratio: 1.5,
+        },
+    ];
+    let mut serializer = create_serializer().with_numeric_widening(NumericWidening::ALL);
+    serializer.collect(&value).unwrap();
+    serializer.run_pass(&mut ToPath).unwrap();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Sample {level: 3, ratio: 0.5,  }, Sample {level: 4, ratio: 1.5,  }, ]",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Sample {pub level: Level, \
+         pub ratio: Ratio,  }",
+        write_structs(&serializer)
+    );
+}
+
+#[test]
+fn test_type_hints() {
+    #[derive(Serialize)]
+    struct Frame {
+        palette: Vec<u8>,
+        delay: u8,
+        rate: u32,
+        id: Option<i8>,
+    }
+
+    #[derive(Serialize)]
+    struct Animation {
+        frames: Vec<Frame>,
+    }
+
+    let create_hinted_serializer = || {
+        create_serializer()
+            .with_type_hint_at("frames[].palette", TypeHint::Array(3))
+            .with_type_hint_at("frames[].palette[]", TypeHint::Integer(IntegerType::U16))
+            .with_type_hint("Frame", "delay", TypeHint::Integer(IntegerType::I16))
+            .with_type_hint("Frame", "rate", TypeHint::Path("Hertz".into()))
+            .with_type_hint("Frame", "id", TypeHint::Integer(IntegerType::U32))
+    };
+    let frame = |palette: Vec<u8>, id: Option<i8>| Frame {
+        palette,
+        delay: 16,
+        rate: 60,
+        id,
+    };
+
+    let value = Animation {
+        frames: vec![frame(vec![1, 2, 3], Some(7)), frame(vec![4, 5, 6], None)],
+    };
+    let mut serializer = create_hinted_serializer();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Animation {frames: &[Frame {palette: [1, 2, 3, ], delay: 16, rate: 60, id: Some(7),  }, \
+         Frame {palette: [4, 5, 6, ], delay: 16, rate: 60, id: None,  }, ],  }",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Frame {pub palette: [u16; 3], \
+         pub delay: i16, pub rate: Hertz, pub id: Option<u32>,  }\
+         #[derive(Clone, Debug, PartialEq)] pub struct Animation {pub frames: &'static [Frame],  }",
+        write_structs(&serializer)
+    );
+
+    // Hinted values are written without markers, since their types are known.
+    let output = create_string(&serializer);
+    let mut serializer = create_hinted_serializer().with_numeric_widening(NumericWidening::ALL);
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(output, create_string(&serializer));
+
+    // Values are checked against the hints.
+    let value = Animation {
+        frames: vec![frame(vec![1, 2], None)],
+    };
+    let err = value
+        .serialize(&mut create_hinted_serializer())
+        .unwrap_err();
+    assert_eq!(
+        "The sequence at 'frames[].palette' has 2 elements, but the type hint is an array of 3.",
+        err.to_string()
+    );
+
+    let value = Animation {
+        frames: vec![frame(vec![1, 2, 3], Some(-1))],
+    };
+    let err = value
+        .serialize(&mut create_hinted_serializer())
+        .unwrap_err();
+    assert_eq!(
+        "The value -1 at 'frames[].id' does not fit into the hinted type U32.",
+        err.to_string()
+    );
+
+    let mut serializer =
+        create_serializer().with_type_hint_at("0", TypeHint::Integer(IntegerType::U8));
+    let err = ("a", 1).serialize(&mut serializer).unwrap_err();
+    assert_eq!(
+        "The value at '0' does not match the type hint Integer(U8).",
+        err.to_string()
+    );
+
+    // A hint for a newtype struct applies to its value.
+    #[derive(Serialize)]
+    struct Meters(u32);
+
+    #[derive(Serialize)]
+    struct Route {
+        distance: Meters,
+    }
+
+    let mut serializer = create_serializer().with_type_hint(
+        "Route",
+        "distance",
+        TypeHint::Integer(IntegerType::U16),
+    );
+    let value = Route {
+        distance: Meters(300),
+    };
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Route {distance: Meters(300, ),  }",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Meters(pub u16, );\
+         #[derive(Clone, Debug, PartialEq)] pub struct Route {pub distance: Meters,  }",
+        write_structs(&serializer)
+    );
+}
+
+#[test]
+fn test_string_enums() {
+    #[derive(Serialize)]
+    struct Monster {
+        kind: &'static str,
+        tags: Vec<&'static str>,
+    }
+
+    let create_hinted_serializer = |max_variants| {
+        create_serializer()
+            .with_type_hint(
+                "Monster",
+                "kind",
+                TypeHint::StringEnum {
+                    name: "Kind".into(),
+                    max_variants,
+                },
+            )
+            .with_type_hint_at(
+                "[].tags[]",
+                TypeHint::StringEnum {
+                    name: "Tag".into(),
+                    max_variants,
+                },
+            )
+    };
+    let value = vec![
+        Monster {
+            kind: "fire",
+            tags: vec!["2x", "boss-fight"],
+        },
+        Monster {
+            kind: "water",
+            tags: vec![],
+        },
+        Monster {
+            kind: "fire",
+            tags: vec!["self"],
+        },
+    ];
+
+    let mut serializer = create_hinted_serializer(3);
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Monster {kind: Kind::Fire, tags: &[Tag::_2x, Tag::BossFight, ],  }, \
+         Monster {kind: Kind::Water, tags: &[],  }, \
+         Monster {kind: Kind::Fire, tags: &[Tag::Self_, ],  }, ]",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Monster {pub kind: Kind, pub tags: &'static [Tag],  }",
+        write_structs(&serializer)
+    );
+    assert_eq!(
+        vec!["Fire", "Water"],
+        serializer
+            .enums()
+            .variants("Kind")
+            .map(|(variant, _)| variant)
+            .collect::<Vec<_>>()
+    );
+
+    // The hints follow renamed enums.
+    serializer.rename_type("Kind", "Element").unwrap();
+    vec![Monster {
+        kind: "water",
+        tags: vec![],
+    }]
+    .serialize(&mut serializer)
+    .unwrap();
+    assert!(serializer.enums().get("Element", "Water").is_some());
+    assert!(serializer.enums().get("Kind", "Water").is_none());
+
+    // With more distinct strings than allowed, the strings are written as strings once they are
+    // collected.
+    let mut serializer = create_hinted_serializer(1);
+    serializer.collect(&value).unwrap();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "&[Monster {kind: \"fire\", tags: &[\"2x\", \"boss-fight\", ],  }, \
+         Monster {kind: \"water\", tags: &[],  }, \
+         Monster {kind: \"fire\", tags: &[\"self\", ],  }, ]",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Monster {pub kind: &'static str, \
+         pub tags: &'static [&'static str],  }",
+        write_structs(&serializer)
+    );
+    assert_eq!("", write_enums(&serializer));
+
+    let err = value
+        .serialize(&mut create_hinted_serializer(1))
+        .unwrap_err();
+    assert_eq!(
+        "The strings at '[].tags[]' have more than 1 distinct values, which is the limit for the \
+         enum 'Tag', but the enum was already written. Collect all values with `collect()` before \
+         writing them.",
+        err.to_string()
+    );
+
+    // The name of the enum must not be taken by another type.
+    #[derive(Serialize)]
+    struct Kind {
+        name: &'static str,
+    }
+
+    let mut serializer = create_hinted_serializer(3);
+    Kind { name: "fire" }.serialize(&mut serializer).unwrap();
+    let err = value.serialize(&mut serializer).unwrap_err();
+    assert_eq!(
+        "The name of the enum 'Kind' for the strings at '[].kind' is already taken by another type.",
+        err.to_string()
+    );
+
+    let mut serializer = create_hinted_serializer(3);
+    value.serialize(&mut serializer).unwrap();
+    let err = Kind { name: "fire" }
+        .serialize(&mut serializer)
+        .unwrap_err();
+    assert_eq!(
+        "The type 'Kind' at '' has the same name as an enum that was generated for strings. \
+         Choose another name in the type hint.",
+        err.to_string()
+    );
+
+    let value = vec![Monster {
+        kind: "fire",
+        tags: vec!["boss fight", "boss-fight"],
+    }];
+    let err = value
+        .serialize(&mut create_hinted_serializer(3))
+        .unwrap_err();
+    assert_eq!(
+        "The strings \"boss fight\" and \"boss-fight\" at '[].tags[]' result in the same variant \
+         'Tag::BossFight'.",
+        err.to_string()
+    );
+}
+
+#[test]
+fn test_strict_mode() {
+    #[derive(Serialize, Deserialize)]
+    enum Element {
+        Fire,
+        Water,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Monster {
+        element: Element,
+        level: usize,
+        drops: Vec<u8>,
+    }
+
+    let value = vec![Monster {
+        element: Element::Fire,
+        level: 3,
+        drops: vec![],
+    }];
+
+    let err = value
+        .serialize(&mut create_serializer().with_strict_mode())
+        .unwrap_err();
+    assert_eq!(
+        "The enum 'Element' at '[].element' was not traced, so it would only have the variants \
+         that occur in the data.",
+        err.to_string()
+    );
+
+    let mut serializer = create_serializer().with_strict_mode();
+    serializer.trace::<Element>().unwrap();
+    let err = value.serialize(&mut serializer).unwrap_err();
+    assert_eq!(
+        "The value at '[].level' is a U64, which may have been a usize or isize. Its type must be \
+         set with a type hint in strict mode.",
+        err.to_string()
+    );
+
+    let mut serializer = create_serializer().with_strict_mode().with_type_hint(
+        "Monster",
+        "level",
+        TypeHint::Path("usize".into()),
+    );
+    serializer.trace::<Element>().unwrap();
+    value.serialize(&mut serializer).unwrap();
+    let err = serializer.check_types().unwrap_err();
+    assert_eq!(
+        "Found indeterminate types in strict mode. The type of the value at '[].drops' \
+         (Monster.drops) could not be determined.",
+        err.to_string()
+    );
+
+    // The types can only be written once they pass the check.
+    let err = serializer.structs().write(&mut Vec::new()).unwrap_err();
+    assert_eq!(
+        "The types were not checked in strict mode. Call `Serializer::check_types()` after all \
+         values are serialized, before the types are written.",
+        err.to_string()
+    );
+
+    let mut serializer = create_serializer()
+        .with_strict_mode()
+        .with_type_hint("Monster", "level", TypeHint::Path("usize".into()))
+        .with_fallback_type_at(
+            "[].drops",
+            FieldType::Sequence(Some(Box::new(FieldType::U8)).into()),
+        );
+    serializer.trace::<Element>().unwrap();
+    value.serialize(&mut serializer).unwrap();
+    serializer.check_types().unwrap();
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Monster {pub element: Element, \
+         pub level: usize, pub drops: &'static [u8],  }",
+        write_structs(&serializer)
+    );
+
+    // Values that are serialized afterwards require another check.
+    value.serialize(&mut serializer).unwrap();
+    assert!(serializer.enums().write(&mut Vec::new()).is_err());
+    serializer.check_types().unwrap();
+    assert!(serializer.enums().write(&mut Vec::new()).is_ok());
+
+    // Maps whose name is taken are not given a numbered name.
+    let value = (BTreeMap::from([("x", 1u8)]), BTreeMap::from([("y", 2u8)]));
+    let mut serializer = create_serializer().with_map_naming(|_, _| Some(String::from("Stats")));
+    value.serialize(&mut serializer).unwrap();
+    assert!(serializer.structs().get("Stats2").is_some());
+
+    let mut serializer = create_serializer()
+        .with_strict_mode()
+        .with_map_naming(|_, _| Some(String::from("Stats")));
+    let err = value.serialize(&mut serializer).unwrap_err();
+    assert_eq!(
+        "The name 'Stats' for the map at '1' is already taken, so a number would be appended to it.",
+        err.to_string()
+    );
+}
+
+#[test]
+fn test_trace() {
+    #[derive(Serialize, Deserialize)]
+    struct Sample {
+        color: TestEnum2,
+        count: Option<u16>,
+        data: Vec<u8>,
+    }
+
+    let value = Sample {
+        color: TestEnum2::Transparent,
+        count: None,
+        data: Vec::new(),
+    };
+    let mut serializer = create_serializer();
+    serializer.trace::<Sample>().unwrap();
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Sample {color: TestEnum2::Transparent, count: None, data: &[],  }",
+        create_string(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub struct Sample {pub color: TestEnum2, \
+         pub count: Option<u16>, pub data: &'static [u8],  }",
+        write_structs(&serializer)
+    );
+    assert_eq!(
+        "#[derive(Clone, Debug, PartialEq)] pub enum TestEnum2 {Transparent, \
+         Rgb {r: u8, g: u8, b: u8,  }, Rgba {r: u8, g: u8, b: u8, a: u8,  }, Binary(&'static [u8], ), \
+         Compressed(&'static [u8], ),  }",
+        write_enums(&serializer)
+    );
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Untagged {
+        Number(u8),
+    }
+
+    let mut serializer = create_serializer();
+    let result = serializer.trace::<Untagged>();
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "Could not trace type. Can not trace types that are deserialized from any value, like untagged enums."
+        );
+    } else {
+        assert!(false, "Expected failure");
+    }
+}
+
+fn serialize_with_key<K: Serialize>(key: K) -> Result<String, Error> {
+    let mut serializer = create_serializer();
+    Unsized(vec![(key, 1u8)]).serialize(&mut serializer)?;
+    Ok(create_string(&serializer))
+}
+
+#[test]
+fn test_map_key_kinds() {
+    #[derive(Serialize)]
+    struct UnitKey;
+
+    #[derive(Serialize)]
+    struct NewtypeKey<T>(T);
+
+    #[derive(Serialize)]
+    struct TupleKey(u8, char);
+
+    #[derive(Serialize)]
+    struct StructKey {
+        x: u8,
+    }
+
+    #[derive(Serialize)]
+    enum EnumKey {
+        Newtype(u8),
+        Tuple(u8, u8),
+        Struct { x: u8 },
+    }
+
+    struct BytesKey;
+
+    impl Serialize for BytesKey {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.serialize_bytes(&[1, 2])
+        }
+    }
+
+    let supported = [
+        (serialize_with_key(true), "&[(true, 1, ), ]"),
+        (serialize_with_key(-1i8), "&[(-1, 1, ), ]"),
+        (serialize_with_key(-1i16), "&[(-1, 1, ), ]"),
+        (serialize_with_key(-1i32), "&[(-1, 1, ), ]"),
+        (serialize_with_key(-1i64), "&[(-1, 1, ), ]"),
+        (serialize_with_key(-1i128), "&[(-1, 1, ), ]"),
+        (serialize_with_key(1u8), "&[(1, 1, ), ]"),
+        (serialize_with_key(1u16), "&[(1, 1, ), ]"),
+        (serialize_with_key(1u32), "&[(1, 1, ), ]"),
+        (serialize_with_key(1u64), "&[(1, 1, ), ]"),
+        (serialize_with_key(1u128), "&[(1, 1, ), ]"),
+        (serialize_with_key(0.5f32), "&[(0.5f32, 1, ), ]"),
+        (serialize_with_key(0.5f64), "&[(0.5f64, 1, ), ]"),
+        (serialize_with_key('c'), "&[('c', 1, ), ]"),
+        (serialize_with_key("key"), "Map {key: 1,  }"),
+        (serialize_with_key(BytesKey), "&[(&[1, 2, ], 1, ), ]"),
+        (serialize_with_key(Option::<u8>::None), "&[(None, 1, ), ]"),
+        (serialize_with_key(Some(1u8)), "&[(Some(1), 1, ), ]"),
+        (serialize_with_key(()), "&[((), 1, ), ]"),
+        (serialize_with_key(UnitKey), "&[(UnitKey, 1, ), ]"),
+        (
+            serialize_with_key(Direction::Up),
+            "&[(Direction::Up, 1, ), ]",
+        ),
+        (
+            serialize_with_key(NewtypeKey(1u8)),
+            "&[(NewtypeKey(1, ), 1, ), ]",
+        ),
+        (serialize_with_key(NewtypeKey("key")), "Map {key: 1,  }"),
+        (
+            serialize_with_key(EnumKey::Newtype(1)),
+            "&[(EnumKey::Newtype(1, ), 1, ), ]",
+        ),
+        (serialize_with_key(vec![1u8, 2]), "&[(&[1, 2, ], 1, ), ]"),
+        (serialize_with_key((1u8, 'c')), "&[((1, 'c', ), 1, ), ]"),
+        (
+            serialize_with_key(TupleKey(1, 'c')),
+            "&[(TupleKey(1, 'c', ), 1, ), ]",
+        ),
+        (
+            serialize_with_key(EnumKey::Tuple(1, 2)),
+            "&[(EnumKey::Tuple(1, 2, ), 1, ), ]",
+        ),
+        (
+            serialize_with_key(StructKey { x: 1 }),
+            "&[(StructKey {x: 1,  }, 1, ), ]",
+        ),
+        (
+            serialize_with_key(EnumKey::Struct { x: 1 }),
+            "&[(EnumKey::Struct {x: 1,  }, 1, ), ]",
+        ),
+    ];
+
+    for (result, expected) in supported {
+        assert_eq!(expected, result.unwrap());
+    }
+
+    let result = serialize_with_key(BTreeMap::from([(1u8, 2u8)]));
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "Unsupported key in map at '<root>'. Maps can not be used as map keys."
+        );
+    } else {
+        assert!(false, "Expected failure");
+    }
+}
+
+#[test]
+fn test_map_key_error_path() {
+    #[derive(Serialize)]
+    struct Settings {
+        groups: Vec<Unsized<BTreeMap<u8, u8>, u8>>,
+    }
+
+    let value = Settings {
+        groups: vec![Unsized(vec![(BTreeMap::new(), 1)])],
+    };
+
+    let mut serializer = create_serializer();
+    let result = value.serialize(&mut serializer);
+    if let Err(Error::Message(msg)) = result {
+        assert_eq!(
+            msg,
+            "Unsupported key in map at 'groups[]'. Maps can not be used as map keys."
+        );
+    } else {
+        assert!(false, "Expected failure");
+    }
+}
+
+#[test]
+fn test_seq_unknown_length() {
+    struct Evens(Vec<u32>);
+
+    impl Serialize for Evens {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.collect_seq(self.0.iter().filter(|value| *value % 2 == 0))
+        }
+    }
+
+    let mut serializer = create_serializer();
+    let field_type = Evens(vec![1, 2, 3, 4]).serialize(&mut serializer).unwrap();
+    assert_eq!(
+        FieldType::Sequence(Some(Box::new(FieldType::U32)).into()),
+        field_type
+    );
+    assert_eq!("&[2, 4, ]", create_string(&serializer));
+}
+
+#[test]
+fn test_str_literal() {
+    let mut serializer = create_serializer();
+    let field_type = "Say \"hi\"\nand leave".serialize(&mut serializer).unwrap();
+    assert_eq!(FieldType::Str, field_type);
+    assert_eq!("\"Say \\\"hi\\\"\\nand leave\"", create_string(&serializer));
+
+    let mut serializer = create_serializer();
+    "C:\\Assets\\\"tiles\"".serialize(&mut serializer).unwrap();
+    assert_eq!("r#\"C:\\Assets\\\"tiles\"\"#", create_string(&serializer));
+}
+
+#[test]
+fn test_float_suffix() {
+    // Without an enclosing type, the type of the literal can not be inferred.
+    let mut serializer = create_serializer();
+    let field_type = 1.5f32.serialize(&mut serializer).unwrap();
+    assert_eq!(FieldType::F32, field_type);
+    assert_eq!("1.5f32", create_string(&serializer));
+
+    let mut serializer = create_serializer();
+    vec![f64::NAN, -0.0, 2.0]
+        .serialize(&mut serializer)
+        .unwrap();
+    assert_eq!("&[f64::NAN, -0.0f64, 2.0f64, ]", create_string(&serializer));
+
+    #[derive(Serialize)]
+    struct Floats {
+        single: f32,
+        double: Vec<f64>,
+    }
+
+    let mut serializer = create_serializer();
+    let value = Floats {
+        single: f32::NEG_INFINITY,
+        double: vec![1e-310, 3.0],
+    };
+    value.serialize(&mut serializer).unwrap();
+    assert_eq!(
+        "Floats {single: f32::NEG_INFINITY, double: &[1e-310, 3.0, ],  }",
+        create_string(&serializer)
+    );
+}
+
+#[test]
+fn test_char_literal() {
+    let mut serializer = create_serializer();
+    let field_type = '\''.serialize(&mut serializer).unwrap();
+    assert_eq!(FieldType::Char, field_type);
+    assert_eq!("'\\''", create_string(&serializer));
+}
 
 #[derive(Serialize)]
 struct Simple {
@@ -0,0 +1,711 @@
+//! Tracing of [`Deserialize`] implementations, to find the complete definitions of the generated
+//! types.
+//!
+//! The data that is serialized only contains the variants of enums that it uses, and sequences and
+//! options without values do not reveal their element types. The [`Deserialize`] implementation of
+//! a type, however, asks for every field and variant. Therefore, [`trace()`] deserializes the type
+//! from a [`Tracer`], which produces default values (e.g. `0`, `""`, a single element for
+//! sequences and `Some(..)` for options) and records the type of every value along the way.
+//!
+//! Every trace selects a single variant of every enum, so the type is traced repeatedly until all
+//! variants of all enums that were found have been traced. Recursive types are supported in
+//! sequences (e.g. `Vec<Node>` in `Node`), which are traced as empty sequences once the type is
+//! reached again, in options (e.g. `Option<Box<Link>>` in `Link`), which are traced as `None` in the
+//! next trace once the type was reached through them, and in enum variants, which are traced with a
+//! variant that was traced before.
+//! Maps do not reveal their keys, so structs and variants that contain maps are not recorded.
+
+#[cfg(test)]
+mod test;
+
+use super::ident;
+use super::path::DataPath;
+use super::Error;
+use crate::model::{
+    FieldType, Structured, StructuredStruct, StructuredStructBuilder, StructuredTuple,
+};
+use linked_hash_map::LinkedHashMap;
+use serde::de::value::{BorrowedStrDeserializer, U32Deserializer};
+use serde::de::{DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// The types that were found by tracing.
+#[derive(Debug, Default)]
+pub struct Traced {
+    /// The structs, in the order in which they were completed.
+    pub structs: LinkedHashMap<Cow<'static, str>, Structured>,
+    /// The variants of the enums, in the order in which they are declared.
+    pub enums: LinkedHashMap<Cow<'static, str>, Vec<(Cow<'static, str>, Structured)>>,
+}
+
+/// Traces the [`Deserialize`] implementation of `T`. See the [module](self) documentation.
+pub fn trace<T>() -> Result<Traced, Error>
+where
+    T: Deserialize<'static>,
+{
+    let mut tracer = Tracer::default();
+    let mut traced = false;
+    let mut error = None;
+    let mut stalled = 0;
+    loop {
+        let before = tracer.traced_variants();
+        let result = T::deserialize(&mut tracer);
+        tracer.stack.clear();
+        tracer.path = DataPath::default();
+        match result {
+            Ok(_) => traced = true,
+            // The option through which the type was reached is traced as `None` from now on.
+            Err(err) if std::mem::take(&mut tracer.retry) => {
+                error = Some(err);
+                continue;
+            }
+            // A pass can fail on a recursive type, which may be resolved once other variants have
+            // been traced.
+            Err(err) if tracer.recursion.take().is_some() => error = Some(err),
+            Err(err) => return Err(err),
+        }
+
+        let untraced = tracer.untraced_variants();
+        if untraced == 0 {
+            break;
+        }
+        if tracer.traced_variants() > before {
+            stalled = 0;
+        } else {
+            stalled += 1;
+            if stalled > untraced {
+                break;
+            }
+        }
+    }
+
+    match error {
+        Some(err) if !traced => Err(err),
+        _ => Ok(tracer.into_traced()),
+    }
+}
+
+/// The state of an enum that is being traced.
+struct TracedEnum {
+    variants: &'static [&'static str],
+    /// The structure of every traced variant, if it is known.
+    structures: Vec<Option<Structured>>,
+    /// The indices of the traced variants, in the order in which they were traced.
+    traced: Vec<usize>,
+    /// The number of times that every variant was selected.
+    attempts: Vec<usize>,
+}
+
+impl TracedEnum {
+    fn new(variants: &'static [&'static str]) -> Self {
+        Self {
+            variants,
+            structures: vec![None; variants.len()],
+            traced: Vec::new(),
+            attempts: vec![0; variants.len()],
+        }
+    }
+}
+
+/// A [`Deserializer`](serde::Deserializer) that records the types of the values that are
+/// deserialized from it.
+#[derive(Default)]
+struct Tracer {
+    structs: LinkedHashMap<Cow<'static, str>, Structured>,
+    enums: LinkedHashMap<&'static str, TracedEnum>,
+    /// The structs and enums that enclose the current value.
+    stack: Vec<&'static str>,
+    /// The type of the most recently deserialized value, or `None` if it is not known.
+    last: Option<FieldType>,
+    /// The type that could not be traced because it is recursive, if any.
+    recursion: Option<FieldType>,
+    /// The path and the number of enclosing types at which the recursive type was found.
+    recursion_site: (String, usize),
+    /// The path of the current value.
+    path: DataPath,
+    /// The types of the recursive types inside options, by the path of the option. These options
+    /// are traced as `None`.
+    recursive_options: HashMap<String, FieldType>,
+    /// Whether the trace failed on a recursive type inside an option, so it should be repeated.
+    retry: bool,
+}
+
+impl Tracer {
+    fn recursion_error(&mut self, name: &str, field_type: FieldType) -> Error {
+        self.recursion = Some(field_type);
+        self.recursion_site = (self.path.as_str().to_string(), self.stack.len());
+        Error::message(format!(
+            "Can not trace recursive type '{name}' outside of a sequence or an option."
+        ))
+    }
+
+    /// Enters a struct, unless it already encloses the current value.
+    fn enter_struct(&mut self, name: &'static str) -> Result<(), Error> {
+        if self.stack.contains(&name) {
+            return Err(self.recursion_error(name, FieldType::Struct(Cow::Borrowed(name).into())));
+        }
+        self.stack.push(name);
+        Ok(())
+    }
+
+    fn add_struct(&mut self, name: &'static str, structure: Option<Structured>) {
+        if let Some(structure) = structure {
+            // Generic structs can be traced with different structures. Only the first is kept.
+            if !self.structs.contains_key(name) {
+                self.structs.insert(name.into(), structure);
+            }
+        }
+        self.last = Some(FieldType::Struct(Cow::Borrowed(name).into()));
+    }
+
+    /// Selects the variant of an enum to trace. This is the least attempted variant that was not
+    /// traced yet or, if the enum already encloses the current value, the first variant that was
+    /// traced, since that variant can be traced without the enum itself.
+    fn select_variant(
+        &mut self,
+        name: &'static str,
+        variants: &'static [&'static str],
+    ) -> Result<usize, Error> {
+        if variants.is_empty() {
+            return Err(Error::message(format!(
+                "Can not trace enum '{name}' without variants."
+            )));
+        }
+
+        let recursive = self.stack.contains(&name);
+        let traced_enum = self
+            .enums
+            .entry(name)
+            .or_insert_with(|| TracedEnum::new(variants));
+        let first_traced = traced_enum.traced.first().copied();
+        let index = if recursive {
+            match first_traced {
+                Some(index) => index,
+                None => {
+                    return Err(
+                        self.recursion_error(name, FieldType::Enum(Cow::Borrowed(name).into()))
+                    )
+                }
+            }
+        } else {
+            (0..variants.len())
+                .filter(|index| !traced_enum.traced.contains(index))
+                .min_by_key(|index| traced_enum.attempts[*index])
+                .or(first_traced)
+                .unwrap_or(0)
+        };
+        traced_enum.attempts[index] += 1;
+        Ok(index)
+    }
+
+    fn add_variant(&mut self, name: &'static str, index: usize, structure: Option<Structured>) {
+        if let Some(traced_enum) = self.enums.get_mut(name) {
+            if !traced_enum.traced.contains(&index) {
+                traced_enum.traced.push(index);
+                traced_enum.structures[index] = structure;
+            }
+        }
+    }
+
+    fn traced_variants(&self) -> usize {
+        self.enums
+            .values()
+            .map(|traced_enum| traced_enum.traced.len())
+            .sum()
+    }
+
+    fn untraced_variants(&self) -> usize {
+        self.enums
+            .values()
+            .map(|traced_enum| traced_enum.variants.len() - traced_enum.traced.len())
+            .sum()
+    }
+
+    fn into_traced(self) -> Traced {
+        let enums = self
+            .enums
+            .into_iter()
+            .map(|(name, traced_enum)| {
+                let variants = traced_enum
+                    .variants
+                    .iter()
+                    .zip(traced_enum.structures)
+                    .filter_map(|(variant, structure)| Some(((*variant).into(), structure?)))
+                    .collect();
+                (name.into(), variants)
+            })
+            .collect();
+        Traced {
+            structs: self.structs,
+            enums,
+        }
+    }
+
+    /// Traces the elements of a sequence or tuple. Returns the value and the types of the
+    /// elements, if these are all known.
+    fn trace_elements<'de, V>(
+        &mut self,
+        len: usize,
+        sequence: bool,
+        visitor: V,
+    ) -> Result<(V::Value, Option<Vec<FieldType>>), Error>
+    where
+        V: Visitor<'de>,
+    {
+        let mut access = ElementAccess {
+            tracer: self,
+            remaining: len,
+            sequence,
+            types: Vec::with_capacity(len),
+        };
+        let value = visitor.visit_seq(&mut access)?;
+        let types = access.types.into_iter().collect();
+        Ok((value, types))
+    }
+
+    /// Traces the fields of a struct or struct variant. Returns the value and the structure, if
+    /// the types of all fields are known.
+    fn trace_fields<'de, V>(
+        &mut self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<(V::Value, Option<Structured>), Error>
+    where
+        V: Visitor<'de>,
+    {
+        let mut access = FieldAccess {
+            tracer: self,
+            fields,
+            index: 0,
+            builder: Some(StructuredStruct::builder(Some(fields.len()))),
+        };
+        let value = visitor.visit_map(&mut access)?;
+        let structure = match access.builder {
+            Some(builder) => Some(Structured::Struct(builder.build()?)),
+            None => None,
+        };
+        Ok((value, structure))
+    }
+}
+
+macro_rules! trace_primitive {
+    ($method:ident, $visit:ident, $value:expr, $field_type:expr) => {
+        fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            self.last = Some($field_type);
+            visitor.$visit($value)
+        }
+    };
+}
+
+impl<'de> serde::Deserializer<'de> for &mut Tracer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(Error::message(
+            "Can not trace types that are deserialized from any value, like untagged enums.",
+        ))
+    }
+
+    trace_primitive!(deserialize_bool, visit_bool, false, FieldType::Bool);
+    trace_primitive!(deserialize_i8, visit_i8, 0, FieldType::I8);
+    trace_primitive!(deserialize_i16, visit_i16, 0, FieldType::I16);
+    trace_primitive!(deserialize_i32, visit_i32, 0, FieldType::I32);
+    trace_primitive!(deserialize_i64, visit_i64, 0, FieldType::I64);
+    trace_primitive!(deserialize_i128, visit_i128, 0, FieldType::I128);
+    trace_primitive!(deserialize_u8, visit_u8, 0, FieldType::U8);
+    trace_primitive!(deserialize_u16, visit_u16, 0, FieldType::U16);
+    trace_primitive!(deserialize_u32, visit_u32, 0, FieldType::U32);
+    trace_primitive!(deserialize_u64, visit_u64, 0, FieldType::U64);
+    trace_primitive!(deserialize_u128, visit_u128, 0, FieldType::U128);
+    trace_primitive!(deserialize_f32, visit_f32, 0.0, FieldType::F32);
+    trace_primitive!(deserialize_f64, visit_f64, 0.0, FieldType::F64);
+    trace_primitive!(deserialize_char, visit_char, '\0', FieldType::Char);
+    trace_primitive!(deserialize_str, visit_borrowed_str, "", FieldType::Str);
+    trace_primitive!(deserialize_string, visit_borrowed_str, "", FieldType::Str);
+    trace_primitive!(
+        deserialize_identifier,
+        visit_borrowed_str,
+        "",
+        FieldType::Str
+    );
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.last = Some(FieldType::Unit);
+        visitor.visit_unit()
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.last = Some(FieldType::Sequence(Some(Box::new(FieldType::U8)).into()));
+        visitor.visit_borrowed_bytes(&[])
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if let Some(field_type) = self.recursive_options.get(self.path.as_str()) {
+            self.last = Some(FieldType::Option(Some(Box::new(field_type.clone())).into()));
+            return visitor.visit_none();
+        }
+
+        let site = (self.path.as_str().to_string(), self.stack.len());
+        let value = match visitor.visit_some(&mut *self) {
+            Ok(value) => value,
+            Err(err) => {
+                // The value of the option is the recursive type itself, so it can be left out.
+                if self.recursion_site == site {
+                    if let Some(field_type) = self.recursion.take() {
+                        self.recursive_options.insert(site.0, field_type);
+                        self.retry = true;
+                    }
+                }
+                return Err(err);
+            }
+        };
+        self.last = Some(FieldType::Option(self.last.take().map(Box::new).into()));
+        Ok(value)
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.add_struct(name, Some(Structured::Unit));
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.enter_struct(name)?;
+        let result = visitor.visit_newtype_struct(&mut *self);
+        self.stack.pop();
+        let value = result?;
+
+        let structure = self
+            .last
+            .take()
+            .map(|field_type| Structured::Tuple(StructuredTuple::new(vec![field_type])));
+        self.add_struct(name, structure);
+        Ok(value)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, types) = self.trace_elements(1, true, visitor)?;
+        let element = types.and_then(|mut types| types.pop());
+        self.last = Some(FieldType::Sequence(element.map(Box::new).into()));
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, types) = self.trace_elements(len, false, visitor)?;
+        self.last = types.map(|types| FieldType::Tuple(types.into()));
+        Ok(value)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.enter_struct(name)?;
+        let result = self.trace_elements(len, false, visitor);
+        self.stack.pop();
+        let (value, types) = result?;
+
+        let structure = types.map(|types| Structured::Tuple(StructuredTuple::new(types)));
+        self.add_struct(name, structure);
+        Ok(value)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let value = visitor.visit_map(EmptyAccess)?;
+        self.last = None;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.enter_struct(name)?;
+        let result = self.trace_fields(fields, visitor);
+        self.stack.pop();
+        let (value, structure) = result?;
+
+        self.add_struct(name, structure);
+        Ok(value)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let index = self.select_variant(name, variants)?;
+        self.stack.push(name);
+        self.path.push_field(variants[index]);
+        let result = visitor.visit_enum(VariantTracer {
+            tracer: &mut *self,
+            name,
+            index,
+        });
+        self.path.pop();
+        self.stack.pop();
+        let value = result?;
+
+        self.last = Some(FieldType::Enum(Cow::Borrowed(name).into()));
+        Ok(value)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.last = None;
+        visitor.visit_unit()
+    }
+}
+
+/// Provides the elements of a sequence or tuple.
+struct ElementAccess<'a> {
+    tracer: &'a mut Tracer,
+    remaining: usize,
+    /// Whether the elements are those of a sequence, which can end early.
+    sequence: bool,
+    types: Vec<Option<FieldType>>,
+}
+
+impl<'de, 'a> SeqAccess<'de> for ElementAccess<'a> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+
+        if self.sequence {
+            self.tracer.path.push_element();
+        } else {
+            self.tracer.path.push_index(self.types.len());
+        }
+        let result = seed.deserialize(&mut *self.tracer);
+        self.tracer.path.pop();
+        match result {
+            Ok(value) => {
+                self.types.push(self.tracer.last.take());
+                Ok(Some(value))
+            }
+            // A sequence of a recursive type ends before its first element.
+            Err(_) if self.sequence && self.tracer.recursion.is_some() => {
+                self.types.push(self.tracer.recursion.take());
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+/// Provides the fields of a struct or struct variant.
+struct FieldAccess<'a> {
+    tracer: &'a mut Tracer,
+    fields: &'static [&'static str],
+    index: usize,
+    /// The builder for the structure, or `None` if the type of a field is not known.
+    builder: Option<StructuredStructBuilder>,
+}
+
+impl<'de, 'a> MapAccess<'de> for FieldAccess<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.fields.get(self.index) {
+            Some(field) => seed
+                .deserialize(BorrowedStrDeserializer::new(field))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let name = self
+            .fields
+            .get(self.index)
+            .ok_or_else(|| Error::Bug(String::from("Value requested without a field.")))?;
+        self.index += 1;
+        self.tracer.path.push_field(name);
+        let result = seed.deserialize(&mut *self.tracer);
+        self.tracer.path.pop();
+        let value = result?;
+
+        let field_type = self.tracer.last.take();
+        match (&mut self.builder, field_type) {
+            (Some(builder), Some(field_type)) => match ident::sanitize(name) {
+                Cow::Borrowed(_) => builder.field((*name).into(), field_type)?,
+                Cow::Owned(ident) => {
+                    builder.renamed_field(ident.into(), name.to_string(), field_type)?
+                }
+            },
+            _ => self.builder = None,
+        }
+        Ok(value)
+    }
+}
+
+/// Provides the entries of a map, of which there are none.
+struct EmptyAccess;
+
+impl<'de> MapAccess<'de> for EmptyAccess {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, _seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        Ok(None)
+    }
+
+    fn next_value_seed<V>(&mut self, _seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        Err(Error::Bug(String::from(
+            "Value requested from an empty map.",
+        )))
+    }
+}
+
+/// Provides the selected variant of an enum.
+struct VariantTracer<'a> {
+    tracer: &'a mut Tracer,
+    name: &'static str,
+    index: usize,
+}
+
+impl<'de, 'a> EnumAccess<'de> for VariantTracer<'a> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let index = u32::try_from(self.index)
+            .map_err(|_| Error::Bug(String::from("Variant index out of range.")))?;
+        let value = seed.deserialize(U32Deserializer::<Error>::new(index))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, 'a> VariantAccess<'de> for VariantTracer<'a> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.tracer
+            .add_variant(self.name, self.index, Some(Structured::Unit));
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let value = seed.deserialize(&mut *self.tracer)?;
+        let structure = self
+            .tracer
+            .last
+            .take()
+            .map(|field_type| Structured::Tuple(StructuredTuple::new(vec![field_type])));
+        self.tracer.add_variant(self.name, self.index, structure);
+        Ok(value)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, types) = self.tracer.trace_elements(len, false, visitor)?;
+        let structure = types.map(|types| Structured::Tuple(StructuredTuple::new(types)));
+        self.tracer.add_variant(self.name, self.index, structure);
+        Ok(value)
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (value, structure) = self.tracer.trace_fields(fields, visitor)?;
+        self.tracer.add_variant(self.name, self.index, structure);
+        Ok(value)
+    }
+}
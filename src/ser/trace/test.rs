@@ -0,0 +1,202 @@
+use super::{trace, Traced};
+use crate::model::{FieldType, Structured, StructuredStruct, StructuredTuple};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+fn structure(fields: &[(&'static str, FieldType)]) -> Structured {
+    let mut builder = StructuredStruct::builder(None);
+    for (name, field_type) in fields {
+        builder.field((*name).into(), field_type.clone()).unwrap();
+    }
+    Structured::Struct(builder.build().unwrap())
+}
+
+fn tuple(elements: &[FieldType]) -> Structured {
+    Structured::Tuple(StructuredTuple::new(elements.to_vec()))
+}
+
+fn variants(traced: &Traced, name: &str) -> Vec<(String, Structured)> {
+    traced.enums[name]
+        .iter()
+        .map(|(variant, structure)| (variant.to_string(), structure.clone()))
+        .collect()
+}
+
+fn named(name: &'static str) -> FieldType {
+    FieldType::Struct(Cow::Borrowed(name).into())
+}
+
+fn seq(element: FieldType) -> FieldType {
+    FieldType::Sequence(Some(Box::new(element)).into())
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Palette(u8, u8);
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+enum Shape {
+    Empty,
+    Circle(u16),
+    Rect(u16, u16),
+    Polygon {
+        points: Vec<(i8, i8)>,
+        palette: Option<Palette>,
+    },
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Level {
+    name: &'static str,
+    shapes: Vec<Shape>,
+    #[serde(rename = "type")]
+    kind: char,
+    scale: Option<f32>,
+}
+
+#[test]
+fn test_trace() {
+    let traced = trace::<Level>().unwrap();
+    assert_eq!(
+        vec![
+            ("Empty".to_string(), Structured::Unit),
+            ("Circle".to_string(), tuple(&[FieldType::U16])),
+            ("Rect".to_string(), tuple(&[FieldType::U16, FieldType::U16])),
+            (
+                "Polygon".to_string(),
+                structure(&[
+                    (
+                        "points",
+                        seq(FieldType::Tuple(vec![FieldType::I8, FieldType::I8].into()))
+                    ),
+                    (
+                        "palette",
+                        FieldType::Option(Some(Box::new(named("Palette"))).into())
+                    ),
+                ])
+            ),
+        ],
+        variants(&traced, "Shape")
+    );
+
+    let names: Vec<_> = traced.structs.keys().map(|name| name.as_ref()).collect();
+    assert_eq!(vec!["Level", "Palette"], names);
+    assert_eq!(
+        tuple(&[FieldType::U8, FieldType::U8]),
+        traced.structs["Palette"]
+    );
+    let Structured::Struct(level) = &traced.structs["Level"] else {
+        panic!("Expected a struct.");
+    };
+    let fields: Vec<_> = level.fields().iter().collect();
+    assert_eq!(4, fields.len());
+    assert_eq!("r#type", fields[2].0.as_ref());
+    assert_eq!(
+        &FieldType::Option(Some(Box::new(FieldType::F32)).into()),
+        fields[3].1
+    );
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Node {
+    value: u8,
+    children: Vec<Node>,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+enum Expr {
+    Add(Box<Expr>, Box<Expr>),
+    Lit(u8),
+    Neg(Option<Box<Expr>>),
+}
+
+#[test]
+fn test_trace_recursive() {
+    let traced = trace::<Node>().unwrap();
+    assert_eq!(
+        structure(&[("value", FieldType::U8), ("children", seq(named("Node")))]),
+        traced.structs["Node"]
+    );
+
+    let traced = trace::<Expr>().unwrap();
+    let expr = || FieldType::Enum(Cow::Borrowed("Expr").into());
+    assert_eq!(
+        vec![
+            ("Add".to_string(), tuple(&[expr(), expr()])),
+            ("Lit".to_string(), tuple(&[FieldType::U8])),
+            (
+                "Neg".to_string(),
+                tuple(&[FieldType::Option(Some(Box::new(expr())).into())])
+            ),
+        ],
+        variants(&traced, "Expr")
+    );
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Link {
+        next: Option<Box<Link>>,
+    }
+
+    // Options are traced as `None` once the type is reached again.
+    let traced = trace::<Link>().unwrap();
+    assert_eq!(
+        structure(&[(
+            "next",
+            FieldType::Option(Some(Box::new(named("Link"))).into())
+        )]),
+        traced.structs["Link"]
+    );
+
+    // The type must be the value of the option itself.
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Pair {
+        next: Option<(u8, Box<Pair>)>,
+    }
+
+    let result = trace::<Pair>();
+    assert_eq!(
+        "Can not trace recursive type 'Pair' outside of a sequence or an option.",
+        result.unwrap_err().to_string()
+    );
+}
+
+#[test]
+fn test_trace_maps() {
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Lookup {
+        entries: HashMap<String, u8>,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Container {
+        lookup: Lookup,
+        palettes: Vec<Palette>,
+    }
+
+    let traced = trace::<Container>().unwrap();
+    let names: Vec<_> = traced.structs.keys().map(|name| name.as_ref()).collect();
+    assert_eq!(vec!["Palette", "Container"], names);
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    #[allow(dead_code)]
+    enum Untagged {
+        Number(u8),
+        Text(&'static str),
+    }
+
+    let result = trace::<Untagged>();
+    assert_eq!(
+        "Can not trace types that are deserialized from any value, like untagged enums.",
+        result.unwrap_err().to_string()
+    );
+}
@@ -15,7 +15,7 @@ where
     W: Write,
 {
     pub fn begin(serializer: &'a mut Serializer<W>, len: usize) -> Result<Self, Error> {
-        let util = TupleUtil::begin(len, &mut serializer.writer)?;
+        let util = TupleUtil::begin(Some(len), &mut serializer.writer)?;
 
         Ok(Self { serializer, util })
     }
@@ -32,8 +32,9 @@ where
     where
         T: Serialize,
     {
+        let index = self.util.next_index();
         self.util.element_begin(&mut self.serializer.writer)?;
-        let field_type = value.serialize(&mut *self.serializer)?;
+        let field_type = self.serializer.serialize_index_value(index, value)?;
         self.util
             .element_end(&mut self.serializer.writer, field_type)
     }
@@ -44,6 +45,8 @@ where
             .end(&mut self.serializer.writer)
             .map_err(|msg| msg.message_prepend("Could not build tuple."))?;
 
-        Ok(FieldType::Tuple(built.take().into()))
+        let field_type = FieldType::Tuple(built.take().into());
+        self.serializer.end_compound(&field_type)?;
+        Ok(field_type)
     }
 }
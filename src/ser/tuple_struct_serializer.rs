@@ -1,4 +1,5 @@
 use super::tuple_util::TupleUtil;
+use super::type_override::ConstructorCall;
 use super::Error;
 use super::Serializer;
 use crate::model::FieldType;
@@ -11,6 +12,7 @@ pub struct TupleStructSerializer<'a, W> {
     serializer: &'a mut Serializer<W>,
     util: TupleUtil,
     name: Cow<'static, str>,
+    constructor: Option<ConstructorCall>,
 }
 
 impl<'a, W> TupleStructSerializer<'a, W>
@@ -22,13 +24,15 @@ where
         name: Cow<'static, str>,
         len: usize,
     ) -> Result<Self, Error> {
-        serializer.writer.write(&name)?;
-        let util = TupleUtil::begin(len, &mut serializer.writer)?;
+        let constructor = serializer.struct_instance_begin(&name)?;
+        let util = TupleUtil::begin(Some(len), &mut serializer.writer)?;
+        serializer.enter_type(name.clone(), None);
 
         Ok(Self {
             serializer,
             util,
             name,
+            constructor,
         })
     }
 }
@@ -44,19 +48,31 @@ where
     where
         T: Serialize,
     {
+        let index = self.util.next_index();
         self.util.element_begin(&mut self.serializer.writer)?;
-        let field_type = value.serialize(&mut *self.serializer)?;
+        if let Some(constructor) = &mut self.constructor {
+            constructor.value_begin(&mut self.serializer.writer);
+        }
+        let field_type = self.serializer.serialize_index_value(index, value)?;
+        if let Some(constructor) = &mut self.constructor {
+            constructor.value_end(&index.to_string(), false, &mut self.serializer.writer)?;
+        }
         self.util
             .element_end(&mut self.serializer.writer, field_type)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.serializer.leave_type();
         let built = self.util.end(&mut self.serializer.writer).map_err(|msg| {
             msg.message_prepend(format!("Could not build struct '{}'", self.name))
         })?;
+        self.serializer.writer.instance_end_marker()?;
 
         let structure = Structured::Tuple(built);
-        self.serializer.structs_mut().merge(&self.name, structure)?;
-        Ok(FieldType::Struct(self.name.into()))
+        let field_type =
+            self.serializer
+                .struct_instance_end(self.name, structure, self.constructor)?;
+        self.serializer.end_compound(&field_type)?;
+        Ok(field_type)
     }
 }
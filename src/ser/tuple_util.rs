@@ -8,7 +8,7 @@ pub struct TupleUtil {
 }
 
 impl TupleUtil {
-    pub fn begin<W>(len: usize, writer: &mut CodeWriter<W>) -> Result<Self, Error>
+    pub fn begin<W>(len: Option<usize>, writer: &mut CodeWriter<W>) -> Result<Self, Error>
     where
         W: Write,
     {
@@ -19,6 +19,11 @@ impl TupleUtil {
         })
     }
 
+    /// The index of the next element.
+    pub fn next_index(&self) -> usize {
+        self.builder.len()
+    }
+
     pub fn element_begin<W>(&mut self, writer: &mut CodeWriter<W>) -> Result<(), Error>
     where
         W: Write,
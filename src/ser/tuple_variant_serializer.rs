@@ -24,12 +24,16 @@ where
         variant: Cow<'static, str>,
         len: usize,
     ) -> Result<Self, Error> {
+        serializer.check_builtin_variant(&name, &variant, len == 1)?;
+        let path = serializer.type_path(name.clone());
         let writer = &mut serializer.writer;
-        writer.write(&name)?;
+        writer.write(&path)?;
         writer.write("::")?;
         writer.write(&variant)?;
+        writer.instance_begin_marker(&name, Some(&variant))?;
 
-        let util = TupleUtil::begin(len, &mut serializer.writer)?;
+        let util = TupleUtil::begin(Some(len), &mut serializer.writer)?;
+        serializer.enter_type(name.clone(), Some(variant.clone()));
 
         Ok(Self {
             serializer,
@@ -51,21 +55,26 @@ where
     where
         T: Serialize,
     {
+        let index = self.util.next_index();
         self.util.element_begin(&mut self.serializer.writer)?;
-        let field_type = value.serialize(&mut *self.serializer)?;
+        let field_type = self.serializer.serialize_index_value(index, value)?;
         self.util
             .element_end(&mut self.serializer.writer, field_type)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.serializer.leave_type();
         let built = self.util.end(&mut self.serializer.writer).map_err(|msg| {
             msg.message_prepend(format!("Could not build struct '{}'", self.name))
         })?;
+        self.serializer.writer.instance_end_marker()?;
 
         let structure = Structured::Tuple(built);
         self.serializer
-            .enums_mut()
-            .merge(&self.name, &self.variant, structure)?;
-        Ok(FieldType::Enum(self.name.into()))
+            .merge_variant(self.name.clone(), self.variant, structure)?;
+        self.serializer.update_type_path(&self.name)?;
+        let field_type = FieldType::Enum(self.name.into());
+        self.serializer.end_compound(&field_type)?;
+        Ok(field_type)
     }
 }
@@ -0,0 +1,75 @@
+use crate::model::FieldType;
+
+/// A type that is used for the values at a path or in a field instead of the type that is inferred
+/// from them (see [`Serializer::with_type_hint_at()`](super::Serializer::with_type_hint_at) and
+/// [`Serializer::with_type_hint()`](super::Serializer::with_type_hint)).
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeHint {
+    /// An integer type, e.g. [`IntegerType::U16`]. The values must fit into this type.
+    Integer(IntegerType),
+    /// An array with the provided length, e.g. `[u8; 4]` instead of `&'static [u8]`. The sequences
+    /// must have this length. The type of the elements is inferred, unless it is hinted as well
+    /// (e.g. at `palette[]` for the elements of `palette`).
+    Array(usize),
+    /// An existing type, by path, e.g. `::ves_core::Frequency`. The values are written as usual, so
+    /// they must be valid expressions of this type (e.g. for a type alias).
+    Path(String),
+    /// A unit enum with the provided name that is generated from the strings, e.g. `Kind::Fire` for
+    /// `"fire"`. Every distinct string becomes a variant, whose name is the string in `PascalCase`,
+    /// made into a valid identifier (e.g. `_2x` for `"2x"`). Strings that result in the same variant
+    /// name result in an error. If there are more distinct strings than `max_variants`, they are
+    /// written as strings instead, which requires collecting the values first (see
+    /// [`Serializer::collect()`](super::Serializer::collect)) if any of them were written already.
+    StringEnum { name: String, max_variants: usize },
+}
+
+/// The integer type of a [`TypeHint::Integer`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntegerType {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+}
+
+impl IntegerType {
+    /// The type of the values with this integer type.
+    pub fn field_type(self) -> FieldType {
+        match self {
+            IntegerType::U8 => FieldType::U8,
+            IntegerType::U16 => FieldType::U16,
+            IntegerType::U32 => FieldType::U32,
+            IntegerType::U64 => FieldType::U64,
+            IntegerType::U128 => FieldType::U128,
+            IntegerType::I8 => FieldType::I8,
+            IntegerType::I16 => FieldType::I16,
+            IntegerType::I32 => FieldType::I32,
+            IntegerType::I64 => FieldType::I64,
+            IntegerType::I128 => FieldType::I128,
+        }
+    }
+
+    /// The integer type of the provided type, if it is an integer type.
+    pub(crate) fn of(field_type: &FieldType) -> Option<Self> {
+        Some(match field_type {
+            FieldType::U8 => IntegerType::U8,
+            FieldType::U16 => IntegerType::U16,
+            FieldType::U32 => IntegerType::U32,
+            FieldType::U64 => IntegerType::U64,
+            FieldType::U128 => IntegerType::U128,
+            FieldType::I8 => IntegerType::I8,
+            FieldType::I16 => IntegerType::I16,
+            FieldType::I32 => IntegerType::I32,
+            FieldType::I64 => IntegerType::I64,
+            FieldType::I128 => IntegerType::I128,
+            FieldType::Int(range) => return Self::of(&range.field_type()),
+            _ => return None,
+        })
+    }
+}
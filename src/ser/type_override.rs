@@ -0,0 +1,239 @@
+use super::ident;
+use super::inference;
+use super::CodeWriter;
+use super::Error;
+use crate::model::Structured;
+use std::collections::HashMap;
+use std::io::Write;
+
+/// An existing type that is used instead of generating a type (see
+/// [`Serializer::with_type_override()`](super::Serializer::with_type_override)).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeOverride {
+    path: String,
+    constructor: Option<String>,
+    type_template: Option<String>,
+    shape: Option<Shape>,
+}
+
+/// The fields or variants that a type must have for a built-in override to apply to it, since the
+/// data may contain a type of its own with the same name as a type of the standard library.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Shape {
+    /// A struct with exactly these fields, or a unit struct if there are none.
+    Struct(&'static [&'static str]),
+    /// An enum of which every variant is one of these newtype variants.
+    Enum(&'static [&'static str]),
+}
+
+impl Shape {
+    /// Determines whether the provided structure of a struct instance has this shape.
+    pub fn matches_struct(&self, structure: &Structured) -> bool {
+        match (self, structure) {
+            (Shape::Struct(fields), Structured::Struct(structure)) => {
+                structure.fields().len() == fields.len()
+                    && fields
+                        .iter()
+                        .all(|field| structure.fields().contains_key(*field))
+            }
+            (Shape::Struct(fields), Structured::Unit) => fields.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Determines whether the provided variant has this shape.
+    ///
+    /// # Arguments
+    ///
+    /// * `variant`: The name of the variant.
+    /// * `newtype`: Whether the variant is a newtype variant (or a tuple variant with one element).
+    pub fn matches_variant(&self, variant: &str, newtype: bool) -> bool {
+        match self {
+            Shape::Enum(variants) => newtype && variants.contains(&variant),
+            Shape::Struct(_) => false,
+        }
+    }
+}
+
+impl TypeOverride {
+    /// Creates an override with the path of the existing type, e.g. `::ves_core::Color`.
+    ///
+    /// Values are written as literals with this path, e.g. `::ves_core::Color { r: 1, g: 2, b: 3 }`
+    /// or `::ves_core::Direction::Up`.
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            constructor: None,
+            type_template: None,
+            shape: None,
+        }
+    }
+
+    /// Sets a constructor expression, with which the values of a struct are written instead of a
+    /// literal, e.g. `::ves_core::Color::rgb({r}, {g}, {b})`.
+    ///
+    /// Every `{name}` is replaced by the value of the field with that name, or by the value of the
+    /// element with that index for tuple structs (e.g. `Fixed::from_bits({0})`). `{{` and `}}` are
+    /// written as `{` and `}`, respectively.
+    pub fn with_constructor(mut self, template: impl Into<String>) -> Self {
+        self.constructor = Some(template.into());
+        self
+    }
+
+    /// Sets the type with which fields of this type are declared, if it differs from the path, e.g.
+    /// `::core::ops::Range<{start}>`.
+    ///
+    /// Every `{name}` is replaced by the type of the field with that name, by the type of the element
+    /// with that index for tuple structs, or by the type of the (single) element of the variant with
+    /// that name for enums. Variants that do not occur in the data have the type `()`.
+    pub fn with_type(mut self, template: impl Into<String>) -> Self {
+        self.type_template = Some(template.into());
+        self
+    }
+
+    /// Only applies the override to types with the provided shape.
+    pub(crate) fn with_shape(mut self, shape: Shape) -> Self {
+        self.shape = Some(shape);
+        self
+    }
+
+    pub(crate) fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub(crate) fn constructor(&self) -> Option<&str> {
+        self.constructor.as_deref()
+    }
+
+    pub(crate) fn type_template(&self) -> Option<&str> {
+        self.type_template.as_deref()
+    }
+
+    pub(crate) fn shape(&self) -> Option<&Shape> {
+        self.shape.as_ref()
+    }
+}
+
+/// Writes a struct value through the constructor expression of a [`TypeOverride`].
+///
+/// The code of the struct literal is captured and discarded, unless the override turns out not to
+/// apply (see [`Shape`]). The values of the fields are captured separately as well, and are
+/// inserted into the constructor expression.
+pub struct ConstructorCall {
+    template: String,
+    values: HashMap<String, String>,
+}
+
+impl ConstructorCall {
+    pub fn begin<W>(template: &str, writer: &mut CodeWriter<W>) -> Self {
+        writer.begin_capture();
+        Self {
+            template: template.to_string(),
+            values: HashMap::new(),
+        }
+    }
+
+    /// Begins the value of a field or element.
+    pub fn value_begin<W>(&mut self, writer: &mut CodeWriter<W>) {
+        writer.begin_capture();
+    }
+
+    /// Ends the value of a field or element.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the field (as provided by Serde) or the index of the element.
+    /// * `marked`: Whether the value is that of a field, which is marked for inference (see
+    ///   [`inference`]).
+    /// * `writer`: The writer.
+    pub fn value_end<W>(
+        &mut self,
+        name: &str,
+        marked: bool,
+        writer: &mut CodeWriter<W>,
+    ) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let mut code = writer.end_capture();
+        writer.write(&code)?;
+        if marked && writer.markers {
+            let field_begin = inference::field_begin(&ident::sanitize(name));
+            code = format!("{field_begin}{code}{}", inference::FIELD_END);
+        }
+        self.values.insert(name.to_string(), code);
+        Ok(())
+    }
+
+    /// Passes `None` for a field that is skipped, which is optional since optional fields are
+    /// inferred.
+    pub fn skip(&mut self, name: &str) {
+        self.values.insert(name.to_string(), String::from("None"));
+    }
+
+    /// Ends the call without writing the constructor expression, and returns the code of the struct
+    /// literal instead.
+    pub fn into_literal<W>(self, writer: &mut CodeWriter<W>) -> String {
+        writer.end_capture()
+    }
+
+    /// Writes the constructor expression.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the struct, for the markers and for error reporting.
+    /// * `writer`: The writer.
+    pub fn end<W>(self, name: &str, writer: &mut CodeWriter<W>) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        writer.end_capture();
+        let code = fill(&self.template, &self.values, None).map_err(|msg| {
+            msg.message_prepend(format!("Invalid constructor for struct '{name}'."))
+        })?;
+        writer.instance_begin_marker(name, None)?;
+        writer.write(&code)?;
+        writer.call_end_marker()?;
+        Ok(())
+    }
+}
+
+/// Fills the placeholders in the provided template.
+///
+/// # Arguments
+///
+/// * `template`: The template, in which `{name}` is replaced by the value with that name and `{{`
+///   and `}}` are replaced by `{` and `}`, respectively.
+/// * `values`: The values, by name.
+/// * `default`: The value for names that are not in `values`. If `None`, such names are an error.
+pub fn fill(
+    template: &str,
+    values: &HashMap<String, String>,
+    default: Option<&str>,
+) -> Result<String, Error> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(index) = rest.find(['{', '}']) {
+        out.push_str(&rest[..index]);
+        let tail = &rest[index..];
+        if let Some(remainder) = tail.strip_prefix("{{").or_else(|| tail.strip_prefix("}}")) {
+            out.push_str(&tail[..1]);
+            rest = remainder;
+            continue;
+        }
+
+        let (placeholder, remainder) = tail
+            .strip_prefix('{')
+            .and_then(|tail| tail.split_once('}'))
+            .ok_or_else(|| Error::message("Unbalanced braces."))?;
+        let value = values
+            .get(placeholder.trim())
+            .map(String::as_str)
+            .or(default)
+            .ok_or_else(|| Error::message(format!("Unknown field '{placeholder}'.")))?;
+        out.push_str(value);
+        rest = remainder;
+    }
+    out.push_str(rest);
+    Ok(out)
+}